@@ -0,0 +1,235 @@
+/// The size of an IPv4 header without options.
+const IP_HEADER_LEN: usize = 20;
+
+/// The largest number of header bytes before the payload, which is an IPv4 header with the most
+/// options and a UDP header.
+pub const MAX_HEADER_LEN: usize = 60 + UDP_HEADER_LEN;
+
+/// The size of a UDP header.
+const UDP_HEADER_LEN: usize = 8;
+
+/// The IP protocol number of UDP.
+const UDP_PROTOCOL: u8 = 17;
+
+/// Add up bytes as big endian 16-bit words for an Internet checksum. An odd last byte is padded
+/// with a zero.
+///
+/// # Arguments
+///
+/// * `sum` - The sum of the bytes before these.
+/// * `bytes` - The bytes to add.
+///
+/// # Returns
+///
+/// The sum, which is not folded to 16 bits.
+fn add(sum: u32, bytes: &[u8]) -> u32 {
+    bytes.chunks(2).fold(sum, |acc, word| {
+        acc + (u32::from(word[0]) << 8 | u32::from(*word.get(1).unwrap_or(&0)))
+    })
+}
+
+/// Fold a sum into the one's complement 16-bit checksum.
+///
+/// # Arguments
+///
+/// * `sum` - The sum of the words.
+///
+/// # Returns
+///
+/// The checksum.
+fn fold(mut sum: u32) -> u16 {
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Compute the checksum of an IPv4 header.
+///
+/// # Arguments
+///
+/// * `header` - The header, where the checksum field is zero.
+///
+/// # Returns
+///
+/// The checksum.
+pub fn header_checksum(header: &[u8]) -> u16 {
+    fold(add(0, header))
+}
+
+/// Compute the UDP checksum of a datagram, which also covers a pseudo header with the addresses
+/// from the IP header.
+///
+/// # Arguments
+///
+/// * `src_ip` - The source IP address.
+/// * `dest_ip` - The destination IP address.
+/// * `datagram` - The UDP header and payload, where the checksum field is zero.
+///
+/// # Returns
+///
+/// The checksum, where 0 is sent as `0xFFFF` because 0 means no checksum.
+fn udp_checksum(src_ip: &[u8], dest_ip: &[u8], datagram: &[u8]) -> u16 {
+    let mut sum = add(add(0, src_ip), dest_ip);
+    sum += u32::from(UDP_PROTOCOL) + datagram.len() as u32;
+    match fold(add(sum, datagram)) {
+        0 => 0xFFFF,
+        c => c
+    }
+}
+
+/// Check the IPv4 and UDP checksums of a packet and remove its headers.
+///
+/// # Arguments
+///
+/// * `packet` - The received bytes, which start with the IPv4 header.
+///
+/// # Returns
+///
+/// The UDP payload, or an error message that describes the bad header or checksum.
+pub fn strip(packet: &[u8]) -> Result<&[u8], String> {
+    if packet.len() < IP_HEADER_LEN || packet[0] >> 4 != 4 {
+        return Err(format!("Received {} bytes without an IPv4 header", packet.len()));
+    }
+    let ip_len = usize::from(packet[0] & 0x0F) * 4;
+    if ip_len < IP_HEADER_LEN || packet.len() < ip_len + UDP_HEADER_LEN {
+        return Err(format!("Received {} bytes, which is too short for the headers",
+            packet.len()));
+    }
+    if packet[9] != UDP_PROTOCOL {
+        return Err(format!("IP protocol is {}, expected UDP", packet[9]));
+    }
+    let got = u16::from(packet[10]) << 8 | u16::from(packet[11]);
+    let mut header = packet[..ip_len].to_vec();
+    header[10] = 0;
+    header[11] = 0;
+    let computed = fold(add(0, &header));
+    if got != computed {
+        return Err(format!("IP checksum mismatch: got 0x{:04X}, computed 0x{:04X}", got,
+            computed));
+    }
+    let datagram = &packet[ip_len..];
+    let udp_len = usize::from(datagram[4]) << 8 | usize::from(datagram[5]);
+    if udp_len != datagram.len() {
+        return Err(format!("UDP length is {}, but {} bytes follow the IP header", udp_len,
+            datagram.len()));
+    }
+    // A checksum of 0 means that the sender did not compute one
+    let got = u16::from(datagram[6]) << 8 | u16::from(datagram[7]);
+    if got != 0 {
+        let mut zeroed = datagram.to_vec();
+        zeroed[6] = 0;
+        zeroed[7] = 0;
+        let computed = udp_checksum(&packet[12..16], &packet[16..20], &zeroed);
+        if got != computed {
+            return Err(format!("UDP checksum mismatch: got 0x{:04X}, computed 0x{:04X}", got,
+                computed));
+        }
+    }
+    Ok(&datagram[UDP_HEADER_LEN..])
+}
+
+/// Get the destination IP address and port from the headers of a packet that passed `strip`.
+///
+/// # Arguments
+///
+/// * `packet` - The received bytes, which start with the IPv4 header.
+///
+/// # Returns
+///
+/// The destination IP address and port.
+pub fn dest(packet: &[u8]) -> (u32, u16) {
+    let ip_len = usize::from(packet[0] & 0x0F) * 4;
+    let ip = u32::from_be_bytes([packet[16], packet[17], packet[18], packet[19]]);
+    (ip, u16::from_be_bytes([packet[ip_len + 2], packet[ip_len + 3]]))
+}
+
+/// Add IPv4 and UDP headers with correct checksums to a payload, like an FPGA that sends the
+/// whole packet for inspection.
+///
+/// # Arguments
+///
+/// * `src` - The source IP address and port.
+/// * `dest` - The destination IP address and port.
+/// * `payload` - The UDP payload.
+///
+/// # Returns
+///
+/// The packet.
+pub fn wrap(src: (u32, u16), dest: (u32, u16), payload: &[u8]) -> Vec<u8> {
+    let udp_len = (UDP_HEADER_LEN + payload.len()) as u16;
+    let ip_len = IP_HEADER_LEN as u16 + udp_len;
+    let mut packet = vec![0x45, 0, (ip_len >> 8) as u8, ip_len as u8, 0, 0, 0x40, 0, 64,
+        UDP_PROTOCOL, 0, 0];
+    packet.extend_from_slice(&src.0.to_be_bytes());
+    packet.extend_from_slice(&dest.0.to_be_bytes());
+    let checksum = fold(add(0, &packet));
+    packet[10] = (checksum >> 8) as u8;
+    packet[11] = checksum as u8;
+    packet.extend_from_slice(&src.1.to_be_bytes());
+    packet.extend_from_slice(&dest.1.to_be_bytes());
+    packet.extend_from_slice(&udp_len.to_be_bytes());
+    packet.extend_from_slice(&[0, 0]);
+    packet.extend_from_slice(payload);
+    let checksum = udp_checksum(&packet[12..16], &packet[16..20], &packet[IP_HEADER_LEN..]);
+    packet[IP_HEADER_LEN + 6] = (checksum >> 8) as u8;
+    packet[IP_HEADER_LEN + 7] = checksum as u8;
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A packet from 10.0.0.2:4096 to 10.0.0.1:5555 with a payload of 1, 2, 3, 4, whose checksums
+    /// were computed by hand.
+    const PACKET: &[u8] = &[
+        0x45, 0x00, 0x00, 0x20, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x26, 0xCB,
+        0x0A, 0x00, 0x00, 0x02, 0x0A, 0x00, 0x00, 0x01,
+        0x10, 0x00, 0x15, 0xB3, 0x00, 0x0C, 0xC2, 0x1A,
+        0x01, 0x02, 0x03, 0x04];
+
+    /// The header checksum of a well known example header.
+    #[test]
+    fn known_header() {
+        let mut header = vec![0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00,
+            0x00, 0xC0, 0xA8, 0x00, 0x01, 0xC0, 0xA8, 0x00, 0xC7];
+        assert_eq!(header_checksum(&header), 0xB861);
+        // A header with its checksum sums to zero
+        header[10] = 0xB8;
+        header[11] = 0x61;
+        assert_eq!(header_checksum(&header), 0);
+    }
+
+    /// A packet with correct checksums is stripped to its payload, and is the one that `wrap`
+    /// makes.
+    #[test]
+    fn known_good() {
+        assert_eq!(strip(PACKET), Ok(&[1, 2, 3, 4][..]));
+        assert_eq!(dest(PACKET), (0x0A00_0001, 5555));
+        assert_eq!(wrap((0x0A00_0002, 4096), (0x0A00_0001, 5555), &[1, 2, 3, 4]), PACKET);
+    }
+
+    /// A corrupted IP header or payload fails its checksum.
+    #[test]
+    fn corrupted() {
+        let mut packet = PACKET.to_vec();
+        packet[15] = 0x03;
+        assert_eq!(strip(&packet), Err("IP checksum mismatch: got 0x26CB, computed 0x26CA"
+            .to_string()));
+        let mut packet = PACKET.to_vec();
+        packet[31] = 0x05;
+        assert_eq!(strip(&packet), Err("UDP checksum mismatch: got 0xC21A, computed 0xC219"
+            .to_string()));
+    }
+
+    /// A UDP checksum of zero means that it was not computed, so the payload is not checked.
+    #[test]
+    fn udp_not_computed() {
+        let mut packet = PACKET.to_vec();
+        packet[26] = 0;
+        packet[27] = 0;
+        packet[31] = 0x05;
+        assert_eq!(strip(&packet), Ok(&[1, 2, 3, 5][..]));
+    }
+}
@@ -0,0 +1,33 @@
+use ansi_term::Style;
+use atty::Stream;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Indicates whether styles are applied to the output.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enable or disable styled output. Styles are never used when standard output is not a terminal,
+/// so that logs and pipes get plain text.
+///
+/// # Arguments
+///
+/// * `enabled` - Whether styles should be used when standard output is a terminal.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled && atty::is(Stream::Stdout), Ordering::Relaxed);
+}
+
+/// Get a style to print with, which is plain when styled output is disabled.
+///
+/// # Arguments
+///
+/// * `style` - The style to use when styled output is enabled.
+///
+/// # Returns
+///
+/// The style to print with.
+pub fn style(style: Style) -> Style {
+    if ENABLED.load(Ordering::Relaxed) {
+        style
+    } else {
+        Style::new()
+    }
+}
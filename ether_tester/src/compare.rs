@@ -0,0 +1,271 @@
+use ansi_term::Style;
+use std::fmt;
+use super::color;
+use super::params::CompareMode;
+
+/// The number of bytes shown on each side of the first differing byte.
+const WINDOW: usize = 4;
+
+/// A difference between the expected and actual payloads.
+pub struct Mismatch {
+    /// The offset of the first byte that differs.
+    pub offset: usize,
+
+    /// The expected value of the first byte that differs, or `None` if the actual payload is
+    /// longer than expected.
+    pub expected: Option<u8>,
+
+    /// The actual value of the first byte that differs, or `None` if the actual payload is
+    /// shorter than expected.
+    pub actual: Option<u8>,
+
+    /// The total number of bytes that differ, where every missing or extra byte counts as one.
+    pub count: usize
+}
+
+/// The offset of the first differing byte with its expected and actual values, which is the same
+/// for failures that have the same cause.
+pub type Signature = (usize, Option<u8>, Option<u8>);
+
+impl Mismatch {
+    /// Get the signature of the mismatch.
+    ///
+    /// # Returns
+    ///
+    /// The signature.
+    pub fn signature(&self) -> Signature {
+        (self.offset, self.expected, self.actual)
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.expected, self.actual) {
+            (Some(x), Some(y)) => write!(f, "Error in byte {}: {} != {}", self.offset, x, y),
+            (Some(x), None) =>
+                write!(f, "Error in byte {}: Expected {:#04X}, got none", self.offset, x),
+            (None, Some(y)) =>
+                write!(f, "Error in byte {}: Expected none, got {:#04X}", self.offset, y),
+            (None, None) => write!(f, "Error in byte {}", self.offset)
+        }
+    }
+}
+
+/// Describe a mismatch signature.
+///
+/// # Arguments
+///
+/// * `signature` - The signature.
+///
+/// # Returns
+///
+/// The description, where a missing byte is `none`.
+pub fn signature_string(signature: Signature) -> String {
+    let byte = |b: Option<u8>| b.map_or("none".to_string(), |b| format!("{:#04X}", b));
+    format!("{{offset:{}, exp:{}, got:{}}}", signature.0, byte(signature.1), byte(signature.2))
+}
+
+/// Compares two vectors based on length and content, and describes where they differ. Only the
+/// bytes in the slices are compared, so the actual values must be cut to the received size first.
+///
+/// # Arguments
+///
+/// * `xs` - The expected values.
+/// * `ys` - The actual values.
+/// * `tolerance` - The largest difference between two bytes that still counts as a match.
+///
+/// # Returns
+///
+/// Nothing on success and the mismatch on a failed comparison.
+pub fn verbose_compare(xs: &[u8], ys: &[u8], tolerance: u8) -> Result<(), Mismatch> {
+    let len = xs.len().max(ys.len());
+    let mut mismatch: Option<Mismatch> = None;
+    for i in 0..len {
+        let (x, y) = (xs.get(i).cloned(), ys.get(i).cloned());
+        let matched = match (x, y) {
+            (Some(x), Some(y)) => x.abs_diff(y) <= tolerance,
+            _ => false
+        };
+        if matched {
+            continue;
+        }
+        match mismatch {
+            Some(ref mut m) => m.count += 1,
+            None => mismatch = Some(Mismatch { offset: i, expected: x, actual: y, count: 1 })
+        }
+    }
+    match mismatch {
+        Some(m) => Err(m),
+        None => Ok(())
+    }
+}
+
+/// Put the bytes of a payload in the order that a compare mode compares them in.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes.
+/// * `mode` - The compare mode.
+///
+/// # Returns
+///
+/// The bytes in order, which are sorted unless the mode is exact, and only the distinct values
+/// for the set mode.
+pub fn arrange(bytes: &[u8], mode: CompareMode) -> Vec<u8> {
+    let mut v = bytes.to_vec();
+    if mode != CompareMode::Exact {
+        v.sort_unstable();
+    }
+    if mode == CompareMode::Set {
+        v.dedup();
+    }
+    v
+}
+
+/// Get the name of a compare mode for a failure message.
+///
+/// # Arguments
+///
+/// * `mode` - The compare mode.
+///
+/// # Returns
+///
+/// The name.
+pub fn mode_name(mode: CompareMode) -> &'static str {
+    match mode {
+        CompareMode::Exact => "Exact",
+        CompareMode::Sorted => "Sorted",
+        CompareMode::Set => "Set"
+    }
+}
+
+/// Count the bits that differ between the bytes of two vectors. Bytes that are only in one of the
+/// vectors are left out.
+///
+/// # Arguments
+///
+/// * `xs` - The expected values.
+/// * `ys` - The actual values.
+///
+/// # Returns
+///
+/// The number of differing bits.
+pub fn bit_errors(xs: &[u8], ys: &[u8]) -> u64 {
+    xs.iter().zip(ys).map(|(&x, &y)| u64::from((x ^ y).count_ones())).sum()
+}
+
+/// Find the largest difference between the bytes of two vectors. Bytes that are only in one of the
+/// vectors are left out.
+///
+/// # Arguments
+///
+/// * `xs` - The expected values.
+/// * `ys` - The actual values.
+///
+/// # Returns
+///
+/// The largest absolute difference, or 0 if there are no bytes to compare.
+pub fn max_deviation(xs: &[u8], ys: &[u8]) -> u8 {
+    xs.iter().zip(ys).map(|(&x, &y)| x.abs_diff(y)).max().unwrap_or(0)
+}
+
+/// Format the expected and actual bytes around the first differing byte, which shows an alignment
+/// bug without a full dump. The first differing byte is in brackets, and every byte that differs
+/// is bold and underlined. A byte that is missing from one side is shown as `--`.
+///
+/// # Arguments
+///
+/// * `xs` - The expected values.
+/// * `ys` - The actual values.
+/// * `offset` - The offset of the first byte that differs.
+/// * `tolerance` - The largest difference between two bytes that still counts as a match.
+///
+/// # Returns
+///
+/// The formatted window with one line for the expected bytes and one for the actual bytes.
+pub fn window(xs: &[u8], ys: &[u8], offset: usize, tolerance: u8) -> String {
+    let marked = color::style(Style::new().bold().underline());
+    let start = offset.saturating_sub(WINDOW);
+    let end = (offset + WINDOW + 1).min(xs.len().max(ys.len()));
+    let row = |bytes: &[u8], other: &[u8]| {
+        let cells: Vec<String> = (start..end).map(|i| {
+            let cell = bytes.get(i).map_or("--".to_string(), |b| format!("{:02X}", b));
+            let differs = match (bytes.get(i), other.get(i)) {
+                (Some(x), Some(y)) => x.abs_diff(*y) > tolerance,
+                _ => true
+            };
+            let cell = if differs { marked.paint(cell).to_string() } else { cell };
+            if i == offset { format!("[{}]", cell) } else { cell }
+        }).collect();
+        cells.join(" ")
+    };
+    format!("Bytes {} to {}:\n  Expected {}\n  Actual   {}", start, end - 1,
+        row(xs, ys), row(ys, xs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The bytes 1 to 10.
+    const XS: &[u8] = &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+
+    /// A short packet fails at its end, even when the rest of the receive buffer still holds the
+    /// right bytes from an earlier packet.
+    #[test]
+    fn compare_short_packet() {
+        // The buffer holds a whole packet, but only the first 6 bytes were received this time
+        let buf = XS.to_vec();
+        let size = 6;
+        let mismatch = verbose_compare(XS, &buf[..size], 0).unwrap_err();
+        assert_eq!((mismatch.offset, mismatch.expected, mismatch.actual, mismatch.count),
+            (6, Some(7), None, 4));
+    }
+
+    /// A long packet fails at the first extra byte, which counts as a mismatch for each extra
+    /// byte.
+    #[test]
+    fn compare_long_packet() {
+        let ys = [XS, &[11, 12]].concat();
+        let mismatch = verbose_compare(XS, &ys, 0).unwrap_err();
+        assert_eq!((mismatch.offset, mismatch.expected, mismatch.actual, mismatch.count),
+            (10, None, Some(11), 2));
+        assert!(verbose_compare(XS, XS, 0).is_ok());
+    }
+
+    /// A window at the first byte only has bytes after the mismatch.
+    #[test]
+    fn window_at_start() {
+        color::set_enabled(false);
+        let ys = [0xFF, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        assert_eq!(window(XS, &ys, 0, 0),
+            concat!("Bytes 0 to 4:\n",
+                "  Expected [01] 02 03 04 05\n",
+                "  Actual   [FF] 02 03 04 05"));
+    }
+
+    /// A window at the last byte only has bytes before the mismatch.
+    #[test]
+    fn window_at_end() {
+        color::set_enabled(false);
+        let ys = [1, 2, 3, 4, 5, 6, 7, 8, 9, 0xFF];
+        assert_eq!(window(XS, &ys, 9, 0),
+            concat!("Bytes 5 to 9:\n",
+                "  Expected 06 07 08 09 [0A]\n",
+                "  Actual   06 07 08 09 [FF]"));
+    }
+
+    /// The bytes that are missing from the shorter side are shown as gaps.
+    #[test]
+    fn window_with_different_lengths() {
+        color::set_enabled(false);
+        assert_eq!(window(XS, &XS[..6], 6, 0),
+            concat!("Bytes 2 to 9:\n",
+                "  Expected 03 04 05 06 [07] 08 09 0A\n",
+                "  Actual   03 04 05 06 [--] -- -- --"));
+        assert_eq!(window(&XS[..3], XS, 3, 0),
+            concat!("Bytes 0 to 7:\n",
+                "  Expected 01 02 03 [--] -- -- -- --\n",
+                "  Actual   01 02 03 [04] 05 06 07 08"));
+    }
+}
@@ -0,0 +1,24 @@
+use std::io;
+use std::net::{SocketAddrV4, UdpSocket};
+
+/// Send a command datagram to the control port of firmware that takes commands over UDP as well
+/// as the serial frame. Each command is sent from a new socket on any free port, so the firmware
+/// must not expect replies to reach the tester.
+///
+/// # Arguments
+///
+/// * `addr` - The IP address and port of the control port.
+/// * `command` - The bytes of the command.
+///
+/// # Returns
+///
+/// Nothing on success or an error.
+pub fn send(addr: SocketAddrV4, command: &[u8]) -> io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let sent = socket.send_to(command, addr)?;
+    if sent != command.len() {
+        return Err(io::Error::new(io::ErrorKind::WriteZero,
+            format!("Sent {} of {} command bytes", sent, command.len())));
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+/// The reflected polynomial of the CRC-32 that Ethernet uses.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// The CRC of every byte value, which lets the CRC be updated a byte at a time.
+const TABLE: [u32; 256] = table();
+
+/// Build the table of the CRC of every byte value.
+///
+/// # Returns
+///
+/// The table.
+const fn table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { crc >> 1 ^ POLYNOMIAL } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Compute the CRC-32 of some bytes, which is the same CRC as the frame check sequence of an
+/// Ethernet frame.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes.
+///
+/// # Returns
+///
+/// The CRC.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &b| TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ crc >> 8)
+}
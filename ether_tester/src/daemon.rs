@@ -0,0 +1,104 @@
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+
+/// The byte that separates the output of a request from its status, which never appears in the
+/// text output.
+const STATUS_MARK: u8 = 0;
+
+/// Listen for requests on a Unix socket. A socket file left behind by a daemon that stopped is
+/// removed first, but a daemon that is still running is not disturbed.
+///
+/// # Arguments
+///
+/// * `path` - The path of the socket.
+///
+/// # Returns
+///
+/// The listener or an error message.
+pub fn listen(path: &str) -> Result<UnixListener, String> {
+    let is_socket = fs::metadata(path).map(|m| m.file_type().is_socket()).unwrap_or(false);
+    if is_socket {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("{}: Another daemon is already listening", path));
+        }
+        fs::remove_file(path).map_err(|err| format!("{}: {}", path, err))?;
+    }
+    UnixListener::bind(path).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Read the arguments of a request, which are a single line.
+///
+/// # Arguments
+///
+/// * `stream` - The connection from the client.
+///
+/// # Returns
+///
+/// The line without its end or an error.
+pub fn read_request(stream: &UnixStream) -> io::Result<String> {
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// End the reply to a request with its status, after the output that was written to the
+/// connection.
+///
+/// # Arguments
+///
+/// * `stream` - The connection to the client.
+/// * `passed` - An indicator of whether the request ran and passed.
+///
+/// # Returns
+///
+/// Nothing on success or an error.
+pub fn send_status(mut stream: &UnixStream, passed: bool) -> io::Result<()> {
+    stream.write_all(&[STATUS_MARK, if passed { b'0' } else { b'1' }])
+}
+
+/// Send a request to a daemon and copy its output as it arrives.
+///
+/// # Arguments
+///
+/// * `path` - The path of the socket of the daemon.
+/// * `args` - The arguments of the request.
+/// * `out` - Where to copy the output.
+///
+/// # Returns
+///
+/// An indicator of whether the request ran and passed, or an error message.
+pub fn request(path: &str, args: &[String], out: &mut dyn Write) -> Result<bool, String> {
+    let mut stream = UnixStream::connect(path).map_err(|err| format!("{}: {}", path, err))?;
+    let line = format!("{}\n", args.join(" "));
+    stream.write_all(line.as_bytes()).map_err(|err| err.to_string())?;
+    let mut buf = [0u8; 4096];
+    let mut marked = false;
+    loop {
+        let n = stream.read(&mut buf).map_err(|err| err.to_string())?;
+        if n == 0 {
+            return Err("The daemon stopped before the request finished".to_string());
+        }
+        let mut bytes = &buf[..n];
+        if !marked {
+            match bytes.iter().position(|&b| b == STATUS_MARK) {
+                Some(i) => {
+                    out.write_all(&bytes[..i]).map_err(|err| err.to_string())?;
+                    marked = true;
+                    bytes = &bytes[i + 1..];
+                },
+                None => {
+                    out.write_all(bytes).map_err(|err| err.to_string())?;
+                    continue;
+                }
+            }
+        }
+        // The status may come in a read of its own after the mark
+        if let Some(&status) = bytes.first() {
+            let _ = out.flush();
+            return Ok(status == b'0');
+        }
+    }
+}
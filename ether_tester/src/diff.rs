@@ -0,0 +1,209 @@
+use ansi_term::{Colour, Style};
+use super::color;
+
+/// The number of bytes shown on each row of a diff.
+const ROW_BYTES: usize = 16;
+
+/// The maximum number of cells in the longest common subsequence table. Larger differences are
+/// shown as a full replacement instead, which keeps the memory use bounded for large payloads.
+const MAX_TABLE_CELLS: usize = 1 << 24;
+
+/// A single step of a diff.
+#[derive(Clone, Copy, PartialEq)]
+enum Edit {
+    /// The byte is in both sequences.
+    Keep(u8),
+
+    /// The byte is expected but was not received.
+    Delete(u8),
+
+    /// The byte was received but is not expected.
+    Insert(u8)
+}
+
+/// Format a diff of the expected and actual bytes. Each row starts with ` ` for bytes in both,
+/// `-` for expected bytes that are missing, or `+` for received bytes that are extra, followed by
+/// the offset of the first byte in its own sequence.
+///
+/// # Arguments
+///
+/// * `xs` - The expected values.
+/// * `ys` - The actual values.
+///
+/// # Returns
+///
+/// The formatted diff with one row per line.
+pub fn format(xs: &[u8], ys: &[u8]) -> String {
+    let keep = Style::new();
+    let delete = color::style(Style::new().fg(Colour::Red));
+    let insert = color::style(Style::new().fg(Colour::Green));
+    let edits = diff(xs, ys);
+    let mut rows = vec![];
+    let (mut x_offset, mut y_offset) = (0, 0);
+    let mut i = 0;
+    while i < edits.len() {
+        // Collect the run of edits of the same kind
+        let kind = std::mem::discriminant(&edits[i]);
+        let run: Vec<Edit> = edits[i..].iter()
+            .take_while(|e| std::mem::discriminant(*e) == kind)
+            .cloned()
+            .collect();
+        i += run.len();
+        for (j, chunk) in run.chunks(ROW_BYTES).enumerate() {
+            let (sign, style, offset) = match chunk[0] {
+                Edit::Keep(_) => (' ', keep, x_offset),
+                Edit::Delete(_) => ('-', delete, x_offset),
+                Edit::Insert(_) => ('+', insert, y_offset)
+            };
+            // Long runs of matching bytes are not interesting, so only their ends are shown
+            let num_chunks = run.len().div_ceil(ROW_BYTES);
+            let hidden = sign == ' ' && j > 0 && j + 1 < num_chunks;
+            if !hidden {
+                let hex: Vec<String> = chunk.iter().map(|e| match *e {
+                    Edit::Keep(b) | Edit::Delete(b) | Edit::Insert(b) => format!("{:02X}", b)
+                }).collect();
+                rows.push(style.paint(format!("{} {:04X} | {}", sign, offset, hex.join(" ")))
+                    .to_string());
+            } else if j == 1 {
+                rows.push("  ...".to_string());
+            }
+            match chunk[0] {
+                Edit::Keep(_) => { x_offset += chunk.len(); y_offset += chunk.len(); },
+                Edit::Delete(_) => x_offset += chunk.len(),
+                Edit::Insert(_) => y_offset += chunk.len()
+            }
+        }
+    }
+    rows.join("\n")
+}
+
+/// Compute the edits that turn the expected bytes into the actual bytes using the longest common
+/// subsequence. The common prefix and suffix are removed first, so a single dropped or duplicated
+/// byte is cheap to find even in a large payload.
+///
+/// # Arguments
+///
+/// * `xs` - The expected values.
+/// * `ys` - The actual values.
+///
+/// # Returns
+///
+/// The edits in order.
+fn diff(xs: &[u8], ys: &[u8]) -> Vec<Edit> {
+    let prefix = xs.iter().zip(ys).take_while(|&(x, y)| x == y).count();
+    let suffix = xs[prefix..].iter().rev().zip(ys[prefix..].iter().rev())
+        .take_while(|&(x, y)| x == y)
+        .count();
+    let xm = &xs[prefix..xs.len() - suffix];
+    let ym = &ys[prefix..ys.len() - suffix];
+    let mut edits: Vec<Edit> = xs[..prefix].iter().map(|&b| Edit::Keep(b)).collect();
+    if (xm.len() + 1) * (ym.len() + 1) > MAX_TABLE_CELLS {
+        edits.extend(xm.iter().map(|&b| Edit::Delete(b)));
+        edits.extend(ym.iter().map(|&b| Edit::Insert(b)));
+    } else {
+        // Entry `(i, j)` is the length of the longest common subsequence of `xm[i..]` and `ym[j..]`
+        let w = ym.len() + 1;
+        let mut table = vec![0u32; (xm.len() + 1) * w];
+        for i in (0..xm.len()).rev() {
+            for j in (0..ym.len()).rev() {
+                table[i * w + j] = if xm[i] == ym[j] {
+                    table[(i + 1) * w + j + 1] + 1
+                } else {
+                    table[(i + 1) * w + j].max(table[i * w + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < xm.len() || j < ym.len() {
+            if i < xm.len() && j < ym.len() && xm[i] == ym[j] {
+                edits.push(Edit::Keep(xm[i]));
+                i += 1;
+                j += 1;
+            } else if j == ym.len()
+                    || (i < xm.len() && table[(i + 1) * w + j] >= table[i * w + j + 1]) {
+                edits.push(Edit::Delete(xm[i]));
+                i += 1;
+            } else {
+                edits.push(Edit::Insert(ym[j]));
+                j += 1;
+            }
+        }
+    }
+    edits.extend(xs[xs.len() - suffix..].iter().map(|&b| Edit::Keep(b)));
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the bytes from 0 up to a length.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - The number of bytes.
+    ///
+    /// # Returns
+    ///
+    /// The bytes.
+    fn bytes(len: u8) -> Vec<u8> {
+        (0..len).collect()
+    }
+
+    /// An identical payload is kept whole, and only the ends of a long run are shown.
+    #[test]
+    fn identical() {
+        color::set_enabled(false);
+        assert_eq!(format(&bytes(20), &bytes(20)), concat!(
+            "  0000 | 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F\n",
+            "  0010 | 10 11 12 13"));
+        assert_eq!(format(&bytes(48), &bytes(48)), concat!(
+            "  0000 | 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F\n",
+            "  ...\n",
+            "  0020 | 20 21 22 23 24 25 26 27 28 29 2A 2B 2C 2D 2E 2F"));
+    }
+
+    /// A single wrong byte is a deletion of the expected byte and an insertion of the actual one.
+    #[test]
+    fn single_byte() {
+        color::set_enabled(false);
+        let mut ys = bytes(8);
+        ys[5] = 0xFF;
+        assert_eq!(format(&bytes(8), &ys), concat!(
+            "  0000 | 00 01 02 03 04\n",
+            "- 0005 | 05\n",
+            "+ 0005 | FF\n",
+            "  0006 | 06 07"));
+    }
+
+    /// A run of wrong bytes that is longer than a row is split over rows, with the offset of the
+    /// first byte of each row.
+    #[test]
+    fn run_across_rows() {
+        color::set_enabled(false);
+        let mut ys = bytes(40);
+        for b in &mut ys[10..30] {
+            *b ^= 0xFF;
+        }
+        assert_eq!(format(&bytes(40), &ys), concat!(
+            "  0000 | 00 01 02 03 04 05 06 07 08 09\n",
+            "- 000A | 0A 0B 0C 0D 0E 0F 10 11 12 13 14 15 16 17 18 19\n",
+            "- 001A | 1A 1B 1C 1D\n",
+            "+ 000A | F5 F4 F3 F2 F1 F0 EF EE ED EC EB EA E9 E8 E7 E6\n",
+            "+ 001A | E5 E4 E3 E2\n",
+            "  001E | 1E 1F 20 21 22 23 24 25 26 27"));
+    }
+
+    /// The bytes that are missing from a short packet are deletions, and the extra bytes of a
+    /// long packet are insertions.
+    #[test]
+    fn unequal_lengths() {
+        color::set_enabled(false);
+        assert_eq!(format(&bytes(10), &bytes(7)), concat!(
+            "  0000 | 00 01 02 03 04 05 06\n",
+            "- 0007 | 07 08 09"));
+        assert_eq!(format(&bytes(7), &bytes(10)), concat!(
+            "  0000 | 00 01 02 03 04 05 06\n",
+            "+ 0007 | 07 08 09"));
+    }
+}
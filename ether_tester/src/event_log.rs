@@ -0,0 +1,132 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use super::test_case::TestCase;
+
+/// Writes a line with the wall clock time for every test, so that the tests can be lined up with
+/// the readings of other instruments.
+pub struct EventLog {
+    /// The event file.
+    file: BufWriter<File>,
+
+    /// The name of the device that is written on every line, or an empty string when there is
+    /// only one device.
+    label: String
+}
+
+impl EventLog {
+    /// Create a new event file, replacing any existing file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    /// * `label` - The name of the device, or an empty string when there is only one device.
+    ///
+    /// # Returns
+    ///
+    /// The event log or an error message.
+    pub fn create(path: &str, label: &str) -> Result<EventLog, String> {
+        let file = File::create(path).map_err(|err| format!("{}: {}", path, err))?;
+        Ok(EventLog { file: BufWriter::new(file), label: label.to_string() })
+    }
+
+    /// Add the ARP warmup to the end of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The time that the warmup was sent.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error message on a failed write.
+    pub fn warmup(&mut self, time: SystemTime) -> Result<(), String> {
+        let line = format!("{}{} warmup", timestamp(time), self.device());
+        writeln!(self.file, "{}", line).map_err(|err| err.to_string())
+    }
+
+    /// Add a test to the end of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The time that the test started.
+    /// * `rep` - The number of the test.
+    /// * `test_case` - The test case.
+    /// * `outcome` - The outcome of the test, such as `pass`, `fail`, or `timeout`.
+    /// * `latency` - The time from the start of the test to the packet, if one arrived.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error message on a failed write.
+    pub fn rep(&mut self, time: SystemTime, rep: usize, test_case: &TestCase, outcome: &str,
+        latency: Option<Duration>) -> Result<(), String> {
+        let latency = latency.map_or("-".to_string(), |l| l.as_micros().to_string());
+        let line = format!("{}{} rep={} seed=0x{:02X} gen=0x{:02X} outcome={} latency_us={}",
+            timestamp(time), self.device(), rep, test_case.seed, test_case.gen, outcome, latency);
+        writeln!(self.file, "{}", line).map_err(|err| err.to_string())
+    }
+
+    /// Write out any buffered lines.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error message on a failed write.
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.file.flush().map_err(|err| err.to_string())
+    }
+
+    /// Format the device for a line.
+    ///
+    /// # Returns
+    ///
+    /// The device after a space, or an empty string when there is only one device.
+    fn device(&self) -> String {
+        if self.label.is_empty() {
+            String::new()
+        } else {
+            format!(" device={}", self.label)
+        }
+    }
+}
+
+/// Format a time as an ISO 8601 UTC timestamp with microseconds, such as
+/// `2024-03-05T02:13:45.123456Z`.
+///
+/// # Arguments
+///
+/// * `time` - The time.
+///
+/// # Returns
+///
+/// The timestamp.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_date((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z", year, month, day, secs_of_day / 3600,
+        secs_of_day / 60 % 60, secs_of_day % 60, since_epoch.subsec_micros())
+}
+
+/// Convert a number of days since 1970-01-01 to a date in the proleptic Gregorian calendar.
+///
+/// # Arguments
+///
+/// * `days` - The number of days since the epoch.
+///
+/// # Returns
+///
+/// The year, month, and day of the month.
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    // Count from 0000-03-01 so that the leap day is at the end of each 400 year era
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,316 @@
+use std::error;
+use std::fmt;
+use super::params::Endian;
+
+/// A field of the serial frame.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    /// The test device IP address.
+    SrcIp,
+
+    /// The test device port.
+    SrcPort,
+
+    /// The test device MAC address.
+    SrcMac,
+
+    /// The host IP address.
+    DestIp,
+
+    /// The host port.
+    DestPort,
+
+    /// The host MAC address.
+    DestMac,
+
+    /// The data seed.
+    Seed,
+
+    /// The data generator.
+    Gen,
+
+    /// The 16-bit data seed, which takes the place of the seed in word mode.
+    SeedWord,
+
+    /// The 16-bit data generator, which takes the place of the generator in word mode.
+    GenWord,
+
+    /// The packet sequence number.
+    Seq,
+
+    /// The number of bytes before the generated payload.
+    PrefixLen,
+
+    /// The number of bytes after the generated payload.
+    SuffixLen,
+
+    /// The modulus that the payload wraps at, where 0 means 256.
+    Wrap,
+
+    /// The number of generated payload bytes.
+    Len,
+
+    /// The time that the host sent the frame in microseconds, which the FPGA sends back.
+    Timestamp,
+
+    /// The bytes of the pattern that the FPGA repeats instead of generating a ramp, which has the
+    /// length of the pattern.
+    Pattern(usize)
+}
+
+impl Field {
+    /// Get the field with a name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the field.
+    ///
+    /// # Returns
+    ///
+    /// The field, or `None` if there is no field with the name.
+    pub fn from_name(name: &str) -> Option<Field> {
+        match name {
+            "src_ip" => Some(Field::SrcIp),
+            "src_port" => Some(Field::SrcPort),
+            "src_mac" => Some(Field::SrcMac),
+            "dest_ip" => Some(Field::DestIp),
+            "dest_port" => Some(Field::DestPort),
+            "dest_mac" => Some(Field::DestMac),
+            "seed" => Some(Field::Seed),
+            "gen" => Some(Field::Gen),
+            "seq" => Some(Field::Seq),
+            "prefix_len" => Some(Field::PrefixLen),
+            "suffix_len" => Some(Field::SuffixLen),
+            "wrap" => Some(Field::Wrap),
+            "len" => Some(Field::Len),
+            "timestamp" => Some(Field::Timestamp),
+            // The length is only known once the pattern is parsed
+            "pattern" => Some(Field::Pattern(0)),
+            _ => None
+        }
+    }
+
+    /// Get the number of bytes that the field takes in the frame.
+    ///
+    /// # Returns
+    ///
+    /// The size of the field.
+    pub fn bytes(self) -> usize {
+        match self {
+            Field::SrcIp | Field::DestIp | Field::Timestamp => 4,
+            Field::SrcPort | Field::DestPort | Field::Seq | Field::SeedWord | Field::GenWord
+                | Field::Len => 2,
+            Field::SrcMac | Field::DestMac => 6,
+            Field::Seed | Field::Gen | Field::PrefixLen | Field::SuffixLen | Field::Wrap => 1,
+            Field::Pattern(n) => n
+        }
+    }
+}
+
+/// An error from decoding a serial frame.
+#[derive(Debug, PartialEq)]
+pub enum EtherError {
+    /// The frame is not as long as its layout.
+    FrameLength {
+        /// The number of bytes in the frame.
+        len: usize,
+
+        /// The number of bytes in the layout.
+        expected: usize
+    }
+}
+
+impl fmt::Display for EtherError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EtherError::FrameLength { len, expected } =>
+                write!(f, "Frame is {} bytes, but the layout is {} bytes", len, expected)
+        }
+    }
+}
+
+impl error::Error for EtherError {}
+
+/// The fields of a serial frame, as the FPGA reads them. A field is `None` when it is not in the
+/// layout, which leaves its value up to the firmware.
+#[derive(Debug, Default, PartialEq)]
+pub struct DecodedFrame {
+    /// The data seed, from the 8 or 16-bit seed field.
+    pub seed: Option<u16>,
+
+    /// The data generator, from the 8 or 16-bit generator field.
+    pub gen: Option<u16>,
+
+    /// The packet sequence number.
+    pub seq: Option<u16>,
+
+    /// The test device IP address.
+    pub src_ip: Option<u32>,
+
+    /// The test device port.
+    pub src_port: Option<u16>,
+
+    /// The test device MAC address.
+    pub src_mac: Option<u64>,
+
+    /// The host IP address.
+    pub dest_ip: Option<u32>,
+
+    /// The host port.
+    pub dest_port: Option<u16>,
+
+    /// The host MAC address.
+    pub dest_mac: Option<u64>,
+
+    /// The number of bytes before the generated payload.
+    pub prefix_len: Option<u8>,
+
+    /// The number of bytes after the generated payload.
+    pub suffix_len: Option<u8>,
+
+    /// The modulus that the payload wraps at, where 0 means 256.
+    pub wrap: Option<u8>,
+
+    /// The number of generated payload bytes.
+    pub len: Option<u16>,
+
+    /// The time that the host sent the frame in microseconds.
+    pub timestamp: Option<u32>,
+
+    /// The bytes of the pattern that the FPGA repeats instead of generating a ramp.
+    pub pattern: Option<Vec<u8>>
+}
+
+impl DecodedFrame {
+    /// Decode a serial frame from its bytes alone. The layout and byte order are those that the
+    /// frame was written with, and the values of the fields that are not in the layout are left
+    /// to the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The serial frame.
+    /// * `layout` - The fields of the frame.
+    /// * `endian` - The byte order of the fields.
+    ///
+    /// # Returns
+    ///
+    /// The decoded frame or an error if the frame is not as long as the layout.
+    pub fn from_bytes(bytes: &[u8], layout: &[Field], endian: Endian)
+        -> Result<DecodedFrame, EtherError> {
+        let expected = frame_len(layout);
+        if bytes.len() != expected {
+            return Err(EtherError::FrameLength { len: bytes.len(), expected });
+        }
+        let value = |fields: &[Field]| {
+            fields.iter().filter_map(|&f| field_value(bytes, layout, endian, f)).next()
+        };
+        let pattern = layout.iter().filter_map(|&f| match f {
+            Field::Pattern(_) => field_bytes(bytes, layout, f),
+            _ => None
+        }).next();
+        Ok(DecodedFrame {
+            seed: value(&[Field::SeedWord, Field::Seed]).map(|v| v as u16),
+            gen: value(&[Field::GenWord, Field::Gen]).map(|v| v as u16),
+            seq: value(&[Field::Seq]).map(|v| v as u16),
+            src_ip: value(&[Field::SrcIp]).map(|v| v as u32),
+            src_port: value(&[Field::SrcPort]).map(|v| v as u16),
+            src_mac: value(&[Field::SrcMac]),
+            dest_ip: value(&[Field::DestIp]).map(|v| v as u32),
+            dest_port: value(&[Field::DestPort]).map(|v| v as u16),
+            dest_mac: value(&[Field::DestMac]),
+            prefix_len: value(&[Field::PrefixLen]).map(|v| v as u8),
+            suffix_len: value(&[Field::SuffixLen]).map(|v| v as u8),
+            wrap: value(&[Field::Wrap]).map(|v| v as u8),
+            len: value(&[Field::Len]).map(|v| v as u16),
+            timestamp: value(&[Field::Timestamp]).map(|v| v as u32),
+            pattern: pattern.map(|p| p.to_vec())
+        })
+    }
+}
+
+/// Parse a frame layout, which is a comma separated list of field names in the order that they are
+/// sent.
+///
+/// # Arguments
+///
+/// * `v` - The layout to parse.
+///
+/// # Returns
+///
+/// The fields in order or an error message.
+pub fn parse_layout(v: &str) -> Result<Vec<Field>, String> {
+    let mut layout = vec![];
+    for name in v.split(',').map(|name| name.trim()) {
+        let field = Field::from_name(name)
+            .ok_or_else(|| format!("Unknown frame field: {}", name))?;
+        if layout.contains(&field) {
+            return Err(format!("Frame field is used more than once: {}", name));
+        }
+        layout.push(field);
+    }
+    Ok(layout)
+}
+
+/// Get the number of bytes in a frame.
+///
+/// # Arguments
+///
+/// * `layout` - The fields of the frame.
+///
+/// # Returns
+///
+/// The size of the frame.
+pub fn frame_len(layout: &[Field]) -> usize {
+    layout.iter().map(|f| f.bytes()).sum()
+}
+
+/// Read the value of a field from a frame.
+///
+/// # Arguments
+///
+/// * `frame` - The frame, which must be as long as the layout.
+/// * `layout` - The fields of the frame.
+/// * `endian` - The byte order of the fields.
+/// * `field` - The field to read.
+///
+/// # Returns
+///
+/// The value of the field, or `None` if the layout does not have the field.
+pub fn field_value(frame: &[u8], layout: &[Field], endian: Endian, field: Field) -> Option<u64> {
+    field_bytes(frame, layout, field).map(|bytes| read_uint(bytes, endian))
+}
+
+/// Get the bytes of a field of a frame.
+///
+/// # Arguments
+///
+/// * `frame` - The frame, which must be as long as the layout.
+/// * `layout` - The fields of the frame.
+/// * `field` - The field to get.
+///
+/// # Returns
+///
+/// The bytes of the field, or `None` if the layout does not have the field.
+pub fn field_bytes<'a>(frame: &'a [u8], layout: &[Field], field: Field) -> Option<&'a [u8]> {
+    let i = layout.iter().position(|&f| f == field)?;
+    let offset = frame_len(&layout[..i]);
+    Some(&frame[offset..offset + field.bytes()])
+}
+
+/// Read an unsigned number of up to 8 bytes.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes of the number.
+/// * `endian` - The byte order of the number.
+///
+/// # Returns
+///
+/// The number.
+pub fn read_uint(bytes: &[u8], endian: Endian) -> u64 {
+    let fold = |acc, &b| (acc << 8) | b as u64;
+    match endian {
+        Endian::Big => bytes.iter().fold(0, fold),
+        Endian::Little => bytes.iter().rev().fold(0, fold)
+    }
+}
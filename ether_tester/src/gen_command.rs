@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+/// The most payloads that are kept, after which the cache starts over so that a long run of
+/// random tests does not grow without bound.
+const MAX_CACHED: usize = 4096;
+
+/// The payloads that the command has made, by seed, generator, and number of bytes.
+static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+
+/// Payloads by seed, generator, and number of bytes.
+type Cache = HashMap<(u16, u16, usize), Vec<u8>>;
+
+/// Get the payload that an external command makes for a seed and generator. The command is run
+/// by the shell with the seed, generator, and number of bytes in decimal as its arguments, and
+/// it must write exactly that many bytes to standard output. The command only runs once for a
+/// test, so a test that is run again takes its payload from a cache.
+///
+/// # Arguments
+///
+/// * `command` - The command, which may have arguments of its own.
+/// * `seed` - The data seed.
+/// * `gen` - The data generator.
+/// * `bytes` - The number of bytes in the payload.
+///
+/// # Returns
+///
+/// The payload or an error message.
+pub fn generate(command: &str, seed: u16, gen: u16, bytes: usize) -> Result<Vec<u8>, String> {
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    let key = (seed, gen, bytes);
+    if let Some(payload) = cache.lock().unwrap().get(&key) {
+        return Ok(payload.clone());
+    }
+    let case = format!("seed={} gen={} bytes={}", seed, gen, bytes);
+    // The arguments follow the command so that they need no quoting
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", command))
+        .arg("sh")
+        .arg(seed.to_string())
+        .arg(gen.to_string())
+        .arg(bytes.to_string())
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("{}: {}", command, err))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = match stderr.trim() {
+            "" => String::new(),
+            s => format!(": {}", s)
+        };
+        return Err(format!("{} failed for {} with {}{}", command, case, output.status, stderr));
+    }
+    if output.stdout.len() != bytes {
+        return Err(format!("{} wrote {} bytes for {} instead of {}", command,
+            output.stdout.len(), case, bytes));
+    }
+    let mut cache = cache.lock().unwrap();
+    if cache.len() >= MAX_CACHED {
+        cache.clear();
+    }
+    cache.insert(key, output.stdout.clone());
+    Ok(output.stdout)
+}
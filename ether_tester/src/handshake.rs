@@ -0,0 +1,214 @@
+use std::cell::Cell;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+use super::serial_log;
+
+/// The most bits that a serial character can take with a start bit, 8 data bits, a parity bit,
+/// and 2 stop bits.
+const MAX_CHAR_BITS: u64 = 12;
+
+/// The time on top of the time on the wire that the FPGA has to echo a frame.
+const ECHO_MARGIN_MS: u64 = 50;
+
+thread_local! {
+    /// The number of frames that were sent again on this thread because the FPGA did not echo
+    /// them. Each device writes its frames on its own thread, so this counts for one device.
+    static RETRIES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A serial port that waits for the FPGA to acknowledge every frame written to it. The firmware
+/// echoes each byte that it receives, so the acknowledgement of a frame is the frame itself. A
+/// frame that is not echoed in time is sent again until the retries run out.
+///
+/// The port reads every byte that comes back itself, so it also writes the serial log, since a
+/// reader thread for the log would take the echoes.
+pub struct HandshakePort<P: Write + AsRawFd> {
+    /// The serial port.
+    port: P,
+
+    /// The baudrate of the serial port.
+    baud: usize,
+
+    /// The number of times to send a frame again.
+    retries: u32,
+
+    /// The serial log, if there is one.
+    log: Option<File>
+}
+
+impl<P: Write + AsRawFd> HandshakePort<P> {
+    /// Wrap a serial port.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The serial port.
+    /// * `baud` - The baudrate of the serial port, which sets the time to wait for the echo.
+    /// * `retries` - The number of times to send a frame again.
+    /// * `log` - The serial log, or `None` to log nothing.
+    ///
+    /// # Returns
+    ///
+    /// The port.
+    pub fn new(port: P, baud: usize, retries: u32, log: Option<File>) -> HandshakePort<P> {
+        HandshakePort { port, baud, retries, log }
+    }
+
+    /// Read the bytes that are waiting on the port, or that arrive within a time, and log them.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer to read into.
+    /// * `wait_ms` - The longest time to wait for a byte.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes that were read, which is 0 when none arrived in time.
+    fn read_some(&self, buf: &mut [u8], wait_ms: libc::c_int) -> usize {
+        let fd = self.port.as_raw_fd();
+        let mut poll_fd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        if unsafe { libc::poll(&mut poll_fd, 1, wait_ms) } <= 0 {
+            return 0;
+        }
+        let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n <= 0 {
+            return 0;
+        }
+        if let Some(ref log) = self.log {
+            // A log that cannot be written is not worth failing the test for
+            let _ = serial_log::write_entry(log, &buf[..n as usize]);
+        }
+        n as usize
+    }
+
+    /// Throw away the bytes that are waiting on the port, so that an echo that arrives late for an
+    /// earlier attempt does not count for the next one. The bytes are read into the log when
+    /// there is one, and dropped by the driver otherwise.
+    fn discard_input(&self) {
+        if self.log.is_none() {
+            unsafe { libc::tcflush(self.port.as_raw_fd(), libc::TCIFLUSH) };
+            return;
+        }
+        let mut buf = [0u8; 4096];
+        while self.read_some(&mut buf, 0) > 0 {}
+    }
+
+    /// Wait for the echo of a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The frame that was written.
+    ///
+    /// # Returns
+    ///
+    /// An indicator of whether the whole frame came back unchanged in time.
+    fn await_echo(&self, frame: &[u8]) -> bool {
+        let wire_us = frame.len() as u64 * MAX_CHAR_BITS * 1_000_000 / self.baud.max(1) as u64;
+        let deadline = Instant::now() + Duration::from_micros(wire_us)
+            + Duration::from_millis(ECHO_MARGIN_MS);
+        let mut echo = vec![0u8; frame.len()];
+        let mut len = 0;
+        while len < echo.len() {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            let wait_ms = (deadline - now).as_millis().max(1) as libc::c_int;
+            len += self.read_some(&mut echo[len..], wait_ms);
+        }
+        echo == frame
+    }
+}
+
+impl<P: Write + AsRawFd> Write for HandshakePort<P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for attempt in 0..=self.retries {
+            if attempt > 0 {
+                RETRIES.with(|r| r.set(r.get() + 1));
+                debug!("No echo of the serial frame, sending it again ({} of {})", attempt,
+                    self.retries);
+            }
+            self.discard_input();
+            self.port.write_all(buf)?;
+            self.port.flush()?;
+            if self.await_echo(buf) {
+                return Ok(buf.len());
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::TimedOut, format!(
+            "The FPGA did not echo the serial frame after {} attempts", self.retries + 1)))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+/// Get the number of frames that were sent again on this thread, and start counting from zero.
+///
+/// # Returns
+///
+/// The number of frames.
+pub fn take_retries() -> u64 {
+    RETRIES.with(|r| r.replace(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    /// A frame whose first echo is dropped is sent again and passes on the retry.
+    #[test]
+    fn retry_dropped_echo() {
+        let (host, mut fpga) = UnixStream::pair().unwrap();
+        let echo = thread::spawn(move || {
+            let mut frame = [0u8; 4];
+            fpga.read_exact(&mut frame).unwrap();
+            fpga.read_exact(&mut frame).unwrap();
+            fpga.write_all(&frame).unwrap();
+            frame
+        });
+        let mut port = HandshakePort::new(host, 115_200, 2, None);
+        port.write_all(&[1, 2, 3, 4]).unwrap();
+        assert_eq!(take_retries(), 1);
+        assert_eq!(echo.join().unwrap(), [1, 2, 3, 4]);
+    }
+
+    /// A frame that is never echoed fails once the retries run out.
+    #[test]
+    fn retries_run_out() {
+        let (host, _fpga) = UnixStream::pair().unwrap();
+        let mut port = HandshakePort::new(host, 115_200, 1, None);
+        let err = port.write_all(&[1, 2, 3, 4]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(take_retries(), 1);
+    }
+
+    /// The echoes and the bytes before them are written to the serial log.
+    #[test]
+    fn log_echoes() {
+        let path = env::temp_dir().join(format!("handshake-{}.log", std::process::id()));
+        let (host, mut fpga) = UnixStream::pair().unwrap();
+        fpga.write_all(b"boot").unwrap();
+        let echo = thread::spawn(move || {
+            let mut frame = [0u8; 2];
+            fpga.read_exact(&mut frame).unwrap();
+            fpga.write_all(&frame).unwrap();
+        });
+        let log = serial_log::open(path.to_str().unwrap()).unwrap();
+        let mut port = HandshakePort::new(host, 115_200, 0, Some(log));
+        port.write_all(&[0xAB, 0xCD]).unwrap();
+        echo.join().unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(text.contains("Read 4 bytes"), "{}", text);
+        assert!(text.contains("Read 2 bytes"), "{}", text);
+    }
+}
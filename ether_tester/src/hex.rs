@@ -0,0 +1,60 @@
+/// The number of bytes shown on each row of a dump.
+const ROW_BYTES: usize = 16;
+
+/// Format bytes as a dump with the offset, hexadecimal, and ASCII of each row. Bytes that are not
+/// printable ASCII are shown as `.`.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to dump.
+///
+/// # Returns
+///
+/// The formatted dump with one row per line.
+pub fn dump(bytes: &[u8]) -> String {
+    let rows: Vec<String> = bytes.chunks(ROW_BYTES).enumerate().map(|(i, chunk)| {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+        let ascii: String = chunk.iter()
+            .map(|&b| if (0x20..0x7F).contains(&b) { b as char } else { '.' })
+            .collect();
+        format!("{:04X}  {:<width$}  |{}|", i * ROW_BYTES, hex.join(" "), ascii,
+            width = 3 * ROW_BYTES - 1)
+    }).collect();
+    rows.join("\n")
+}
+
+/// Format bytes as hexadecimal on a single line.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to format.
+///
+/// # Returns
+///
+/// The bytes in hexadecimal separated by spaces.
+pub fn string(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    hex.join(" ")
+}
+
+/// Parse hexadecimal bytes, such as `DEAD`, `0xDEAD`, or `DE:AD`. Spaces and colons between the
+/// bytes are ignored.
+///
+/// # Arguments
+///
+/// * `v` - The value to parse.
+///
+/// # Returns
+///
+/// The bytes, or `None` if the value is not valid.
+pub fn parse(v: &str) -> Option<Vec<u8>> {
+    let v = v.trim_start_matches("0x").trim_start_matches("0X");
+    let digits: Vec<char> = v.chars().filter(|&c| c != ' ' && c != ':').collect();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    digits.chunks(2).map(|pair| {
+        let pair: String = pair.iter().collect();
+        u8::from_str_radix(&pair, 16).ok()
+    }).collect()
+}
@@ -0,0 +1,190 @@
+/// The way that the bytes of a payload are generated from the seed and the generator.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Generator {
+    /// Byte `i` is `(seed + (i mod n) * gen) mod n`, where `n` is the modulus that the bytes wrap
+    /// at, or 256 for `None`. Every byte is below `n`, and the pattern repeats every `n` bytes.
+    /// The seed is reduced along with the rest, so a seed of `n` or more starts the payload at
+    /// `seed mod n`, even when the generator is 0.
+    Ramp(Option<u16>),
+
+    /// The payload is 16-bit words, where word `i` is `(seed + i * gen) mod 65536` and is sent
+    /// most significant byte first.
+    BigWords,
+
+    /// The payload is 16-bit words, where word `i` is `(seed + i * gen) mod 65536` and is sent
+    /// least significant byte first.
+    LittleWords
+}
+
+/// Build the payload that the FPGA sends for a seed and generator, without any sequence number,
+/// prefix, or suffix. This is the whole algorithm of the firmware, so a payload built here is the
+/// golden payload for any other implementation.
+///
+/// All of the arithmetic is unsigned and wraps as described by the generator. A generator of 0 is
+/// the constant fill pattern, where every byte (or word) is the seed. A word payload with an odd
+/// number of bytes ends with the first byte of the last word.
+///
+/// # Arguments
+///
+/// * `seed` - The data seed.
+/// * `gen` - The data generator.
+/// * `bytes` - The number of bytes in the payload.
+/// * `generator` - The way that the bytes are generated.
+///
+/// # Returns
+///
+/// The payload.
+pub fn build_expected(seed: u8, gen: u8, bytes: usize, generator: Generator) -> Vec<u8> {
+    match generator {
+        Generator::Ramp(wrap_at) => ramp(seed, gen, bytes, wrap_at),
+        Generator::BigWords => word_ramp(seed.into(), gen.into(), bytes, true),
+        Generator::LittleWords => word_ramp(seed.into(), gen.into(), bytes, false)
+    }
+}
+
+/// Generate a payload, where byte `i` is the seed plus `i` times the generator. A generator of 0
+/// is the constant fill pattern, where every byte is the seed.
+///
+/// # Arguments
+///
+/// * `seed` - The data seed.
+/// * `gen` - The data generator.
+/// * `bytes` - The number of bytes in the payload.
+/// * `wrap_at` - The modulus that the bytes wrap at, or `None` for the natural 8-bit wrap.
+///
+/// # Returns
+///
+/// The payload.
+pub fn ramp(seed: u8, gen: u8, bytes: usize, wrap_at: Option<u16>) -> Vec<u8> {
+    // The constant fill is built directly so that it stays constant whatever the generator does
+    if gen == 0 {
+        return vec![ramp_value(seed, 0, 0, wrap_at); bytes];
+    }
+    (0..bytes).map(|i| ramp_value(seed, gen, i, wrap_at)).collect()
+}
+
+/// Get a single byte of a payload.
+///
+/// # Arguments
+///
+/// * `seed` - The data seed.
+/// * `gen` - The data generator.
+/// * `i` - The index of the byte.
+/// * `wrap_at` - The modulus that the bytes wrap at, or `None` for the natural 8-bit wrap.
+///
+/// # Returns
+///
+/// The value of the byte.
+pub fn ramp_value(seed: u8, gen: u8, i: usize, wrap_at: Option<u16>) -> u8 {
+    let n = wrap_at.map_or(256, u64::from);
+    ((seed as u64 + (i as u64 % n) * gen as u64) % n) as u8
+}
+
+/// Generate a payload that repeats a fixed pattern, which is cut short at the end when the number
+/// of bytes is not a multiple of the length of the pattern.
+///
+/// # Arguments
+///
+/// * `pattern` - The bytes that repeat.
+/// * `bytes` - The number of bytes in the payload.
+///
+/// # Returns
+///
+/// The payload.
+pub fn tile(pattern: &[u8], bytes: usize) -> Vec<u8> {
+    pattern.iter().cycle().take(bytes).cloned().collect()
+}
+
+/// Generate a payload of 16-bit words, where word `i` is the seed plus `i` times the generator with
+/// a wrap at 65536. A payload with an odd number of bytes ends with the first byte of the last
+/// word.
+///
+/// # Arguments
+///
+/// * `seed` - The data seed.
+/// * `gen` - The data generator.
+/// * `bytes` - The number of bytes in the payload.
+/// * `big_endian` - Whether each word is sent most significant byte first.
+///
+/// # Returns
+///
+/// The payload.
+pub fn word_ramp(seed: u16, gen: u16, bytes: usize, big_endian: bool) -> Vec<u8> {
+    let mut v = Vec::with_capacity(bytes + 1);
+    for i in 0..bytes.div_ceil(2) {
+        let word = word_ramp_value(seed, gen, i);
+        if big_endian {
+            v.extend_from_slice(&word.to_be_bytes());
+        } else {
+            v.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+    v.truncate(bytes);
+    v
+}
+
+/// Get a single word of a word payload.
+///
+/// # Arguments
+///
+/// * `seed` - The data seed.
+/// * `gen` - The data generator.
+/// * `i` - The index of the word.
+///
+/// # Returns
+///
+/// The value of the word.
+pub fn word_ramp_value(seed: u16, gen: u16, i: usize) -> u16 {
+    // Only the low 16 bits of the index matter with a wrap at 65536
+    seed.wrapping_add((i as u16).wrapping_mul(gen))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every generator builds the payload that was worked out by hand.
+    #[test]
+    fn build_expected_generators() {
+        assert_eq!(build_expected(5, 3, 6, Generator::Ramp(None)), vec![5, 8, 11, 14, 17, 20]);
+        assert_eq!(build_expected(250, 3, 4, Generator::Ramp(None)), vec![250, 253, 0, 3]);
+        assert_eq!(build_expected(150, 30, 4, Generator::Ramp(Some(100))), vec![50, 80, 10, 40]);
+        assert_eq!(build_expected(150, 0, 3, Generator::Ramp(Some(100))), vec![50, 50, 50]);
+        assert_eq!(build_expected(0x12, 1, 5, Generator::BigWords), vec![0x00, 0x12, 0x00, 0x13,
+            0x00]);
+        assert_eq!(build_expected(0x12, 1, 5, Generator::LittleWords), vec![0x12, 0x00, 0x13,
+            0x00, 0x14]);
+    }
+
+    /// A ramp that wraps at 100 crosses the wrap and repeats every 100 bytes.
+    #[test]
+    fn ramp_wrap_at_100() {
+        let wrap = Some(100);
+        assert_eq!(ramp(90, 7, 4, wrap), vec![90, 97, 4, 11]);
+        assert_eq!(ramp_value(90, 7, 100, wrap), 90);
+        assert_eq!(ramp_value(90, 7, 102, wrap), 4);
+        // Large seeds and generators are reduced without overflowing a byte
+        assert_eq!(ramp_value(250, 255, 1, wrap), 5);
+        assert_eq!(ramp_value(255, 255, 99, wrap), 0);
+    }
+
+    /// A pattern that does not divide the payload is cut short at the end.
+    #[test]
+    fn tile_pattern() {
+        assert_eq!(tile(&[0xA1, 0xB2, 0xC3], 8), vec![0xA1, 0xB2, 0xC3, 0xA1, 0xB2, 0xC3, 0xA1,
+            0xB2]);
+        assert_eq!(tile(&[0xA1, 0xB2, 0xC3], 2), vec![0xA1, 0xB2]);
+    }
+
+    /// A word ramp wraps from 65535 to 0 in both byte orders.
+    #[test]
+    fn word_ramp_wrap() {
+        assert_eq!(word_ramp(0xFFFE, 1, 8, true), vec![0xFF, 0xFE, 0xFF, 0xFF, 0x00, 0x00, 0x00,
+            0x01]);
+        assert_eq!(word_ramp(0xFFFE, 1, 7, false), vec![0xFE, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x01]);
+        assert_eq!(word_ramp_value(0xFFF0, 0x0010, 1), 0);
+        assert_eq!(word_ramp_value(0x0001, 0xFFFF, 3), 0xFFFE);
+        // Only the low 16 bits of the index matter
+        assert_eq!(word_ramp_value(0x1234, 0x0101, 0x10002), word_ramp_value(0x1234, 0x0101, 2));
+    }
+}
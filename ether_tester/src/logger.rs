@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// The verbosity of the log, which is the number of `-v` flags.
+static VERBOSITY: AtomicUsize = AtomicUsize::new(0);
+
+/// The time that the log started.
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// The level of a log message.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    /// The progress of the tests, shown with `-v`.
+    Info = 1,
+
+    /// The sizes of the data written and read, shown with `-vv`.
+    Debug = 2,
+
+    /// The data written and read, shown with `-vvv`.
+    Trace = 3
+}
+
+/// Set the verbosity of the log and start its clock.
+///
+/// # Arguments
+///
+/// * `verbosity` - The number of `-v` flags.
+pub fn init(verbosity: usize) {
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+    start();
+}
+
+/// Check whether messages of a level are logged.
+///
+/// # Arguments
+///
+/// * `level` - The level of the message.
+///
+/// # Returns
+///
+/// Whether the message would be logged.
+pub fn enabled(level: Level) -> bool {
+    VERBOSITY.load(Ordering::Relaxed) >= level as usize
+}
+
+/// Log a message to standard error with the time since the log started. The message is only
+/// formatted when it is logged.
+///
+/// # Arguments
+///
+/// * `level` - The level of the message.
+/// * `message` - Produces the message.
+pub fn log<F: FnOnce() -> String>(level: Level, message: F) {
+    if !enabled(level) {
+        return;
+    }
+    let name = match level {
+        Level::Info => "INFO ",
+        Level::Debug => "DEBUG",
+        Level::Trace => "TRACE"
+    };
+    let elapsed = start().elapsed();
+    eprintln!("[{:>4}.{:06} {}] {}", elapsed.as_secs(), elapsed.subsec_micros(), name, message());
+}
+
+/// Get the time that the log started.
+///
+/// # Returns
+///
+/// The start time.
+pub fn start() -> Instant {
+    *START.get_or_init(Instant::now)
+}
+
+/// Log a message about the progress of the tests.
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::logger::log($crate::logger::Level::Info, || format!($($arg)*)) }
+}
+
+/// Log a message about the sizes of the data written and read.
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::logger::log($crate::logger::Level::Debug, || format!($($arg)*)) }
+}
+
+/// Log a message with the data written and read.
+macro_rules! trace {
+    ($($arg:tt)*) => { $crate::logger::log($crate::logger::Level::Trace, || format!($($arg)*)) }
+}
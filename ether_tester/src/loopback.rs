@@ -0,0 +1,144 @@
+use std::io;
+use std::io::Write;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use super::checksum;
+use super::frame;
+use super::frame::DecodedFrame;
+use super::gen_command;
+use super::params::Params;
+use super::test_case;
+use super::test_case::TestCase;
+
+/// The time without serial bytes after which a partial frame is dropped.
+const FRAME_TIMEOUT_MS: u64 = 100;
+
+/// An emulation of the FPGA that takes the place of the serial port. Every frame written to it is
+/// handled by a background thread exactly like the firmware does, so the payload comes back to
+/// the tester over UDP.
+pub struct Loopback {
+    /// The channel to the emulator thread.
+    tx: Sender<Vec<u8>>
+}
+
+impl Loopback {
+    /// Start the emulator thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The test parameters, which describe the serial frame and the number of
+    ///   payload bytes to send like the `DATA_BYTES` parameter of the firmware.
+    ///
+    /// # Returns
+    ///
+    /// The loopback handle or an error message.
+    pub fn spawn(params: &Params) -> Result<Loopback, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|err| err.to_string())?;
+        let (tx, rx) = channel();
+        let params = params.clone();
+        thread::spawn(move || emulate(socket, rx, &params));
+        Ok(Loopback { tx })
+    }
+}
+
+impl Write for Loopback {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.tx.send(buf.to_vec()) {
+            Ok(_) => Ok(buf.len()),
+            Err(_) => Err(io::Error::new(io::ErrorKind::BrokenPipe, "Loopback thread stopped"))
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Run the emulator until the loopback handle is dropped. The serial bytes are collected into
+/// frames, and each complete frame produces one UDP packet. A partial frame is dropped when no
+/// more bytes arrive in time.
+///
+/// # Arguments
+///
+/// * `socket` - The socket to send packets from.
+/// * `rx` - The channel that receives the serial bytes.
+/// * `params` - The test parameters.
+fn emulate(socket: UdpSocket, rx: Receiver<Vec<u8>>, params: &Params) {
+    let frame_bytes = frame::frame_len(&params.frame_layout);
+    let mut frame = vec![];
+    loop {
+        let chunk = match rx.recv_timeout(Duration::from_millis(FRAME_TIMEOUT_MS)) {
+            Ok(chunk) => chunk,
+            Err(RecvTimeoutError::Timeout) => {
+                frame.clear();
+                continue;
+            },
+            Err(RecvTimeoutError::Disconnected) => break
+        };
+        for b in chunk {
+            frame.push(b);
+            if frame.len() == frame_bytes {
+                // A failed send is the same as a lost packet, which the tester reports
+                let _ = send_frame(&socket, &frame, params);
+                frame.clear();
+            }
+        }
+    }
+}
+
+/// Send the packet described by a serial frame. Fields that are not in the frame keep the values
+/// from the parameters, like registers in the firmware that are not written.
+///
+/// # Arguments
+///
+/// * `socket` - The socket to send the packet from.
+/// * `frame` - The serial frame.
+/// * `params` - The test parameters.
+///
+/// # Returns
+///
+/// Nothing on success and an error on a failed send.
+fn send_frame(socket: &UdpSocket, frame: &[u8], params: &Params) -> io::Result<()> {
+    let start = Instant::now();
+    let decoded = DecodedFrame::from_bytes(frame, &params.frame_layout, params.frame_endian)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let test_case = TestCase::from_decoded(params, &decoded);
+    let dest_ip = Ipv4Addr::from(test_case.dest_ip);
+    let dest_port = test_case.dest_port;
+    // A modulus of 0 in the frame is a wrap at 256
+    let wrap_at = decoded.wrap.map_or_else(|| params.ramp_wrap_at.unwrap_or(0), u16::from);
+    let wrap_at = match wrap_at {
+        0 => None,
+        n => Some(n)
+    };
+    let bytes = decoded.len.map_or(params.bytes, |n| n as usize);
+    let mut payload = if let Some(ref pattern) = params.pattern {
+        test_case::tile(decoded.pattern.as_ref().unwrap_or(pattern), bytes)
+    } else if let Some(ref command) = params.gen_command {
+        gen_command::generate(command, test_case.seed, test_case.gen, bytes)
+            .map_err(io::Error::other)?
+    } else if params.word_mode {
+        test_case::word_ramp(test_case.seed, test_case.gen, bytes, params.frame_endian)
+    } else {
+        test_case::ramp(test_case.seed as u8, test_case.gen as u8, bytes, wrap_at)
+    };
+    if params.seqnum {
+        test_case::stamp_seq(&mut payload, test_case.seq);
+    }
+    if params.fpga_timestamp && payload.len() >= test_case::TIMESTAMP_LEN {
+        let latency_us = start.elapsed().as_micros() as u32;
+        test_case::stamp_time(&mut payload, test_case.timestamp, latency_us);
+    }
+    // The prefix and suffix are fixed in the firmware, so only their lengths are in the frame
+    let payload = test_case::frame_payload(&params.payload_prefix, payload,
+        &params.payload_suffix);
+    // Firmware that sends its frames out for inspection puts the headers in the payload
+    let payload = if params.check_checksums {
+        checksum::wrap((params.src_ip, params.src_port), (dest_ip.into(), dest_port), &payload)
+    } else {
+        payload
+    };
+    socket.send_to(&payload, (dest_ip, dest_port)).map(|_| ())
+}
@@ -1,20 +1,234 @@
 extern crate ansi_term;
+extern crate atty;
 use ansi_term::{Colour, Style};
 #[macro_use]
 extern crate clap;
+extern crate ether_tester;
+extern crate libc;
+extern crate rand;
 extern crate regex;
 extern crate serial;
+use rand::Rng;
 use serial::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::env;
 use std::fmt::Display;
-use std::io::Write;
-use std::net::UdpSocket;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::io::{BufRead, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::process::Command;
 use std::result::Result;
-use std::time::Duration;
+use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
+#[macro_use]
+mod logger;
+#[macro_use]
+mod output;
+mod checksum;
+mod color;
+mod compare;
+mod control;
+mod crc;
+#[cfg(unix)]
+mod daemon;
+mod diff;
+mod event_log;
+mod frame;
+mod gen_command;
+#[cfg(unix)]
+mod handshake;
+mod hex;
+mod loopback;
+mod metrics;
 mod params;
+mod payload_log;
+mod pcap;
+mod profile;
+mod raw;
+mod record;
+mod repro;
+#[cfg(unix)]
+mod serial_log;
+mod socket;
+mod swap;
+mod table;
 mod test_case;
-use params::Params;
+mod watchdog;
+use compare::{max_deviation, verbose_compare, Mismatch, Signature};
+use event_log::EventLog;
+use frame::DecodedFrame;
+#[cfg(unix)]
+use handshake::HandshakePort;
+use loopback::Loopback;
+use metrics::Metrics;
+use params::{CompareMode, Endian, Format, Params, TimeoutPolicy};
+use payload_log::PayloadLog;
+use raw::RawSocket;
+use record::Recorder;
+#[cfg(unix)]
+use serial_log::LoggedPort;
+use socket::Membership;
 use test_case::TestCase;
+use watchdog::Watchdog;
+
+/// The time to wait after the ARP warmup datagram before testing.
+const ARP_WARMUP_DELAY_MS: u64 = 100;
+
+/// The number of tests that are sent at each baudrate of a baudrate sweep.
+const BAUD_SWEEP_TESTS: usize = 3;
+
+/// The delay before the first retry of a failed socket bind. The delay doubles for every retry.
+const BIND_RETRY_DELAY_MS: u64 = 100;
+
+/// The delay between attempts to open the serial port.
+const OPEN_RETRY_DELAY_MS: u64 = 500;
+
+/// The chance out of 256 that a test sends a malformed frame when fuzzing.
+const MALFORMED_CHANCE: u8 = 32;
+
+/// The largest number of failed seeds and generators that are listed in the summary.
+const MAX_LISTED_CASES: usize = 32;
+
+/// The largest number of packet sizes that are listed in the summary.
+const MAX_LISTED_SIZES: usize = 16;
+
+/// The number of tests that are sent at each payload size of an MTU discovery.
+const MTU_DISCOVER_TESTS: usize = 3;
+
+/// The number of times that the failure hook has been run.
+static HOOK_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Indicates that the script that runs the first failed test again has been written.
+static REPRO_WRITTEN: AtomicBool = AtomicBool::new(false);
+
+/// Indicates that Ctrl-C was pressed, which stops the tests so that the summary is still written.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The settings that a batch line must share with the previous line to reuse its socket, which
+/// are the bind IP address, the port, the receive buffer size, and the multicast group with the
+/// interface that it is joined on.
+type SocketKey = (u32, u16, Option<usize>, Option<(u32, u32)>);
+
+/// The serial port and socket that stay open between the lines of a batch or the requests of a
+/// daemon, with the settings that they were opened with.
+#[derive(Default)]
+struct KeptOpen {
+    /// The serial port with its name and baudrate.
+    port: Option<((String, usize), Box<dyn Write>)>,
+
+    /// The socket with the settings that it was bound with and its membership in the multicast
+    /// group, if any.
+    socket: Option<(SocketKey, UdpSocket, Option<Membership>)>
+}
+
+/// The outcome of running the tests on one device.
+#[derive(Default)]
+struct Outcome {
+    /// The name of the device, which is empty when there is only one device.
+    label: String,
+
+    /// The number of tests that were run.
+    num_run: usize,
+
+    /// The number of tests that failed.
+    num_failed: u64,
+
+    /// Indicates that the tests stopped early because of the failure limit.
+    stopped: bool,
+
+    /// The test that timed out and stopped the run under the abort timeout policy, if any.
+    aborted: Option<usize>,
+
+    /// Indicates that the tests stopped because no packets arrived in the first tests.
+    no_packets: bool,
+
+    /// The number of packets received.
+    packets_received: u64,
+
+    /// The number of payload bytes received.
+    bytes_received: u64,
+
+    /// The time from the start of the first test to the last received packet.
+    elapsed: Duration,
+
+    /// The test with the most bytes that differ and the number of bytes, if any test received a
+    /// wrong payload.
+    worst: Option<(usize, usize)>,
+
+    /// The number of tests where no packet was received.
+    dropped: u64,
+
+    /// The number of tests where the packet had the wrong payload.
+    corrupted: u64,
+
+    /// The number of tests where the packet had a bad header or checksum.
+    checksum_errors: u64,
+
+    /// The largest difference between an expected and received byte.
+    deviation: u8,
+
+    /// The number of bits that differed from the expected payloads.
+    bit_errors: u64,
+
+    /// The number of bits that were compared with the expected payloads.
+    bits_compared: u64,
+
+    /// The number of extra packets that were read after wrong packets.
+    rereads: u64,
+
+    /// The seed and generator of every failed test of a seed sweep.
+    failed_cases: Vec<(u16, u16)>,
+
+    /// The number of serial frames that were sent again after a timeout.
+    resent: u64,
+
+    /// The number of packets that were skipped because they were the same as the packet before.
+    duplicates: u64,
+
+    /// The number of packets that matched each candidate payload.
+    candidate_matches: Vec<u64>,
+
+    /// The number of corrupted frames that did not produce a packet.
+    frames_rejected: u64,
+
+    /// The number of corrupted frames that produced the packet that they describe.
+    frames_tolerated: u64,
+
+    /// The number of tests that started more than one period after their time.
+    behind_rate: u64,
+
+    /// The number of serial frames that were sent again because the FPGA did not echo them.
+    handshake_retries: u64,
+
+    /// The number of packets that the operating system dropped before they were read, if it
+    /// counts them.
+    os_dropped: Option<u64>,
+
+    /// The number of packets of the tests that were received with each size.
+    sizes: HashMap<usize, u64>,
+
+    /// The number of tests that were run and that failed for each destination of a round robin.
+    dest_results: Vec<(usize, u64)>,
+
+    /// The number of payload mismatches with each signature when failures are grouped.
+    signatures: HashMap<Signature, u64>,
+
+    /// The time from writing the serial frame to receiving the packet of each test that received
+    /// one.
+    latencies: Vec<Duration>,
+
+    /// The time that the FPGA took to handle the frame of each test, as it reported in the
+    /// payload.
+    fpga_latencies: Vec<Duration>,
+
+    /// The round trip of each test without the time that the FPGA took.
+    network_latencies: Vec<Duration>
+}
 
 /// Prints a message and then terminates the program.
 ///
@@ -23,152 +237,3630 @@ use test_case::TestCase;
 /// * `title` - The title of the message that is printed first.
 /// * `body` - The body of the mesage that is printed second.
 fn fatal<T: Display, U: Display>(title: T, body: U) -> ! {
-    let style = Style::new().bold().fg(Colour::Red);
+    // Errors skip quiet mode so that it is clear why nothing ran
+    let style = color::style(Style::new().bold().fg(Colour::Red));
     println!("{}: {}: {}", style.paint("Error"), title, body);
     std::process::exit(1);
 }
 
-/// Compares two vectors based on length and content, and produces a meaningful error message.
+/// Gets the value of a result, or prints its error message and then terminates the program.
 ///
 /// # Arguments
 ///
-/// * `xs` - The expected values.
-/// * `ys` - The actual values.
-/// * `ylen` - The number of `y`s to compare.
+/// * `result` - The result, where the error message starts with what failed.
 ///
 /// # Returns
 ///
-/// Nothing on success and an error message on a failed conparison.
-fn verbose_compare(xs: Vec<u8>, ys: Vec<u8>, ylen: usize) -> Result<(), String> {
-    let mut xs_iter = xs.iter();
-    let mut ys_iter = ys.iter().take(ylen);
-    for i in 0.. {
-        match (xs_iter.next(), ys_iter.next()) {
-            (Some(x), Some(y)) => if x != y {
-                return Err(format!("Error in byte {}: {} != {}", i, x, y))
-            },
-            (Some(x), None) =>
-                return Err(format!("Error in byte {}: Expected {:#04X}, got none", i, x)),
-            (None, Some(y)) =>
-                return Err(format!("Error in byte {}: Expected none, got {:#04X}", i, y)),
-            (None, None) => return Ok(()),
+/// The value of the result.
+fn or_fatal<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|msg| {
+        error(msg);
+        std::process::exit(1);
+    })
+}
+
+/// Prints an error message and continues, for an error that fails the run without stopping it.
+///
+/// # Arguments
+///
+/// * `msg` - The error message, which starts with what failed.
+fn error<T: Display>(msg: T) {
+    // Errors skip quiet mode so that it is clear why a device was not tested
+    let style = color::style(Style::new().bold().fg(Colour::Red));
+    println!("{}: {}", style.paint("Error"), msg);
+}
+
+/// Prints a warning message and continues.
+///
+/// # Arguments
+///
+/// * `title` - The title of the message that is printed first.
+/// * `body` - The body of the mesage that is printed second.
+fn warn<T: Display, U: Display>(title: T, body: U) {
+    warn_to(&mut io::stdout(), title, body);
+}
+
+/// Prints a warning message to a stream and continues.
+///
+/// # Arguments
+///
+/// * `out` - The stream to print to.
+/// * `title` - The title of the message that is printed first.
+/// * `body` - The body of the mesage that is printed second.
+fn warn_to<T: Display, U: Display>(out: &mut dyn Write, title: T, body: U) {
+    let style = color::style(Style::new().bold().fg(Colour::Yellow));
+    outln!(to out, "{}: {}: {}", style.paint("Warning"), title, body);
+}
+
+/// Finish a run and exit with a failure status if it failed. In quiet mode only a line about the
+/// failure is printed.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `passed` - An indicator of whether the run passed.
+/// * `failure` - The line that is printed in quiet mode when the run failed.
+fn finish(params: &Params, passed: bool, failure: &str) {
+    if params.quiet {
+        if !passed {
+            println!("{}", failure);
         }
+    } else {
+        outln!();
+    }
+    if !passed {
+        std::process::exit(1);
     }
-    return Ok(())
 }
 
-fn main() {
-    let title = Style::new().bold().fg(Colour::Blue);
-    let heading = Style::new().fg(Colour::Cyan);
-    let info = Style::new().fg(Colour::Blue);
-    let fail = Style::new().bold().fg(Colour::Red);
-    let success = Style::new().bold().fg(Colour::Green);
+/// Write a whole serial frame, since a single write may send only part of it. Stopping part way
+/// through a frame leaves the FPGA waiting for the rest, so this is reported separately from other
+/// write errors.
+///
+/// # Arguments
+///
+/// * `port` - The port to write to.
+/// * `frame` - The serial frame.
+///
+/// # Returns
+///
+/// Nothing on success and an error message on a failed or incomplete write.
+fn write_frame(port: &mut dyn Write, frame: &[u8]) -> Result<(), String> {
+    let mut written = 0;
+    while written < frame.len() {
+        match port.write(&frame[written..]) {
+            Ok(0) => break,
+            Ok(n) => written += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => if written == 0 {
+                return Err(err.to_string())
+            } else {
+                return Err(format!("Partial serial write: {}/{} bytes: {}", written, frame.len(),
+                    err))
+            }
+        }
+    }
+    if written < frame.len() {
+        return Err(format!("Partial serial write: {}/{} bytes", written, frame.len()));
+    }
+    Ok(())
+}
 
-    // Get the command line arguments
-    let params = match Params::get() {
-        Ok(p) => p,
-        Err(msg) => fatal("Bad command line argument", msg)
-    };
+/// Check whether a socket error is a read that timed out.
+///
+/// # Arguments
+///
+/// * `err` - The error from the socket.
+///
+/// # Returns
+///
+/// An indicator of whether the error is a timeout.
+fn is_timeout(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut
+}
 
-    // Print the test parameters
-    println!("{}", title.paint("Parameters"));
-    println!("{}", title.paint("----------"));
-    println!("{} {}", heading.paint("Source         "), info.paint("(Test Device)"));
-    println!("{} {}", heading.paint("  IP           "), params.src_ip_string());
-    println!("{} {}", heading.paint("  Port         "), params.src_port);
-    println!("{} {}", heading.paint("  Mac          "), params.src_mac_string());
-    println!("{} {}", heading.paint("Destination    "), info.paint("(Host Device)"));
-    println!("{} {}", heading.paint("  IP           "), params.dest_ip_string());
-    println!("{} {}", heading.paint("  Port         "), params.dest_port);
-    println!("{} {}", heading.paint("  Mac          "), params.dest_mac_string());
-    println!("{} {}", heading.paint("Serial Port    "), params.serial_port);
-    println!("{} {}", heading.paint("Serial Baudrate"), params.serial_baud.speed());
-    println!();
-
-    // Open a new port
-    let mut port = match serial::open(&params.serial_port) {
-        Ok(p) => p,
-        Err(err) => fatal("Could not open serial port", err.to_string())
-    };
-    match port.reconfigure(&|settings| {
-        settings.set_baud_rate(params.serial_baud)?;
-        settings.set_char_size(Bits8);
-        settings.set_parity(ParityNone);
-        settings.set_stop_bits(Stop1);
-        settings.set_flow_control(FlowNone);
-        Ok(())
-    }) {
-        Ok(_) => {},
-        Err(err) => fatal("Could not change serial settings", err.to_string())
+/// Look for a packet from the source that went to another destination than the one that the
+/// tester listens on.
+///
+/// # Arguments
+///
+/// * `sniffer` - The packet socket that receives the packets from the source.
+/// * `buf` - The buffer to read the packets into.
+/// * `listening` - The IP address and port that the tester listens on.
+///
+/// # Returns
+///
+/// A description of where the packet went, or `None` if no packet went elsewhere.
+fn wrong_dest(sniffer: &RawSocket, buf: &mut [u8], listening: (u32, u16)) -> Option<String> {
+    let mut diagnosis = None;
+    while let Ok((ip, port)) = sniffer.try_recv_dest(buf) {
+        if port != listening.1 {
+            diagnosis = Some(format!("Packet received on port {}, but tester is listening on {}",
+                port, listening.1));
+        } else if ip != listening.0 {
+            diagnosis = Some(format!("Packet received for {}, but tester is listening on {}",
+                Ipv4Addr::from(ip), Ipv4Addr::from(listening.0)));
+        }
     }
+    diagnosis
+}
 
-    // Bind a socket to the test system
-    let socket = if !params.no_socket {
-        let socket_addr = format!("{}:{}", params.dest_ip_string(), params.dest_port);
-        Some(match UdpSocket::bind(socket_addr) {
-            Ok(s) => {
-                if let Err(err) = s.set_read_timeout(Some(Duration::new(1, 0))) {
-                    fatal("Could not set socket read timeout", err.to_string())
-                }
-                s
-            },
-            Err(err) => fatal("Could not open socket", err.to_string())
-        })
+/// Get the payload of a received packet without the headers before it, which are the IPv4 and
+/// UDP headers when checksums are checked and the Ethernet, IPv4, and UDP headers in raw mode.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `packet` - The received packet.
+///
+/// # Returns
+///
+/// The payload, or the whole packet when its headers cannot be removed.
+fn packet_payload<'p>(params: &Params, packet: &'p [u8]) -> &'p [u8] {
+    if params.check_checksums {
+        checksum::strip(packet).unwrap_or(packet)
+    } else if params.raw {
+        &packet[raw::HEADER_LEN.min(packet.len())..]
+    } else {
+        packet
+    }
+}
+
+/// Find out whether a packet is a late packet from an earlier test by its sequence number.
+///
+/// # Arguments
+///
+/// * `payload` - The received payload.
+/// * `seq` - The sequence number of the current test.
+/// * `num_sent` - The number of tests that have been sent, including the current test.
+///
+/// # Returns
+///
+/// The sequence number of the packet if it is from an earlier test, or `None` otherwise.
+fn late_seq(payload: &[u8], seq: u16, num_sent: usize) -> Option<u16> {
+    if payload.len() < 2 {
+        return None;
+    }
+    let received = (payload[0] as u16) << 8 | payload[1] as u16;
+    // Sequence numbers wrap, so only the last half of the range counts as earlier
+    let age = seq.wrapping_sub(received) as usize;
+    if age > 0 && age < num_sent.min(0x8000) {
+        Some(received)
     } else {
         None
-    };
+    }
+}
+
+/// Compare a packet with the expected payload. Only the first bytes of both are compared when the
+/// comparison is limited, so that padding after them does not matter.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `expected` - The expected payload.
+/// * `actual` - The received payload.
+///
+/// # Returns
+///
+/// Nothing on success and the mismatch on a failed comparison.
+fn compare_payload(params: &Params, expected: &[u8], actual: &[u8]) -> Result<(), Mismatch> {
+    let n = params.compare_bytes.unwrap_or(usize::MAX);
+    let expected = &expected[..n.min(expected.len())];
+    let actual = &actual[..n.min(actual.len())];
+    match params.compare_mode {
+        CompareMode::Exact => verbose_compare(expected, actual, params.tolerance),
+        mode => {
+            let actual = compare::arrange(actual, mode);
+            verbose_compare(&compare::arrange(expected, mode), &actual, params.tolerance)
+        }
+    }
+}
+
+/// Check whether a packet has the CRC of the expected payload, in which case there are no wrong
+/// bytes to look for. Only the bytes that are compared count toward the CRC, and the lengths must
+/// also be equal.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `expected` - The expected payload.
+/// * `expected_crc` - The CRC of the compared bytes of the expected payload, or `None` when the
+///   CRCs are not compared.
+/// * `actual` - The received payload.
+///
+/// # Returns
+///
+/// Whether the CRCs match, which is never the case when they are not compared.
+fn crc_matches(params: &Params, expected: &[u8], expected_crc: Option<u32>, actual: &[u8])
+    -> bool {
+    let n = params.compare_bytes.unwrap_or(actual.len()).min(actual.len());
+    expected_crc.is_some_and(|crc| {
+        n == params.compare_bytes.unwrap_or(expected.len()).min(expected.len())
+            && crc == crc::crc32(&actual[..n])
+    })
+}
+
+/// Send a command to the control port of the firmware, if there is one.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `command` - The command, or `None` to send nothing.
+///
+/// # Returns
+///
+/// Nothing on success or an error message.
+fn send_control(params: &Params, command: &Option<Vec<u8>>) -> Result<(), String> {
+    if let (Some(addr), Some(command)) = (params.control, command.as_ref()) {
+        control::send(addr, command)
+            .map_err(|err| format!("Could not send control command: {}: {}", addr, err))?;
+        info!("Sent control command {} to {}", hex::string(command), addr);
+    }
+    Ok(())
+}
+
+/// Open the serial port, or start emulating the FPGA in its place.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+///
+/// # Returns
+///
+/// The port to write serial frames to or an error message.
+fn open_port(params: &Params) -> Result<Box<dyn Write>, String> {
+    if params.loopback {
+        return match Loopback::spawn(params) {
+            Ok(l) => Ok(Box::new(l)),
+            Err(err) => Err(format!("Could not start loopback: {}", err))
+        }
+    }
+    let mut port = open_serial(params)?;
+    port.reconfigure(&|settings| {
+        settings.set_baud_rate(params.serial_baud)?;
+        settings.set_char_size(params.data_bits);
+        settings.set_parity(params.parity);
+        settings.set_stop_bits(params.stop_bits);
+        settings.set_flow_control(params.flow_control);
+        Ok(())
+    }).map_err(|err| format!("Could not change serial settings: {}", err))?;
+    wrap_port(port, params)
+}
+
+/// Wrap the serial port in the handshake or the serial log, when they are asked for.
+///
+/// # Arguments
+///
+/// * `port` - The configured serial port.
+/// * `params` - The test parameters.
+///
+/// # Returns
+///
+/// The port to write serial frames to or an error message.
+#[cfg(unix)]
+fn wrap_port(port: serial::SystemPort, params: &Params) -> Result<Box<dyn Write>, String> {
+    let log = params.serial_log.as_ref()
+        .map(|path| serial_log::open(path)
+            .map_err(|msg| format!("Could not open serial log: {}", msg)))
+        .transpose()?;
+    Ok(match (params.handshake_retries, log) {
+        // The handshake reads the echoes, so it writes the log instead of a reader thread
+        (Some(retries), log) => Box::new(HandshakePort::new(port, params.serial_baud.speed(),
+            retries, log)),
+        (None, Some(log)) => Box::new(LoggedPort::new(port, log)),
+        (None, None) => Box::new(port)
+    })
+}
+
+/// Wrap the serial port. The handshake and the serial log poll the port, which this platform
+/// cannot do, so they are rejected with the parameters and the port is used as it is.
+///
+/// # Arguments
+///
+/// * `port` - The configured serial port.
+/// * `_params` - The test parameters.
+///
+/// # Returns
+///
+/// The port to write serial frames to.
+#[cfg(not(unix))]
+fn wrap_port(port: serial::SystemPort, _params: &Params) -> Result<Box<dyn Write>, String> {
+    Ok(Box::new(port))
+}
 
-    println!("{}", title.paint("Results"));
-    println!("{}", title.paint("-------"));
-    let mut num_failed: u64 = 0;
-    for i in 1..=params.reps {
-        let test_case = TestCase::new(&params);
-        // Run the communication
-        let result: Result<(), String> = port
-            // Write the test information over serial
-            .write(&test_case.to_bytes())
-            .map_err(|err| err.to_string())
-            // Read the incoming Ethernet data and compare it to the expected data
-            .and_then(|_| {
-                if let Some(ref s) = socket {
-                    // Read the packet
-                    let mut buf = vec![0; params.bytes];
-                    match s.recv_from(&mut buf) {
-                        Ok((size, _socket_addr)) => verbose_compare(test_case.expected(), buf, size),
-                        Err(err) => Err(format!("Could not read socket: {}", err.to_string()))
+/// Open the serial port, and retry in case the device is not ready yet.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+///
+/// # Returns
+///
+/// The serial port or an error message.
+fn open_serial(params: &Params) -> Result<serial::SystemPort, String> {
+    let mut attempt = 0;
+    loop {
+        match serial::open(&params.serial_port) {
+            Ok(mut p) => {
+                // The driver asserts the lines on open, so they are cleared as soon as possible.
+                // A port without the lines, like a pseudo terminal, cannot reset the board anyway.
+                if params.no_dtr {
+                    if let Err(err) = p.set_dtr(false) {
+                        warn("Could not clear DTR", err);
                     }
-                } else {
-                    // If the ethernet is not used then the test automatically passes
-                    Ok(())
                 }
-            });
-        // Print output
-        match result {
-            Ok(_) => if test_case.params.show_all {
-                if params.no_socket {
-                    println!("{}", success.paint("(No socket open)"));
-                } else {
-                    println!("{}", success.paint(format!("Passed {}", i)));
+                if params.no_rts {
+                    if let Err(err) = p.set_rts(false) {
+                        warn("Could not clear RTS", err);
+                    }
                 }
+                return Ok(p);
             },
-            Err(msg) => {
-                num_failed += 1;
-                println!("{}: {}", fail.paint(format!("Failed {}", i)), msg);
+            Err(err) => {
+                if attempt >= params.open_retries {
+                    return Err(format!("Could not open serial port: {}", err));
+                }
+                attempt += 1;
+                warn("Could not open serial port", format!("{}, retry {} of {} in {} ms", err,
+                    attempt, params.open_retries, OPEN_RETRY_DELAY_MS));
+                thread::sleep(Duration::from_millis(OPEN_RETRY_DELAY_MS));
             }
         }
     }
-    // Print a summary of what happened
-    if num_failed > 0 {
-        // Print one empty line to separate the summary from the previous failures
-        println!();
-        println!("{}", fail.paint(format!("Failed {} of {} tests", num_failed, params.reps)));
-    // else all tests passed
-    } else if !params.show_all {
-        if !params.no_socket {
-            println!("{}", success.paint(format!("Passed all {} tests", params.reps)));
-        } else {
-            println!("{}", success.paint(format!("Ran {} tests (No socket open)", params.reps)));
+}
+
+/// Bind a socket to the test system.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+///
+/// # Returns
+///
+/// The socket to receive packets on, with its membership in the multicast group if one is joined,
+/// or an error message. The group is left when the membership is dropped.
+fn bind_socket(params: &Params) -> Result<(UdpSocket, Option<Membership>), String> {
+    // Multicast traffic is not addressed to the host IP, so the socket must listen on all
+    // addresses and join the group on the host interface instead
+    let socket_ip = match params.multicast {
+        Some(_) => Ipv4Addr::UNSPECIFIED,
+        None => Ipv4Addr::from(params.bind_ip())
+    };
+    let socket_addr = SocketAddrV4::new(socket_ip, params.dest_port);
+    // Retry with an exponential backoff in case a previous run still holds the address
+    let mut attempt = 0;
+    let s = loop {
+        match socket::bind(socket_addr) {
+            Ok(s) => break s,
+            Err(err) => {
+                if attempt >= params.bind_retries {
+                    return Err(format!("Could not open socket: {}", err));
+                }
+                let delay = BIND_RETRY_DELAY_MS << attempt.min(6);
+                warn("Could not open socket", format!("{}, retrying in {} ms", err, delay));
+                thread::sleep(Duration::from_millis(delay));
+                attempt += 1;
+            }
+        }
+    };
+    s.set_read_timeout(Some(params.timeout))
+        .map_err(|err| format!("Could not set socket read timeout: {}", err))?;
+    if let Some(size) = params.rcvbuf {
+        match socket::set_recv_buffer(&s, size) {
+            Ok(applied) => {
+                if applied < size {
+                    warn("Receive buffer was limited", format!(
+                        "{} bytes were requested, but {} bytes were applied", size, applied));
+                } else {
+                    outln!("Receive buffer is {} bytes", applied);
+                }
+            },
+            Err(err) => return Err(format!("Could not set socket receive buffer: {}", err))
+        }
+    }
+    let membership = match params.multicast {
+        Some(group) => Some(Membership::join(&s, Ipv4Addr::from(group),
+            Ipv4Addr::from(params.membership_ip()))
+            .map_err(|err| format!("Could not join multicast group: {}", err))?),
+        None => None
+    };
+    Ok((s, membership))
+}
+
+/// Send a priming datagram to the FPGA so that the host resolves the MAC address of the FPGA and
+/// the switches learn the host before the first test. Any failure is only a warning because the
+/// tests can still run without it.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `socket` - The socket to send from, or `None` to use a temporary socket.
+fn arp_warmup(params: &Params, socket: Option<&UdpSocket>) {
+    let temp;
+    let s = match socket {
+        Some(s) => s,
+        None => match UdpSocket::bind((Ipv4Addr::from(params.bind_ip()), 0)) {
+            Ok(s) => {
+                temp = s;
+                &temp
+            },
+            Err(err) => return warn("Could not send ARP warmup", err)
+        }
+    };
+    if let Err(err) = s.send_to(&[], (Ipv4Addr::from(params.src_ip), params.src_port)) {
+        return warn("Could not send ARP warmup", err);
+    }
+    // Give the address resolution time to finish before the tests start
+    thread::sleep(Duration::from_millis(ARP_WARMUP_DELAY_MS));
+}
+
+/// Warn about addresses that will likely stop packets from reaching the host.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters for the device.
+fn check_addresses(params: &Params) {
+    let bind_ip = Ipv4Addr::from(params.bind_ip());
+    if !params.has_same_subnet() {
+        warn("Different subnets", format!(
+            "src {} and bind {} are on different subnets, so packets may not be received",
+            params.src_ip_string(), bind_ip));
+    }
+    // Multicast packets are sent to the group instead of the destination
+    if params.multicast.is_none() && params.bind_ip() != params.dest_ip {
+        warn("Destination is not the bind address", format!(
+            "The FPGA sends to {}, but the socket is bound to {}", params.dest_ip_string(),
+            bind_ip));
+    }
+    // A multicast stream is sent to the MAC address of the group
+    if !params.allow_group_mac && params.multicast.is_none() {
+        check_mac("destination", params.dest_mac, &params.dest_mac_string());
+    }
+}
+
+/// Warn about a MAC address that is a multicast or broadcast address, which is usually a typing
+/// mistake that stops unicast packets from arriving.
+///
+/// # Arguments
+///
+/// * `name` - The name of the address, such as `source`.
+/// * `mac` - The MAC address.
+/// * `mac_string` - The MAC address as text.
+fn check_mac(name: &str, mac: u64, mac_string: &str) {
+    if mac == 0xFFFF_FFFF_FFFF {
+        warn("Group MAC address", format!("{} MAC {} is the broadcast address", name,
+            mac_string));
+    } else if mac & (1 << 40) != 0 {
+        warn("Group MAC address", format!("{} MAC {} has the multicast bit set", name,
+            mac_string));
+    }
+}
+
+/// Run the failure hook, unless it has already run as many times as allowed. The hook runs to
+/// completion before testing continues, so that it can capture the state at the failure.
+///
+/// # Arguments
+///
+/// * `command` - The shell command to run.
+/// * `limit` - The number of times that the hook may run across all devices.
+/// * `label` - The name of the device, which is empty when there is only one device.
+/// * `rep` - The number of the failed test.
+/// * `offset` - The offset of the first byte that differs, if the payload was wrong.
+fn run_hook(command: &str, limit: usize, label: &str, rep: usize, offset: Option<usize>) {
+    if HOOK_RUNS.fetch_add(1, Ordering::SeqCst) >= limit {
+        return;
+    }
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let status = Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .env("ETHER_DEVICE", label)
+        .env("ETHER_REP", rep.to_string())
+        .env("ETHER_OFFSET", offset.map_or(String::new(), |o| o.to_string()))
+        .status();
+    match status {
+        Ok(status) => if !status.success() {
+            warn("Failure hook failed", status)
+        },
+        Err(err) => warn("Could not run failure hook", err)
+    }
+}
+
+/// Write the script that runs a failed test again, unless a script has already been written for
+/// an earlier failure on any device.
+///
+/// # Arguments
+///
+/// * `path` - The path of the script.
+/// * `params` - The test parameters.
+/// * `test_case` - The failed test.
+/// * `rep` - The number of the failed test.
+/// * `msg` - The failure message.
+fn write_repro(path: &str, params: &Params, test_case: &TestCase, rep: usize, msg: &str) {
+    if REPRO_WRITTEN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let args: Vec<String> = env::args().collect();
+    let args = repro::pin_args(&args, test_case.seed, test_case.gen);
+    let note = format!("Failed {} ({}): {}", rep, case_string(test_case), msg);
+    if let Err(msg) = repro::write(path, &args, &params.from_env, &note) {
+        warn("Could not write repro script", msg)
+    }
+}
+
+/// Get the test case of the next random test. A continuous run carries the payload sequence of
+/// the previous test on, like the counter in the firmware, and wraps where the counter does.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `prev` - The test case of the previous test, or `None` for the first test.
+/// * `rng` - The random number generator of the run.
+///
+/// # Returns
+///
+/// The test case.
+fn next_test_case<'a, R: Rng>(params: &'a Params, prev: Option<&TestCase<'a>>, rng: &mut R)
+    -> TestCase<'a> {
+    match prev {
+        Some(prev) if params.continuous => prev.next(),
+        _ => TestCase::new(params, rng)
+    }
+}
+
+/// Run all of the tests on one device.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters for the device.
+/// * `label` - The name of the device that is printed before each result, or an empty string
+///   when there is only one device.
+///
+/// # Returns
+///
+/// The outcome of the tests or an error message.
+fn run(params: &Params, label: &str) -> Result<Outcome, String> {
+    let mut port = open_port(params)?;
+    // A round robin receives on one socket for each destination
+    let bound: Vec<(UdpSocket, Option<Membership>)> = if params.no_socket {
+        vec![]
+    } else if params.round_robin {
+        params.device_params().iter().map(bind_socket).collect::<Result<_, _>>()?
+    } else {
+        vec![bind_socket(params)?]
+    };
+    // The memberships leave the multicast group when they are dropped after the tests
+    let (sockets, _memberships): (Vec<UdpSocket>, Vec<Option<Membership>>) =
+        bound.into_iter().unzip();
+    run_tests(params, label, &mut *port, &sockets, &mut io::stdout())
+}
+
+/// The state that carries from one test to the next while the tests run on one device.
+struct Tester<'a> {
+    /// The test parameters for the device.
+    params: &'a Params,
+
+    /// The name of the device, which is empty when there is only one device.
+    label: &'a str,
+
+    /// The name of the device as it is printed before each result.
+    prefix: String,
+
+    /// The port to write serial frames to.
+    port: &'a mut dyn Write,
+
+    /// The socket to receive packets on for each destination, which is empty when no socket is
+    /// used.
+    sockets: &'a [UdpSocket],
+
+    /// Where to print the result of each test.
+    out: &'a mut dyn Write,
+
+    /// The socket that packets are read from instead of the UDP socket in raw mode.
+    raw_socket: Option<RawSocket>,
+
+    /// The socket that shows where a packet went when it did not arrive.
+    sniffer: Option<RawSocket>,
+
+    /// The log of the events of the run.
+    event_log: Option<EventLog>,
+
+    /// The file that the test cases are recorded to.
+    recorder: Option<Recorder>,
+
+    /// The file that the received packets are written to.
+    payload_log: Option<PayloadLog>,
+
+    /// The serial frame of the current test.
+    frame: Vec<u8>,
+
+    /// The buffer that packets are received into.
+    buf: Vec<u8>,
+
+    /// The buffer that the sniffer receives packets into.
+    sniff_buf: Vec<u8>,
+
+    /// The offset of the payload in a packet, which is after the headers of a raw frame.
+    seq_start: usize,
+
+    /// The offset of the FPGA timestamps in a packet, if the FPGA sends them.
+    stamp_start: Option<usize>,
+
+    /// The byte order of the payload words.
+    word_endian: Endian,
+
+    /// Indicates that the byte order of the payload words is known.
+    endian_found: bool,
+
+    /// The hash of the last packet that was read.
+    prev_hash: Option<u64>,
+
+    /// The number of packets that the operating system had dropped before the tests.
+    os_drops_start: Option<u64>,
+
+    /// The time that the tests started.
+    loop_start: Instant,
+
+    /// The time that the last packet was received.
+    last_recv: Instant,
+
+    /// The outcome of the tests so far.
+    outcome: Outcome
+}
+
+/// What was seen during one test besides whether it passed.
+struct Trial {
+    /// The number of the test, which starts at one.
+    rep: usize,
+
+    /// The destination of the test, which is always the first unless the tests are a round robin.
+    dest: usize,
+
+    /// The time that the test started.
+    start: Instant,
+
+    /// The time of day that the test started.
+    start_time: SystemTime,
+
+    /// Indicates that a bit of the serial frame was flipped.
+    corrupt: bool,
+
+    /// The offset of the first wrong byte, if the packet had the wrong payload.
+    offset: Option<usize>,
+
+    /// The signature of the wrong payload, if any.
+    signature: Option<Signature>,
+
+    /// The time from writing the serial frame to receiving the packet, if one arrived.
+    latency: Option<Duration>,
+
+    /// The bit errors and the bits that were compared. Only the packet that the test ends on
+    /// counts toward the bit error rate.
+    bit_count: Option<(u64, u64)>,
+
+    /// The FPGA and network latencies of the packet that the test ends on.
+    split_latency: Option<(Duration, Duration)>,
+
+    /// Indicates that no packet arrived before the timeout.
+    timed_out: bool
+}
+
+impl Trial {
+    /// Start a test.
+    ///
+    /// # Arguments
+    ///
+    /// * `rep` - The number of the test, which starts at one.
+    /// * `dest` - The destination of the test.
+    ///
+    /// # Returns
+    ///
+    /// The test with nothing seen yet.
+    fn new(rep: usize, dest: usize) -> Trial {
+        Trial {
+            rep,
+            dest,
+            start: Instant::now(),
+            start_time: SystemTime::now(),
+            corrupt: false,
+            offset: None,
+            signature: None,
+            latency: None,
+            bit_count: None,
+            split_latency: None,
+            timed_out: false
+        }
+    }
+}
+
+/// The kind of serial frame that a test sent.
+enum Sent<'a> {
+    /// A whole frame, which must produce the expected packet.
+    Whole,
+
+    /// A frame that was cut short, which must not produce a packet.
+    Malformed,
+
+    /// A frame with a flipped bit, which must be rejected or produce the packet of the test case
+    /// that it decodes to.
+    Corrupted(Option<TestCase<'a>>)
+}
+
+/// The way that a packet failed a test.
+enum Failure {
+    /// The packet is bad in a way that reading another packet cannot fix.
+    Bad(String),
+
+    /// The packet came from another source.
+    Source,
+
+    /// The packet had the wrong payload, with the message that describes it.
+    Payload(Mismatch, String)
+}
+
+impl<'a> Tester<'a> {
+    /// Open the logs and sockets that the tests need and warm up the route to the FPGA.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The test parameters for the device.
+    /// * `label` - The name of the device that is printed before each result, or an empty string
+    ///   when there is only one device.
+    /// * `port` - The port to write serial frames to.
+    /// * `sockets` - The socket to receive packets on for each destination, which is empty when
+    ///   no socket is used.
+    /// * `out` - Where to print the result of each test.
+    ///
+    /// # Returns
+    ///
+    /// The tester or an error message.
+    fn new(params: &'a Params, label: &'a str, port: &'a mut dyn Write, sockets: &'a [UdpSocket],
+        out: &'a mut dyn Write) -> Result<Tester<'a>, String> {
+        let mut event_log = params.event_log.as_ref()
+            .map(|path| EventLog::create(path, label)
+                .map_err(|msg| format!("Could not create event file: {}", msg)))
+            .transpose()?;
+        if params.arp_warmup {
+            if let Some(ref mut l) = event_log {
+                l.warmup(SystemTime::now())
+                    .map_err(|msg| format!("Could not write event file: {}", msg))?;
+            }
+            arp_warmup(params, sockets.first());
+        }
+        let recorder = params.record.as_ref()
+            .map(|path| Recorder::create(path)
+                .map_err(|msg| format!("Could not create record file: {}", msg)))
+            .transpose()?;
+        let payload_log = params.payload_out.as_ref()
+            .map(|path| PayloadLog::create(path, params.payload_out_lengths)
+                .map_err(|msg| format!("Could not create payload file: {}", msg)))
+            .transpose()?;
+        let headers = if params.raw {
+            raw::MAX_EXTRA_LEN
+        } else if params.check_checksums {
+            checksum::MAX_HEADER_LEN
+        } else {
+            0
+        };
+        // The UDP socket stays open so that the host does not answer the packets with an ICMP
+        // error
+        let raw_socket = if params.raw && !sockets.is_empty() {
+            let interface = params.interface.as_ref().map(|(name, _)| name.as_str());
+            let r = RawSocket::open(interface, (params.dest_ip, params.dest_port))
+                .map_err(|err| format!("Could not open raw socket: {}", err))?;
+            r.set_read_timeout(params.timeout)
+                .map_err(|err| format!("Could not set raw socket read timeout: {}", err))?;
+            Some(r)
+        } else {
+            None
+        };
+        // The sequence number is at the start of the payload, after the headers of a raw frame
+        let seq_start = if params.raw { raw::HEADER_LEN } else { 0 };
+        // The FPGA puts its timestamps at the end of the generated bytes
+        let stamp_start = if params.fpga_timestamp {
+            Some(seq_start + params.payload_prefix.len() + params.bytes - test_case::TIMESTAMP_LEN)
+        } else {
+            None
+        };
+        let sniffer = if params.diagnose_port && !sockets.is_empty() {
+            let interface = params.interface.as_ref().map(|(name, _)| name.as_str());
+            match RawSocket::sniff(interface, params.src_ip) {
+                Ok(s) => Some(s),
+                Err(err) => {
+                    warn("Could not open socket to diagnose ports", err);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let os_drops_start = os_drops(params, sockets);
+        // The setup is done, so the throughput is timed from here to the last received packet
+        let loop_start = Instant::now();
+        Ok(Tester {
+            params,
+            label,
+            prefix: if label.is_empty() { String::new() } else { format!("[{}] ", label) },
+            port,
+            sockets,
+            out,
+            raw_socket,
+            sniffer,
+            event_log,
+            recorder,
+            payload_log,
+            // The buffers are reused for every test so that fast runs do not allocate for each
+            // test
+            frame: Vec::with_capacity(frame::frame_len(&params.frame_layout)),
+            // The spare byte keeps a packet that is too long from being cut to the expected length
+            buf: vec![0; params.packet_len() + headers + 1],
+            sniff_buf: vec![0; raw::MAX_EXTRA_LEN],
+            seq_start,
+            stamp_start,
+            // The byte order of the words is found from the packets when it is not known
+            word_endian: params.frame_endian,
+            endian_found: !params.compare_endian_auto,
+            prev_hash: None,
+            os_drops_start,
+            loop_start,
+            last_recv: loop_start,
+            outcome: Outcome {
+                label: label.to_string(),
+                candidate_matches: vec![0; params.expect_any.len()],
+                dest_results: if params.round_robin {
+                    vec![(0, 0); params.devices.len()]
+                } else {
+                    vec![]
+                },
+                ..Outcome::default()
+            }
+        })
+    }
+
+    /// Wait until a test is due at the rate of the tests.
+    ///
+    /// # Arguments
+    ///
+    /// * `rep` - The number of the test, which starts at one.
+    /// * `rate` - The number of tests per second.
+    fn pace(&mut self, rep: usize, rate: f64) {
+        // Each test starts at its own time from the start so that slow tests do not drift the rate
+        let due = self.loop_start + Duration::from_secs_f64((rep - 1) as f64 / rate);
+        let now = Instant::now();
+        if now < due {
+            thread::sleep(due - now);
+        } else if now - due > Duration::from_secs_f64(1.0 / rate) {
+            self.outcome.behind_rate += 1;
+            debug!("{}Test {} started {} us late", self.prefix, rep, (now - due).as_micros());
+        }
+    }
+
+    /// Set the read timeout of a socket and of the raw socket that is read instead, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The socket.
+    /// * `timeout` - The read timeout.
+    ///
+    /// # Returns
+    ///
+    /// Nothing, or an error message.
+    fn set_read_timeout(&self, s: &UdpSocket, timeout: Duration) -> Result<(), String> {
+        s.set_read_timeout(Some(timeout))
+            .map_err(|err| format!("Could not set socket read timeout: {}", err))?;
+        if let Some(ref r) = self.raw_socket {
+            r.set_read_timeout(timeout)
+                .map_err(|err| format!("Could not set raw socket read timeout: {}", err))?;
+        }
+        Ok(())
+    }
+
+    /// Receive a packet from the raw socket in raw mode, or from a UDP socket otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The UDP socket.
+    /// * `buf` - The buffer to receive the packet into.
+    ///
+    /// # Returns
+    ///
+    /// The size and source of the packet, or the error of the read.
+    fn recv_from(&self, s: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        match self.raw_socket {
+            Some(ref r) => r.recv_from(buf),
+            None => s.recv_from(buf)
+        }
+    }
+
+    /// Run one test by sending its serial frame and checking the packet that it produces.
+    ///
+    /// # Arguments
+    ///
+    /// * `test_case` - The test case, which gets the time that it is sent.
+    /// * `trial` - What is seen during the test.
+    /// * `rng` - The generator of the random values of the run.
+    ///
+    /// # Returns
+    ///
+    /// Nothing if the test passed, or the reason that it failed.
+    fn test<R: Rng>(&mut self, test_case: &mut TestCase<'a>, trial: &mut Trial, rng: &mut R)
+        -> Result<(), String> {
+        // Only the packets of this test can show where its packet went
+        if let Some(ref s) = self.sniffer {
+            while s.try_recv_dest(&mut self.sniff_buf).is_ok() {}
+        }
+        let (sent, payload) = self.send(test_case, trial, rng)?;
+        let sockets = self.sockets;
+        let s = match sockets.get(trial.dest) {
+            Some(s) => s,
+            // If the ethernet is not used then the test automatically passes
+            None => return Ok(())
+        };
+        // The buffer is taken while the packet in it is checked, since the checks update the
+        // tester
+        let mut buf = std::mem::take(&mut self.buf);
+        let result = match sent {
+            Sent::Malformed => self.receive_malformed(s, &mut buf),
+            Sent::Corrupted(Some(ref c)) => self.receive_corrupted(s, &mut buf, c),
+            _ => self.receive(s, &mut buf, test_case, payload, trial)
+        };
+        self.buf = buf;
+        result
+    }
+
+    /// Make the serial frame of a test and write it to the port. The frame is cut short or has a
+    /// bit flipped when the parameters ask for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `test_case` - The test case, which gets the time that it is sent.
+    /// * `trial` - What is seen during the test.
+    /// * `rng` - The generator of the random values of the run.
+    ///
+    /// # Returns
+    ///
+    /// The kind of frame that was sent and the expected payload, or an error message.
+    fn send<R: Rng>(&mut self, test_case: &mut TestCase<'a>, trial: &mut Trial, rng: &mut R)
+        -> Result<(Sent<'a>, Vec<u8>), String> {
+        let params = self.params;
+        test_case.timestamp = self.loop_start.elapsed().as_micros() as u32;
+        test_case.write_bytes(&mut self.frame);
+        // A frame that is cut short must not produce a packet
+        let malformed = params.fuzz_malformed && self.frame.len() > 1
+            && rng.gen::<u8>() < MALFORMED_CHANCE;
+        if malformed {
+            let len = test_case::gen_range_usize(rng, 1, self.frame.len());
+            self.frame.truncate(len);
+            debug!("{}Sending malformed frame of {} bytes", self.prefix, len);
+        }
+        // A frame with a flipped bit must be rejected or produce the packet that it describes
+        // The chance is drawn from a u32 for the same reason as in `test_case::gen_u64`
+        trial.corrupt = !malformed && params.corrupt_frame
+            .is_some_and(|rate| (rng.gen::<u32>() as f64) < rate * (1u64 << 32) as f64);
+        let sent = if malformed {
+            Sent::Malformed
+        } else if trial.corrupt {
+            let bit = test_case::gen_range_usize(rng, 0, 8 * self.frame.len());
+            self.frame[bit / 8] ^= 0x80 >> (bit % 8);
+            debug!("{}Sending frame with bit {} flipped", self.prefix, bit);
+            Sent::Corrupted(DecodedFrame::from_bytes(&self.frame, &params.frame_layout,
+                params.frame_endian).ok().map(|decoded| TestCase::from_decoded(params, &decoded)))
+        } else {
+            Sent::Whole
+        };
+        // The payload is made before the frame is written, so that a test whose payload cannot be
+        // made fails without leaving a packet behind for the next test
+        let payload = test_case.expected_in(self.word_endian)?;
+        write_frame(self.port, &self.frame)?;
+        debug!("{}Wrote {} serial bytes", self.prefix, self.frame.len());
+        trace!("{}Serial frame: {}", self.prefix, hex::string(&self.frame));
+        Ok((sent, payload))
+    }
+
+    /// Read the packet of a malformed frame, which must not produce one.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The socket to read from.
+    /// * `buf` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// Nothing if no packet arrived, or an error message.
+    fn receive_malformed(&mut self, s: &UdpSocket, buf: &mut [u8]) -> Result<(), String> {
+        match self.recv_from(s, buf) {
+            Ok((size, socket_addr)) => {
+                self.outcome.packets_received += 1;
+                self.outcome.bytes_received += size as u64;
+                Err(format!("Received {} bytes from {} for a malformed frame", size, socket_addr))
+            },
+            Err(ref err) if is_timeout(err) => Ok(()),
+            Err(err) => Err(format!("Could not read socket: {}", err))
+        }
+    }
+
+    /// Read the packet of a corrupted frame, which must either not produce one or produce the
+    /// packet of the test case that the frame decodes to.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The socket to read from.
+    /// * `buf` - The buffer to read into.
+    /// * `c` - The test case that the corrupted frame decodes to.
+    ///
+    /// # Returns
+    ///
+    /// Nothing if the FPGA rejected or tolerated the frame, or an error message.
+    fn receive_corrupted(&mut self, s: &UdpSocket, buf: &mut [u8], c: &TestCase)
+        -> Result<(), String> {
+        let params = self.params;
+        match self.recv_from(s, buf) {
+            Ok((size, socket_addr)) => {
+                self.outcome.packets_received += 1;
+                self.outcome.bytes_received += size as u64;
+                let actual = &buf[..size];
+                let want = SocketAddr::from((Ipv4Addr::from(c.src_ip), c.src_port));
+                if params.check_source && socket_addr != want {
+                    return Err(format!("Packet from {} for a corrupted frame, expected source {}",
+                        socket_addr, want));
+                }
+                let expected = c.expected_in(self.word_endian)?;
+                match compare_payload(params, &expected, actual) {
+                    Ok(_) => {
+                        self.outcome.frames_tolerated += 1;
+                        info!("{}FPGA tolerated the corrupted frame", self.prefix);
+                        Ok(())
+                    },
+                    Err(mismatch) => Err(format!("Packet does not match the corrupted frame: {}",
+                        mismatch))
+                }
+            },
+            Err(ref err) if is_timeout(err) => {
+                self.outcome.frames_rejected += 1;
+                info!("{}FPGA rejected the corrupted frame", self.prefix);
+                Ok(())
+            },
+            Err(err) => Err(format!("Could not read socket: {}", err))
+        }
+    }
+
+    /// Read the packet of a test and verify it. Another packet is read after a wrong packet and
+    /// the frame is sent again after a timeout when the parameters allow it.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The socket to read from.
+    /// * `buf` - The buffer to read into.
+    /// * `test_case` - The test case of the test.
+    /// * `payload` - The expected payload.
+    /// * `trial` - What is seen during the test.
+    ///
+    /// # Returns
+    ///
+    /// Nothing if the test passed, or the reason that it failed.
+    fn receive(&mut self, s: &UdpSocket, buf: &mut [u8], test_case: &TestCase, payload: Vec<u8>,
+        trial: &mut Trial) -> Result<(), String> {
+        let params = self.params;
+        let expected = if params.raw { raw::frame(test_case, &payload) } else { payload };
+        // The CRC is computed once for every packet that is read for the test
+        let expected_crc = if params.compare_hash {
+            let n = params.compare_bytes.unwrap_or(expected.len());
+            Some(crc::crc32(&expected[..n.min(expected.len())]))
+        } else {
+            None
+        };
+        let src_addr = SocketAddr::from((Ipv4Addr::from(test_case.src_ip), test_case.src_port));
+        let mut rereads_left = params.reread;
+        let mut retries_left = params.timeout_retries;
+        loop {
+            let (size, socket_addr) = match self.read_packet(s, buf, test_case, &expected, trial)? {
+                Ok(r) => r,
+                Err(err) => {
+                    let retry = params.timeout_policy == TimeoutPolicy::Retry;
+                    if is_timeout(&err) && retry && retries_left > 0 {
+                        retries_left -= 1;
+                        self.outcome.resent += 1;
+                        debug!("{}Sending the serial frame again after a timeout", self.prefix);
+                        write_frame(self.port, &self.frame)?;
+                        continue;
+                    }
+                    trial.timed_out = is_timeout(&err);
+                    self.outcome.dropped += 1;
+                    let msg = format!("Could not read socket: {}", err);
+                    let listening = (params.multicast.unwrap_or(test_case.dest_ip),
+                        test_case.dest_port);
+                    let wrong = match self.sniffer {
+                        Some(ref s) if trial.timed_out => {
+                            wrong_dest(s, &mut self.sniff_buf, listening)
+                        },
+                        _ => None
+                    };
+                    return Err(match wrong {
+                        Some(diagnosis) => format!("{}\n{}", msg, diagnosis),
+                        None => msg
+                    });
+                }
+            };
+            let packet = &buf[..size];
+            match self.verify(test_case, &expected, expected_crc, packet, socket_addr, trial) {
+                Ok(_) => return Ok(()),
+                Err(Failure::Bad(msg)) => return Err(msg),
+                Err(_) if rereads_left > 0 => {
+                    rereads_left -= 1;
+                    self.outcome.rereads += 1;
+                    debug!("{}Reading again after a wrong packet", self.prefix);
+                },
+                Err(Failure::Source) => return Err(format!("Packet from {}, expected source {}",
+                    socket_addr, src_addr)),
+                Err(Failure::Payload(mismatch, msg)) => {
+                    self.outcome.corrupted += 1;
+                    trial.offset = Some(mismatch.offset);
+                    trial.signature = Some(mismatch.signature());
+                    if self.outcome.worst.map_or(true, |(_, count)| mismatch.count > count) {
+                        self.outcome.worst = Some((trial.rep, mismatch.count));
+                    }
+                    return Err(msg);
+                }
+            }
+        }
+    }
+
+    /// Read the next packet of a test, skipping late packets from earlier tests and duplicates.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The socket to read from.
+    /// * `buf` - The buffer to read into.
+    /// * `test_case` - The test case of the test.
+    /// * `expected` - The expected packet.
+    /// * `trial` - What is seen during the test.
+    ///
+    /// # Returns
+    ///
+    /// The size and source of the packet or the error of the read, or an error message if the
+    /// packet could not be written to the payload file.
+    fn read_packet(&mut self, s: &UdpSocket, buf: &mut [u8], test_case: &TestCase,
+        expected: &[u8], trial: &mut Trial) -> Result<io::Result<(usize, SocketAddr)>, String> {
+        let params = self.params;
+        loop {
+            let received = self.recv_from(s, buf);
+            if let Ok((size, socket_addr)) = received {
+                self.outcome.packets_received += 1;
+                self.outcome.bytes_received += size as u64;
+                *self.outcome.sizes.entry(size).or_insert(0) += 1;
+                self.last_recv = Instant::now();
+                trial.latency = Some(self.last_recv - trial.start);
+                debug!("{}Received {} bytes from {}", self.prefix, size, socket_addr);
+                trace!("{}Payload: {}", self.prefix, hex::string(&buf[..size]));
+                if params.echo {
+                    outln!(to self.out, "{}Rep {} ({} bytes): {}", self.prefix, trial.rep, size,
+                        hex::string(&buf[..size]));
+                }
+                // A late packet from an earlier test would fail every test after it
+                if params.seqnum {
+                    let payload = &buf[self.seq_start.min(size)..size];
+                    if let Some(seq) = late_seq(payload, test_case.seq, self.outcome.num_run) {
+                        debug!("{}Skipped late packet {}", self.prefix, seq);
+                        continue;
+                    }
+                }
+                // A repeat of the last packet is only a duplicate when it is not also the
+                // expected packet, such as with a fixed seed and generator
+                // The headers are left out of both sides so that only the payloads are compared
+                if params.detect_duplicates {
+                    let payload = packet_payload(params, &buf[..size]);
+                    let mut hasher = DefaultHasher::new();
+                    payload.hash(&mut hasher);
+                    let hash = hasher.finish();
+                    if self.prev_hash == Some(hash) && payload != &expected[self.seq_start..] {
+                        self.outcome.duplicates += 1;
+                        outln!(to self.out, "{}Duplicate packet detected at rep {}", self.prefix,
+                            trial.rep);
+                        continue;
+                    }
+                    self.prev_hash = Some(hash);
+                }
+                if let Some(ref mut l) = self.payload_log {
+                    l.write(&buf[..size])
+                        .map_err(|msg| format!("Could not write payload file: {}", msg))?;
+                }
+            }
+            return Ok(received);
+        }
+    }
+
+    /// Check a packet against the expected packet of a test.
+    ///
+    /// # Arguments
+    ///
+    /// * `test_case` - The test case of the test.
+    /// * `expected` - The expected packet.
+    /// * `expected_crc` - The CRC of the expected packet when packets are compared by hash.
+    /// * `packet` - The packet that was received.
+    /// * `socket_addr` - The source of the packet.
+    /// * `trial` - What is seen during the test.
+    ///
+    /// # Returns
+    ///
+    /// Nothing if the packet matches, or how it failed.
+    fn verify(&mut self, test_case: &TestCase, expected: &[u8], expected_crc: Option<u32>,
+        packet: &[u8], socket_addr: SocketAddr, trial: &mut Trial) -> Result<(), Failure> {
+        let params = self.params;
+        // The headers are checked and removed so that only the payload is compared
+        let actual = if params.check_checksums {
+            match checksum::strip(packet) {
+                Ok(payload) => payload,
+                Err(msg) => {
+                    self.outcome.checksum_errors += 1;
+                    return Err(Failure::Bad(msg));
+                }
+            }
+        } else {
+            packet
+        };
+        // A multicast packet is addressed to the group rather than the host
+        if params.check_dest {
+            let got = checksum::dest(packet);
+            let want = (params.multicast.unwrap_or(test_case.dest_ip), test_case.dest_port);
+            if got != want {
+                return Err(Failure::Bad(format!("Destination mismatch: packet addressed to \
+                    {}:{}, expected {}:{}", Ipv4Addr::from(got.0), got.1, Ipv4Addr::from(want.0),
+                    want.1)));
+            }
+        }
+        let size = actual.len();
+        // A wrong length field is clearer as the two lengths than as wrong bytes
+        if let Some((at, width)) = params.verify_length_field {
+            let field = actual.get(at..at + width).ok_or_else(|| Failure::Bad(format!(
+                "Length field at byte {} is past the end of the {} byte packet", at, size)))?;
+            let length = frame::read_uint(field, params.frame_endian);
+            if length != size as u64 {
+                return Err(Failure::Bad(format!("Length field says {} but packet is {} bytes",
+                    length, size)));
+            }
+        }
+        // The timestamps are filled in by the FPGA, so they are taken from the packet instead of
+        // being compared
+        let stamp_start = self.stamp_start;
+        let stamped;
+        let expected: &[u8] = match stamp_start {
+            Some(at) if size >= at + test_case::TIMESTAMP_LEN => {
+                let stamp = &actual[at..at + test_case::TIMESTAMP_LEN];
+                let (timestamp, fpga_us) = test_case::read_time(stamp);
+                let now = self.last_recv.duration_since(self.loop_start).as_micros() as u32;
+                let round_trip = now.wrapping_sub(timestamp);
+                trial.split_latency = Some((
+                    Duration::from_micros(fpga_us.into()),
+                    Duration::from_micros(round_trip.saturating_sub(fpga_us).into())
+                ));
+                let mut v = expected.to_vec();
+                v[at..at + test_case::TIMESTAMP_LEN].copy_from_slice(stamp);
+                stamped = v;
+                &stamped
+            },
+            _ => expected
+        };
+        // A packet from another source is not compared, since it cannot be the packet from the
+        // FPGA
+        let src_addr = SocketAddr::from((Ipv4Addr::from(test_case.src_ip), test_case.src_port));
+        let check_source = params.check_source || (params.fuzz && !params.loopback);
+        let mut result = if check_source && socket_addr != src_addr {
+            Err(None)
+        } else {
+            let n = params.compare_bytes.unwrap_or(size).min(size);
+            let same_crc = crc_matches(params, expected, expected_crc, actual);
+            if !same_crc {
+                self.outcome.deviation = self.outcome.deviation
+                    .max(max_deviation(expected, &actual[..n]));
+            }
+            if params.ber {
+                let bits = 8 * n.min(expected.len()) as u64;
+                let errors = if same_crc { 0 } else { compare::bit_errors(expected, &actual[..n]) };
+                trial.bit_count = Some((errors, bits));
+            }
+            if same_crc {
+                Ok(())
+            } else {
+                compare_payload(params, expected, actual).map_err(Some)
+            }
+        };
+        // Another payload is framed and stamped like the expected one to compare it
+        let matches_other = |payload: Vec<u8>| {
+            let mut payload = if params.raw { raw::frame(test_case, &payload) } else { payload };
+            if let Some(at) = stamp_start {
+                let end = at + test_case::TIMESTAMP_LEN;
+                if size >= end {
+                    payload[at..end].copy_from_slice(&actual[at..end]);
+                }
+            }
+            compare_payload(params, &payload, actual).is_ok()
+        };
+        // Until the byte order of the words is found, a wrong payload is also compared with its
+        // words in the other byte order
+        if !self.endian_found {
+            if let Err(Some(_)) = result {
+                let other = match self.word_endian {
+                    Endian::Big => Endian::Little,
+                    Endian::Little => Endian::Big
+                };
+                if matches_other(test_case.expected_in(other).map_err(Failure::Bad)?) {
+                    self.word_endian = other;
+                    result = Ok(());
+                }
+            }
+            if result.is_ok() {
+                self.endian_found = true;
+                let name = match self.word_endian {
+                    Endian::Big => "big",
+                    Endian::Little => "little"
+                };
+                outln!(to self.out, "{}Payload words are {} endian", self.prefix, name);
+            }
+        }
+        // A wrong payload may still be one of the other candidates
+        if !params.expect_any.is_empty() {
+            let mut matched = if result.is_ok() { Some(0) } else { None };
+            if let Err(Some(_)) = result {
+                matched = (1..params.expect_any.len())
+                    .find(|&c| matches_other(test_case.candidate(c)));
+                if matched.is_some() {
+                    result = Ok(());
+                }
+            }
+            if let Some(c) = matched {
+                self.outcome.candidate_matches[c] += 1;
+                debug!("{}Packet matched the {} payload", self.prefix,
+                    params::candidate_name(params.expect_any[c]));
+            }
+        }
+        match result {
+            Ok(_) => Ok(()),
+            Err(None) => Err(Failure::Source),
+            Err(Some(mismatch)) => {
+                let msg = mismatch_message(params, &mismatch, expected, actual);
+                Err(Failure::Payload(mismatch, msg))
+            }
+        }
+    }
+
+    /// Add the result of a test to the outcome, log it, and print it.
+    ///
+    /// # Arguments
+    ///
+    /// * `test_case` - The test case of the test.
+    /// * `result` - Nothing if the test passed, or the reason that it failed.
+    /// * `trial` - What was seen during the test.
+    ///
+    /// # Returns
+    ///
+    /// Nothing, or an error message if the event file could not be written.
+    fn account(&mut self, test_case: &TestCase, result: Result<(), String>, trial: &Trial)
+        -> Result<(), String> {
+        let params = self.params;
+        let i = trial.rep;
+        info!("{}Test {} finished in {} us", self.prefix, i, trial.start.elapsed().as_micros());
+        if let Some(latency) = trial.latency {
+            self.outcome.latencies.push(latency);
+        }
+        if let Some((errors, bits)) = trial.bit_count {
+            self.outcome.bit_errors += errors;
+            self.outcome.bits_compared += bits;
+        }
+        if let Some((fpga, network)) = trial.split_latency {
+            self.outcome.fpga_latencies.push(fpga);
+            self.outcome.network_latencies.push(network);
+        }
+        if let Some(r) = self.outcome.dest_results.get_mut(trial.dest) {
+            r.0 += 1;
+        }
+        if let Some(ref mut l) = self.event_log {
+            let outcome = match result {
+                Ok(_) if self.sockets.get(trial.dest).is_none() => "sent",
+                Ok(_) => "pass",
+                Err(_) if trial.timed_out => "timeout",
+                Err(_) => "fail"
+            };
+            l.rep(trial.start_time, i, test_case, outcome, trial.latency)
+                .map_err(|msg| format!("Could not write event file: {}", msg))?;
+        }
+        // Print output
+        match result {
+            Ok(_) => if params.format == Format::Tap {
+                outln!(to self.out, "ok {}", i);
+            } else if test_case.params.show_all {
+                let success = color::style(Style::new().bold().fg(Colour::Green));
+                if params.no_socket {
+                    outln!(to self.out, "{}{}", self.prefix, success.paint("(No socket open)"));
+                } else {
+                    outln!(to self.out, "{}{}", self.prefix, success.paint(format!("Passed {} ({})",
+                        i, case_string(test_case))));
+                }
+            },
+            Err(mut msg) => {
+                self.outcome.num_failed += 1;
+                if let Some(r) = self.outcome.dest_results.get_mut(trial.dest) {
+                    r.1 += 1;
+                }
+                if params.seed_sweep {
+                    self.outcome.failed_cases.push((test_case.seed, test_case.gen));
+                }
+                // The frame is needed to reproduce a failure from random addresses or bits
+                if params.fuzz || trial.corrupt {
+                    msg = format!("{}\nFrame: {}", msg, hex::string(&self.frame));
+                }
+                // A repeat of a signature is only counted so that one bug does not flood the
+                // output
+                let repeat = match trial.signature {
+                    Some(s) if params.group_failures => {
+                        let count = self.outcome.signatures.entry(s).or_insert(0);
+                        *count += 1;
+                        *count > 1
+                    },
+                    _ => false
+                };
+                if params.format == Format::Tap {
+                    outln!(to self.out, "{}", tap_failure(i, &msg));
+                } else if !repeat {
+                    let fail = color::style(Style::new().bold().fg(Colour::Red));
+                    let title = format!("Failed {} ({})", i, case_string(test_case));
+                    outln!(to self.out, "{}{}: {}", self.prefix, fail.paint(title), msg);
+                }
+                if let Some(ref path) = params.repro_script {
+                    write_repro(path, params, test_case, i, &msg);
+                }
+                if let Some(ref command) = params.on_failure {
+                    run_hook(command, params.on_failure_limit, self.label, i, trial.offset);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write out the record, payload, and event files so far.
+    ///
+    /// # Returns
+    ///
+    /// Nothing, or an error message.
+    fn flush(&mut self) -> Result<(), String> {
+        if let Some(ref mut r) = self.recorder {
+            r.flush().map_err(|msg| format!("Could not write record file: {}", msg))?;
+        }
+        if let Some(ref mut l) = self.payload_log {
+            l.flush().map_err(|msg| format!("Could not write payload file: {}", msg))?;
+        }
+        if let Some(ref mut l) = self.event_log {
+            l.flush().map_err(|msg| format!("Could not write event file: {}", msg))?;
+        }
+        Ok(())
+    }
+
+    /// End the tests.
+    ///
+    /// # Returns
+    ///
+    /// The outcome of the tests, or an error message if the files could not be written.
+    fn finish(mut self) -> Result<Outcome, String> {
+        self.flush()?;
+        self.outcome.elapsed = self.last_recv - self.loop_start;
+        // Without the handshake no frame is ever sent again
+        #[cfg(unix)]
+        {
+            self.outcome.handshake_retries = handshake::take_retries();
+        }
+        // A socket that is kept open between runs has drops from earlier runs
+        let os_drops_start = self.os_drops_start;
+        self.outcome.os_dropped = os_drops(self.params, self.sockets)
+            .and_then(|end| os_drops_start.map(|start| end.saturating_sub(start)));
+        Ok(self.outcome)
+    }
+}
+
+/// Describe a payload that does not match, with the hints that the parameters ask for.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `mismatch` - How the payload does not match.
+/// * `expected` - The expected payload.
+/// * `actual` - The payload that was received.
+///
+/// # Returns
+///
+/// The message that describes the mismatch.
+fn mismatch_message(params: &Params, mismatch: &Mismatch, expected: &[u8], actual: &[u8])
+    -> String {
+    let mut msg = match params.compare_mode {
+        CompareMode::Exact => format!("{}\n{}", mismatch,
+            compare::window(expected, actual, mismatch.offset, params.tolerance)),
+        // The offset is into the sorted bytes, so they are shown
+        mode => {
+            let n = params.compare_bytes.unwrap_or(usize::MAX);
+            let sorted_expected = compare::arrange(&expected[..n.min(expected.len())], mode);
+            let sorted_actual = compare::arrange(&actual[..n.min(actual.len())], mode);
+            format!("{} comparison: {}\n{}", compare::mode_name(mode), mismatch,
+                compare::window(&sorted_expected, &sorted_actual, mismatch.offset,
+                    params.tolerance))
+        }
+    };
+    if params.suggest_endian {
+        if let Some(hint) = swap::suggest(expected, actual) {
+            msg = format!("{}\n{}", msg, hint);
+        }
+    }
+    if params.suggest_inverted {
+        if let Some(hint) = swap::suggest_inverted(expected, actual) {
+            msg = format!("{}\n{}", msg, hint);
+        }
+    }
+    if params.detect_bitshift {
+        if let Some(hint) = swap::suggest_bitshift(expected, actual) {
+            msg = format!("{}\n{}", msg, hint);
+        }
+    }
+    if params.detect_offset {
+        if let Some(hint) = swap::suggest_offset(expected, actual) {
+            msg = format!("{}\n{}", msg, hint);
+        }
+    }
+    if params.align_check {
+        if let Some(hint) = swap::suggest_rotation(expected, actual) {
+            msg = format!("{}\n{}", msg, hint);
+        }
+    }
+    if params.diff {
+        msg = format!("{}\n{}", msg, diff::format(expected, actual));
+    }
+    msg
+}
+
+/// Run all of the tests on one device with a serial port and socket that are already open.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters for the device.
+/// * `label` - The name of the device that is printed before each result, or an empty string
+///   when there is only one device.
+/// * `port` - The port to write serial frames to.
+/// * `sockets` - The socket to receive packets on for each destination, which is empty when no
+///   socket is used.
+/// * `out` - Where to print the result of each test.
+///
+/// # Returns
+///
+/// The outcome of the tests or an error message.
+fn run_tests(params: &Params, label: &str, port: &mut dyn Write, sockets: &[UdpSocket],
+    out: &mut dyn Write) -> Result<Outcome, String> {
+    // Every random value of the run comes from one generator so that the run can be repeated
+    let mut rng = params.rng();
+    // Load the recorded test cases to replay or the sweep, which replace the random test cases
+    let mut replayed = params.replay.as_ref()
+        .map(|path| match record::replay(path, params) {
+            Ok(test_cases) => Ok(test_cases.into_iter()),
+            Err(msg) => Err(format!("Could not read replay file: {}", msg))
+        })
+        .transpose()?;
+    if params.seed_sweep {
+        replayed = Some(TestCase::sweep(params, &mut rng).into_iter());
+    }
+    let reps = replayed.as_ref().map_or(params.reps.unwrap_or(usize::MAX), |r| r.len());
+    let mut tester = Tester::new(params, label, port, sockets, out)?;
+    let deadline = params.duration.map(|d| Instant::now() + d);
+    let watchdog = params.watchdog.map(|interval| Watchdog::start(interval, &tester.prefix));
+    let mut prev_test_case: Option<TestCase> = None;
+    for i in 1..=reps {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            break;
+        }
+        if let Some(rate) = params.rate {
+            tester.pace(i, rate);
+        }
+        let dest = if params.round_robin { (i - 1) % params.devices.len() } else { 0 };
+        // Make sure that a blocking read cannot run past the end of the duration
+        if let Some(deadline) = deadline {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            if let Some(s) = sockets.get(dest) {
+                tester.set_read_timeout(s, (deadline - now).min(params.timeout))?;
+            }
+        }
+        let mut test_case = match replayed.as_mut() {
+            Some(r) => r.next().unwrap(),
+            None => next_test_case(params, prev_test_case.as_ref(), &mut rng)
+        };
+        if let Some(ref mut r) = tester.recorder {
+            r.record(&test_case).map_err(|msg| format!("Could not write record file: {}", msg))?;
+        }
+        test_case.seq = tester.outcome.num_run as u16;
+        tester.outcome.num_run += 1;
+        if params.fuzz {
+            test_case.fuzz(&mut rng);
+        }
+        if params.round_robin {
+            let device = &params.devices[dest];
+            test_case.dest_ip = device.dest_ip;
+            test_case.dest_port = device.dest_port;
+            test_case.dest_mac = device.dest_mac;
+        }
+        info!("{}Test {}: seed 0x{:02X}, gen 0x{:02X}", tester.prefix, i, test_case.seed,
+            test_case.gen);
+        let mut trial = Trial::new(i, dest);
+        let result = tester.test(&mut test_case, &mut trial, &mut rng);
+        tester.account(&test_case, result, &trial)?;
+        prev_test_case = Some(test_case);
+        let outcome = &tester.outcome;
+        if let Some(ref w) = watchdog {
+            w.finished(i, outcome.num_failed, outcome.packets_received);
+        }
+        if params.report_every.is_some_and(|n| outcome.num_run % n == 0) {
+            report_progress(&tester.prefix, outcome.num_run, outcome.num_failed,
+                tester.loop_start.elapsed());
+            // A long run that is killed keeps what it has written so far
+            tester.flush()?;
+        }
+        if trial.timed_out && params.timeout_policy == TimeoutPolicy::Abort {
+            tester.outcome.aborted = Some(i);
+            break;
+        }
+        if params.max_failures > 0 && tester.outcome.num_failed >= params.max_failures {
+            tester.outcome.stopped = true;
+            break;
+        }
+        // Nothing at all arriving is a setup problem, so the rest of the tests would be wasted
+        let outcome = &mut tester.outcome;
+        if !sockets.is_empty() && params.require_first == Some(outcome.num_run)
+            && outcome.packets_received == 0 {
+            outcome.no_packets = true;
+            break;
+        }
+    }
+    // The tests are over, so the time that the rest takes is not a stuck test
+    drop(watchdog);
+    tester.finish()
+}
+
+/// Get the number of packets that the operating system dropped for the sockets of a run.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `sockets` - The sockets of the run.
+///
+/// # Returns
+///
+/// The number of packets, or `None` if any of the sockets has no count.
+fn os_drops(params: &Params, sockets: &[UdpSocket]) -> Option<u64> {
+    // Packets are read from the raw socket in raw mode, so the UDP socket drops every packet
+    if params.raw {
+        return None;
+    }
+    sockets.iter().map(|s| socket::os_drops(s).ok().and_then(|drops| drops)).sum()
+}
+
+/// Format the seed and generator of a test, so that a failure can be run again with `--seed` and
+/// `--gen`.
+///
+/// # Arguments
+///
+/// * `test_case` - The test case.
+///
+/// # Returns
+///
+/// The seed and generator, such as `seed=0xA1 gen=0x07`.
+fn case_string(test_case: &TestCase) -> String {
+    format!("seed=0x{:02X} gen=0x{:02X}", test_case.seed, test_case.gen)
+}
+
+/// Format a failed test as a TAP line. The first line of the message describes the failure, and
+/// the rest of it follows as diagnostic lines.
+///
+/// # Arguments
+///
+/// * `rep` - The number of the test.
+/// * `msg` - The failure message.
+///
+/// # Returns
+///
+/// The lines.
+fn tap_failure(rep: usize, msg: &str) -> String {
+    let mut lines = msg.lines();
+    let mut tap = format!("not ok {} - {}", rep, lines.next().unwrap_or(""));
+    for line in lines {
+        tap = format!("{}\n# {}", tap, line);
+    }
+    tap
+}
+
+/// Print a summary of the tests so far. The line is set apart from the test results so that it
+/// is easy to find among failures.
+///
+/// # Arguments
+///
+/// * `prefix` - The prefix that is printed before the summary to show the device.
+/// * `num_run` - The number of tests that have run.
+/// * `num_failed` - The number of tests that have failed.
+/// * `elapsed` - The time since the tests started.
+fn report_progress(prefix: &str, num_run: usize, num_failed: u64, elapsed: Duration) {
+    let title = color::style(Style::new().bold().fg(Colour::Blue));
+    let passed = num_run as u64 - num_failed;
+    let rate = 100.0 * passed as f64 / num_run as f64;
+    let secs = elapsed.as_secs_f64();
+    let per_sec = if secs > 0.0 { num_run as f64 / secs } else { 0.0 };
+    outln!("{}{} {} tests, {} passed, {} failed, {:.3}% passed, {:.1} tests/s {}", prefix,
+        title.paint("--- Progress:"), num_run, passed, num_failed, rate, per_sec,
+        title.paint("---"));
+}
+
+/// Print the test parameters under headings.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `devices` - The test parameters for each device.
+fn print_parameters(params: &Params, devices: &[Params]) {
+    let heading = color::style(Style::new().fg(Colour::Cyan));
+    let info = color::style(Style::new().fg(Colour::Blue));
+    let auto = params.src_auto.as_ref().map_or(String::new(), |name| format!(" (Auto from {})",
+        name));
+    outln!("{} {}", heading.paint("Source         "),
+        info.paint(format!("(Test Device){}{}", params.env_note("ETHER_SRC"), auto)));
+    outln!("{} {}", heading.paint("  IP           "), params.src_ip_string());
+    outln!("{} {}", heading.paint("  Port         "), params.src_port);
+    outln!("{} {}", heading.paint("  Mac          "), params.src_mac_string());
+    for (i, device) in devices.iter().enumerate() {
+        if devices.len() > 1 {
+            outln!("{} {}", heading.paint(format!("Device {:<8}", i + 1)), device.label());
+        }
+        outln!("{} {}", heading.paint("Destination    "),
+            info.paint(format!("(Host Device){}", device.env_note("ETHER_DEST"))));
+        outln!("{} {}", heading.paint("  IP           "), device.dest_ip_string());
+        outln!("{} {}", heading.paint("  Port         "), device.dest_port);
+        outln!("{} {}", heading.paint("  Mac          "), device.dest_mac_string());
+        if let Some((ref name, ip)) = device.interface {
+            outln!("{} {} ({})", heading.paint("  Interface    "), name, Ipv4Addr::from(ip));
+        }
+        if let Some(group) = device.multicast_string() {
+            outln!("{} {}", heading.paint("  Multicast    "), group);
+        }
+        if device.loopback {
+            outln!("{} {}", heading.paint("Serial Port    "), info.paint("(Loopback)"));
+        } else {
+            outln!("{} {}{}", heading.paint("Serial Port    "), device.serial_port,
+                info.paint(device.env_note("ETHER_SERIAL")));
+            outln!("{} {}", heading.paint("Serial Baudrate"), device.serial_baud.speed());
+            outln!("{} {}", heading.paint("Serial Settings"), device.serial_settings_string());
+        }
+        // A device can be run again on its own with its run seed
+        if devices.len() > 1 {
+            outln!("{} {}", heading.paint("  Run Seed     "), device.run_seed);
+        }
+    }
+    if let Some(addr) = params.control {
+        outln!("{} {}", heading.paint("Control        "), addr);
+    }
+    outln!("{} {}", heading.paint("Run Seed       "), params.run_seed);
+}
+
+/// Print the byte order of the host and the bytes of the serial frame that hold the source IP
+/// address, which do not depend on the host when the frame is encoded correctly.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+fn print_byte_order(params: &Params) {
+    let heading = color::style(Style::new().fg(Colour::Cyan));
+    let endian = |big| if big { "Big endian" } else { "Little endian" };
+    outln!("{} {}", heading.paint("Host           "), endian(cfg!(target_endian = "big")));
+    outln!("{} {}", heading.paint("Frame          "),
+        endian(params.frame_endian == Endian::Big));
+    let mut bytes = vec![];
+    TestCase::with_seed_gen(params, 0, 0).write_bytes(&mut bytes);
+    let layout = &params.frame_layout;
+    let src_ip = match layout.iter().position(|&f| f == frame::Field::SrcIp) {
+        Some(i) => {
+            let offset = frame::frame_len(&layout[..i]);
+            format!("{} at offset {}", hex::string(&bytes[offset..offset + 4]), offset)
+        },
+        None => "Not in the frame".to_string()
+    };
+    outln!("{} {} ({})", heading.paint("Source IP      "), src_ip, params.src_ip_string());
+    outln!("{} {}", heading.paint("Frame Bytes    "), hex::string(&bytes));
+}
+
+/// Get the test parameters as the rows of a table.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `devices` - The test parameters for each device.
+///
+/// # Returns
+///
+/// The name and value of each row.
+fn parameter_rows(params: &Params, devices: &[Params]) -> Vec<(String, String)> {
+    let mut rows = vec![
+        ("Source IP".to_string(), params.src_ip_string() + &params.env_note("ETHER_SRC")
+            + &params.src_auto.as_ref().map_or(String::new(), |name| format!(" (Auto from {})",
+                name))),
+        ("Source Port".to_string(), params.src_port.to_string()),
+        ("Source Mac".to_string(), params.src_mac_string())
+    ];
+    for (i, device) in devices.iter().enumerate() {
+        let name = if devices.len() > 1 { format!("Device {} ", i + 1) } else { String::new() };
+        rows.push((format!("{}Destination IP", name),
+            device.dest_ip_string() + &device.env_note("ETHER_DEST")));
+        rows.push((format!("{}Destination Port", name), device.dest_port.to_string()));
+        rows.push((format!("{}Destination Mac", name), device.dest_mac_string()));
+        if let Some((ref interface, ip)) = device.interface {
+            rows.push((format!("{}Interface", name),
+                format!("{} ({})", interface, Ipv4Addr::from(ip))));
+        }
+        if let Some(group) = device.multicast_string() {
+            rows.push((format!("{}Multicast", name), group));
+        }
+        if device.loopback {
+            rows.push((format!("{}Serial Port", name), "(Loopback)".to_string()));
+        } else {
+            rows.push((format!("{}Serial Port", name),
+                device.serial_port.clone() + &device.env_note("ETHER_SERIAL")));
+            rows.push((format!("{}Serial Baudrate", name), device.serial_baud.speed().to_string()));
+            rows.push((format!("{}Serial Settings", name), device.serial_settings_string()));
+        }
+        if devices.len() > 1 {
+            rows.push((format!("{}Run Seed", name), device.run_seed.to_string()));
+        }
+    }
+    if let Some(addr) = params.control {
+        rows.push(("Control".to_string(), addr.to_string()));
+    }
+    rows.push(("Run Seed".to_string(), params.run_seed.to_string()));
+    rows
+}
+
+/// Format a bit error rate in scientific notation.
+///
+/// # Arguments
+///
+/// * `errors` - The number of bits that differed.
+/// * `bits` - The number of bits that were compared.
+///
+/// # Returns
+///
+/// The bit error rate, or `n/a` when no bits were compared.
+fn ber_string(errors: u64, bits: u64) -> String {
+    if bits == 0 {
+        "n/a".to_string()
+    } else {
+        format!("{:.1e}", errors as f64 / bits as f64)
+    }
+}
+
+/// Format the spread of latencies.
+///
+/// # Arguments
+///
+/// * `latencies` - The latencies in any order.
+///
+/// # Returns
+///
+/// The smallest, median, and largest latency in microseconds, or `n/a` when there are none.
+fn latency_string(latencies: &[Duration]) -> String {
+    if latencies.is_empty() {
+        return "n/a".to_string();
+    }
+    let mut sorted = latencies.to_vec();
+    sorted.sort();
+    format!("min {} us, median {} us, max {} us", sorted[0].as_micros(),
+        sorted[sorted.len() / 2].as_micros(), sorted[sorted.len() - 1].as_micros())
+}
+
+/// Format the number of packets that matched each candidate payload.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `matches` - The number of packets that matched each candidate.
+///
+/// # Returns
+///
+/// The name and number of packets of each candidate, such as `ramp 10, big-words 0`.
+fn candidate_string(params: &Params, matches: &[u64]) -> String {
+    params.expect_any.iter().zip(matches)
+        .map(|(&generator, n)| format!("{} {}", params::candidate_name(generator), n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format the number of packets of each size, most common first. Long lists are cut short so that
+/// packets of random sizes do not flood the summary.
+///
+/// # Arguments
+///
+/// * `sizes` - The number of packets of each size.
+///
+/// # Returns
+///
+/// The list of sizes, such as `64 bytes: 9823, 60 bytes: 177`.
+fn size_list(sizes: &HashMap<usize, u64>) -> String {
+    let mut counts: Vec<(usize, u64)> = sizes.iter().map(|(&size, &n)| (size, n)).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let listed: Vec<String> = counts.iter()
+        .take(MAX_LISTED_SIZES)
+        .map(|&(size, n)| format!("{} bytes: {}", size, n))
+        .collect();
+    let mut list = listed.join(", ");
+    if counts.len() > MAX_LISTED_SIZES {
+        list = format!("{}, and {} more sizes", list, counts.len() - MAX_LISTED_SIZES);
+    }
+    list
+}
+
+/// Format the seeds and generators of failed tests as a list. Long lists are cut short so that a
+/// bad sweep does not flood the summary.
+///
+/// # Arguments
+///
+/// * `cases` - The seed and generator of each failed test.
+///
+/// # Returns
+///
+/// The list of cases.
+fn case_list(cases: &[(u16, u16)]) -> String {
+    let listed: Vec<String> = cases.iter()
+        .take(MAX_LISTED_CASES)
+        .map(|&(seed, gen)| format!("0x{:02X}/0x{:02X}", seed, gen))
+        .collect();
+    let mut list = listed.join(", ");
+    if cases.len() > MAX_LISTED_CASES {
+        list = format!("{}, and {} more", list, cases.len() - MAX_LISTED_CASES);
+    }
+    list
+}
+
+/// Print the summary of the tests.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `outcomes` - The outcome of the tests on each device.
+/// * `out` - Where to print the summary.
+fn print_summary(params: &Params, outcomes: &[Outcome], out: &mut dyn Write) {
+    let fail = color::style(Style::new().bold().fg(Colour::Red));
+    let success = color::style(Style::new().bold().fg(Colour::Green));
+    let num_run: usize = outcomes.iter().map(|o| o.num_run).sum();
+    let num_failed: u64 = outcomes.iter().map(|o| o.num_failed).sum();
+    if num_failed > 0 {
+        // Print one empty line to separate the summary from the previous failures
+        outln!(to out);
+        outln!(to out, "{}", fail.paint(format!("Failed {} of {} tests", num_failed, num_run)));
+        if outcomes.len() == 1 && outcomes[0].stopped {
+            outln!(to out, "{}", fail.paint(format!(
+                "Stopped after reaching the limit of {} failures", params.max_failures)));
+        }
+        if let (1, Some(rep)) = (outcomes.len(), outcomes[0].aborted) {
+            outln!(to out, "{}", fail.paint(format!("Aborted after a timeout in rep {}", rep)));
+        }
+        if outcomes.len() == 1 && outcomes[0].no_packets {
+            outln!(to out, "{}", fail.paint(format!("No packets received in first {} reps, check \
+                cabling and addresses", outcomes[0].num_run)));
+        }
+        // Point at the failure to look at first
+        let worst = outcomes.iter()
+            .filter_map(|o| o.worst.map(|(rep, count)| (&o.label, rep, count)))
+            .max_by_key(|&(_, _, count)| count);
+        if let Some((label, rep, count)) = worst {
+            let device = if label.is_empty() { String::new() } else { format!(" of {}", label) };
+            outln!(to out, "Worst failure: rep {}{}, {} bytes differed", rep, device, count);
+        }
+        if params.seed_sweep {
+            let cases: Vec<(u16, u16)> = outcomes.iter()
+                .flat_map(|o| o.failed_cases.iter().cloned())
+                .collect();
+            outln!(to out, "Failed seeds/gens: {}", case_list(&cases));
+        }
+        // Link loss and data errors have different causes, so they are counted apart
+        if params.seqnum {
+            let dropped: u64 = outcomes.iter().map(|o| o.dropped).sum();
+            let corrupted: u64 = outcomes.iter().map(|o| o.corrupted).sum();
+            outln!(to out, "Dropped {} packets, corrupted {} packets", dropped, corrupted);
+        }
+    // else all tests passed
+    } else if !params.show_all || outcomes.len() > 1 {
+        if !params.no_socket {
+            outln!(to out, "{}", success.paint(format!("Passed all {} tests", num_run)));
+        } else {
+            outln!(to out, "{}", success.paint(format!("Ran {} tests (No socket open)", num_run)));
+        }
+    }
+    // Break the summary down by device so that a single bad device stands out
+    if outcomes.len() > 1 {
+        for o in outcomes {
+            if o.num_failed > 0 {
+                let stopped = if o.no_packets {
+                    " (no packets received)"
+                } else if o.stopped || o.aborted.is_some() {
+                    " (stopped early)"
+                } else {
+                    ""
+                };
+                outln!(to out, "  {}: {}{}", o.label,
+                    fail.paint(format!("Failed {} of {} tests", o.num_failed, o.num_run)), stopped);
+            } else {
+                outln!(to out, "  {}: {}", o.label,
+                    success.paint(format!("Passed all {} tests", o.num_run)));
+            }
+        }
+    }
+    // The devices run at the same time, so their rates add up over the longest run
+    if params.throughput {
+        let packets: u64 = outcomes.iter().map(|o| o.packets_received).sum();
+        let bytes: u64 = outcomes.iter().map(|o| o.bytes_received).sum();
+        let secs = outcomes.iter().map(|o| o.elapsed).max().unwrap_or_default().as_secs_f64();
+        let (pps, bps) = if secs > 0.0 {
+            (packets as f64 / secs, bytes as f64 / secs)
+        } else {
+            (0.0, 0.0)
+        };
+        outln!(to out,
+            "Throughput: {:.1} packets/s, {:.1} bytes/s ({} packets, {} bytes in {:.3} s)",
+            pps, bps, packets, bytes, secs);
+    }
+}
+
+/// Get the summary of the tests as the rows of a table.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `outcomes` - The outcome of the tests on each device.
+///
+/// # Returns
+///
+/// The name and value of each row.
+fn summary_rows(params: &Params, outcomes: &[Outcome]) -> Vec<(String, String)> {
+    let mut rows = vec![];
+    for o in outcomes {
+        let name = if outcomes.len() > 1 { format!("{} ", o.label) } else { String::new() };
+        rows.push((format!("{}Tests Run", name), o.num_run.to_string()));
+        rows.push((format!("{}Tests Failed", name), o.num_failed.to_string()));
+        if o.stopped {
+            rows.push((format!("{}Stopped Early", name),
+                format!("Limit of {} failures", params.max_failures)));
+        }
+        if let Some(rep) = o.aborted {
+            rows.push((format!("{}Aborted", name), format!("Timeout in rep {}", rep)));
+        }
+        if o.no_packets {
+            rows.push((format!("{}Aborted", name),
+                format!("No packets received in first {} reps", o.num_run)));
+        }
+        if let Some((rep, count)) = o.worst {
+            rows.push((format!("{}Worst Failure", name),
+                format!("Rep {}, {} bytes differed", rep, count)));
+        }
+        if params.seed_sweep && !o.failed_cases.is_empty() {
+            rows.push((format!("{}Failed Seeds/Gens", name), case_list(&o.failed_cases)));
+        }
+        if params.seqnum {
+            rows.push((format!("{}Dropped Packets", name), o.dropped.to_string()));
+            rows.push((format!("{}Corrupted Packets", name), o.corrupted.to_string()));
+        }
+        if params.throughput {
+            let secs = o.elapsed.as_secs_f64();
+            let (pps, bps) = if secs > 0.0 {
+                (o.packets_received as f64 / secs, o.bytes_received as f64 / secs)
+            } else {
+                (0.0, 0.0)
+            };
+            rows.push((format!("{}Throughput", name),
+                format!("{:.1} packets/s, {:.1} bytes/s", pps, bps)));
+        }
+        if params.tolerance > 0 {
+            rows.push((format!("{}Maximum Deviation", name), o.deviation.to_string()));
+        }
+        if params.ber {
+            rows.push((format!("{}Bit Error Rate", name), format!("{} ({} of {} bits)",
+                ber_string(o.bit_errors, o.bits_compared), o.bit_errors, o.bits_compared)));
+        }
+        if params.fpga_timestamp {
+            rows.push((format!("{}FPGA Latency", name), latency_string(&o.fpga_latencies)));
+            rows.push((format!("{}Network Latency", name),
+                latency_string(&o.network_latencies)));
+        }
+        if params.reread > 0 {
+            rows.push((format!("{}Rereads", name), o.rereads.to_string()));
+        }
+        if params.timeout_policy == TimeoutPolicy::Retry {
+            rows.push((format!("{}Frames Resent", name), o.resent.to_string()));
+        }
+        if params.check_checksums {
+            rows.push((format!("{}Checksum Failures", name), o.checksum_errors.to_string()));
+        }
+        if params.detect_duplicates {
+            rows.push((format!("{}Duplicate Packets", name), o.duplicates.to_string()));
+        }
+        if params.rate.is_some() {
+            rows.push((format!("{}Tests Behind Rate", name), o.behind_rate.to_string()));
+        }
+        if params.corrupt_frame.is_some() {
+            rows.push((format!("{}Corrupted Frames", name), format!("{} rejected, {} tolerated",
+                o.frames_rejected, o.frames_tolerated)));
+        }
+        if !params.expect_any.is_empty() {
+            rows.push((format!("{}Payloads Matched", name),
+                candidate_string(params, &o.candidate_matches)));
+        }
+        if params.handshake_retries.is_some() {
+            rows.push((format!("{}Handshake Retries", name), o.handshake_retries.to_string()));
+        }
+        // A single size is the expected one, or every test failed on its length
+        if o.sizes.len() > 1 {
+            rows.push((format!("{}Packet Sizes", name), size_list(&o.sizes)));
+        }
+        if o.os_dropped.unwrap_or(0) > 0 {
+            rows.push((format!("{}OS Dropped Packets", name), o.os_dropped.unwrap().to_string()));
+        }
+        for (device, &(run, failed)) in params.devices.iter().zip(&o.dest_results) {
+            rows.push((format!("{}Failed to {}:{}", name, Ipv4Addr::from(device.dest_ip),
+                device.dest_port), format!("{} of {}", failed, run)));
+        }
+    }
+    rows
+}
+
+/// Warn about parameters that will likely cause trouble.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `devices` - The test parameters for each device.
+fn check_params(params: &Params, devices: &[Params]) {
+    if !params.fits_in_frame() {
+        warn("Large payload", format!("{} bytes do not fit in one Ethernet frame, so each packet \
+            must be fragmented by the FPGA", params.packet_len()));
+    }
+    if !params.loopback && !params.allow_group_mac {
+        check_mac("source", params.src_mac, &params.src_mac_string());
+    }
+    for device in devices {
+        if !device.loopback && !device.has_standard_baud() {
+            warn("Non-standard baudrate", format!("{} may not be supported by the serial adapter",
+                device.serial_baud.speed()));
+        }
+        // The frame bytes are binary, so smaller characters or flow control characters mangle them
+        if !device.loopback && device.data_bits != Bits8 {
+            warn("Small serial characters", "Fewer than 8 data bits cannot carry the 8-bit bytes \
+                of the serial frame");
+        }
+        if !device.loopback && device.flow_control == FlowSoftware {
+            warn("Software flow control", "Frame bytes that equal XON or XOFF are taken as flow \
+                control");
+        }
+        // The emulator sends from the host, so its addresses do not need to be on a subnet
+        if !device.loopback {
+            check_addresses(device);
+        }
+    }
+}
+
+/// Write the metrics of a run to a file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+/// * `outcomes` - The outcome of every run of tests.
+/// * `duration` - The time that the program ran for.
+fn write_metrics(path: &str, outcomes: &[Outcome], duration: Duration) {
+    if let Err(msg) = metrics(outcomes, duration).write(path) {
+        fatal("Could not write metrics file", msg)
+    }
+}
+
+/// Write the summary of the run to a JSON file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+/// * `outcomes` - The outcome of the tests on each device.
+/// * `duration` - The time that the whole run took.
+fn write_summary_json(path: &str, outcomes: &[Outcome], duration: Duration) {
+    if let Err(msg) = metrics(outcomes, duration).write_json(path) {
+        fatal("Could not write summary file", msg)
+    }
+}
+
+/// Add up the totals of a run.
+///
+/// # Arguments
+///
+/// * `outcomes` - The outcome of the tests on each device.
+/// * `duration` - The time that the whole run took.
+///
+/// # Returns
+///
+/// The totals.
+fn metrics(outcomes: &[Outcome], duration: Duration) -> Metrics {
+    Metrics {
+        tests: outcomes.iter().map(|o| o.num_run as u64).sum(),
+        failures: outcomes.iter().map(|o| o.num_failed).sum(),
+        packets: outcomes.iter().map(|o| o.packets_received).sum(),
+        bytes: outcomes.iter().map(|o| o.bytes_received).sum(),
+        latencies: outcomes.iter().flat_map(|o| o.latencies.iter().cloned()).collect(),
+        duration,
+        interrupted: INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
+
+/// Stop the tests on Ctrl-C instead of exiting, so that the summary can still be written. A second
+/// Ctrl-C exits straight away in case the tests are stuck.
+#[cfg(unix)]
+fn catch_interrupt() {
+    let handler = interrupt as extern "C" fn(libc::c_int);
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+}
+
+/// Ctrl-C cannot be caught on this platform, so it exits straight away without a summary.
+#[cfg(not(unix))]
+fn catch_interrupt() {}
+
+/// Record that Ctrl-C was pressed, or exit if it was already pressed.
+///
+/// # Arguments
+///
+/// * `_signal` - The signal number.
+#[cfg(unix)]
+extern "C" fn interrupt(_signal: libc::c_int) {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(130) }
+    }
+}
+
+/// Print the summary of the tests and decide whether they passed.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `outcomes` - The outcome of the tests on each device.
+/// * `out` - Where to print the summary.
+///
+/// # Returns
+///
+/// An indicator of whether the tests passed.
+fn report(params: &Params, outcomes: &[Outcome], out: &mut dyn Write) -> bool {
+    match params.format {
+        Format::Human => report_text(params, outcomes, out),
+        Format::Table => report_table(params, outcomes, out),
+        Format::Tap => report_tap(params, outcomes, out)
+    }
+}
+
+/// Print the summary of the tests as headings with the values beside them, followed by the
+/// counts that only some options make, and decide whether the tests passed.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `outcomes` - The outcome of the tests on each device.
+/// * `out` - Where to print the summary.
+///
+/// # Returns
+///
+/// An indicator of whether the tests passed.
+fn report_text(params: &Params, outcomes: &[Outcome], out: &mut dyn Write) -> bool {
+    print_summary(params, outcomes, out);
+    if params.reread > 0 {
+        let rereads: u64 = outcomes.iter().map(|o| o.rereads).sum();
+        outln!(to out, "Read {} extra packets after wrong packets", rereads);
+    }
+    if params.timeout_policy == TimeoutPolicy::Retry {
+        let resent: u64 = outcomes.iter().map(|o| o.resent).sum();
+        outln!(to out, "Sent {} serial frames again after timeouts", resent);
+    }
+    // Checksum failures point at the header logic of the FPGA rather than at the payload
+    if params.check_checksums {
+        let errors: u64 = outcomes.iter().map(|o| o.checksum_errors).sum();
+        outln!(to out, "Checksum failures: {}", errors);
+    }
+    if params.detect_duplicates {
+        let duplicates: u64 = outcomes.iter().map(|o| o.duplicates).sum();
+        outln!(to out, "Duplicate packets: {}", duplicates);
+    }
+    if params.rate.is_some() {
+        let behind: u64 = outcomes.iter().map(|o| o.behind_rate).sum();
+        outln!(to out, "Tests behind the rate: {}", behind);
+    }
+    if params.corrupt_frame.is_some() {
+        let rejected: u64 = outcomes.iter().map(|o| o.frames_rejected).sum();
+        let tolerated: u64 = outcomes.iter().map(|o| o.frames_tolerated).sum();
+        outln!(to out, "Corrupted frames: {} rejected, {} tolerated", rejected, tolerated);
+    }
+    if !params.expect_any.is_empty() {
+        let mut matches = vec![0; params.expect_any.len()];
+        for o in outcomes {
+            for (total, n) in matches.iter_mut().zip(&o.candidate_matches) {
+                *total += n;
+            }
+        }
+        outln!(to out, "Payloads matched: {}", candidate_string(params, &matches));
+    }
+    if params.handshake_retries.is_some() {
+        let retries: u64 = outcomes.iter().map(|o| o.handshake_retries).sum();
+        outln!(to out, "Handshake retries: {}", retries);
+    }
+    let mut sizes: HashMap<usize, u64> = HashMap::new();
+    for (&size, &n) in outcomes.iter().flat_map(|o| &o.sizes) {
+        *sizes.entry(size).or_insert(0) += n;
+    }
+    if sizes.len() > 1 {
+        outln!(to out, "Packet sizes: {}", size_list(&sizes));
+    }
+    // Packets that the host dropped look the same as packets that the FPGA never sent
+    let os_dropped: u64 = outcomes.iter().filter_map(|o| o.os_dropped).sum();
+    if os_dropped > 0 {
+        outln!(to out, "OS dropped {} packets during the run, try a larger --rcvbuf", os_dropped);
+    }
+    // A bad route only fails the tests to its own destination
+    if params.round_robin {
+        for o in outcomes {
+            for (device, &(run, failed)) in params.devices.iter().zip(&o.dest_results) {
+                outln!(to out, "Failed {} of {} tests to {}:{}", failed, run,
+                    Ipv4Addr::from(device.dest_ip), device.dest_port);
+            }
+        }
+    }
+    if params.group_failures {
+        let mut signatures: HashMap<Signature, u64> = HashMap::new();
+        for (&s, &count) in outcomes.iter().flat_map(|o| &o.signatures) {
+            *signatures.entry(s).or_insert(0) += count;
+        }
+        let mut signatures: Vec<(Signature, u64)> = signatures.into_iter().collect();
+        signatures.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for (s, count) in signatures {
+            let plural = if count == 1 { "" } else { "s" };
+            outln!(to out, "{} failure{} of signature {}", count, plural,
+                compare::signature_string(s));
+        }
+    }
+    if params.ber {
+        let errors: u64 = outcomes.iter().map(|o| o.bit_errors).sum();
+        let bits: u64 = outcomes.iter().map(|o| o.bits_compared).sum();
+        outln!(to out, "BER: {} ({} bit errors / {:.2e} bits)", ber_string(errors, bits), errors,
+            bits as f64);
+    }
+    if params.fpga_timestamp {
+        let fpga: Vec<Duration> = outcomes.iter()
+            .flat_map(|o| o.fpga_latencies.iter().cloned())
+            .collect();
+        let network: Vec<Duration> = outcomes.iter()
+            .flat_map(|o| o.network_latencies.iter().cloned())
+            .collect();
+        outln!(to out, "FPGA latency: {}", latency_string(&fpga));
+        outln!(to out, "Network latency: {}", latency_string(&network));
+    }
+    // Show how much the bytes varied so that a sensible tolerance can be picked
+    if params.tolerance > 0 {
+        let deviation = outcomes.iter().map(|o| o.deviation).max().unwrap_or(0);
+        outln!(to out, "Maximum deviation: {} (tolerance {})", deviation, params.tolerance);
+    }
+    meets_pass_rate(params, outcomes, out)
+}
+
+/// Print the summary of the tests as a table and decide whether they passed.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `outcomes` - The outcome of the tests on each device.
+/// * `out` - Where to print the summary.
+///
+/// # Returns
+///
+/// An indicator of whether the tests passed.
+fn report_table(params: &Params, outcomes: &[Outcome], out: &mut dyn Write) -> bool {
+    let title = color::style(Style::new().bold().fg(Colour::Blue));
+    outln!(to out);
+    outln!(to out, "{}", title.paint("Summary"));
+    outln!(to out, "{}", title.paint("-------"));
+    outln!(to out, "{}", table::format(&summary_rows(params, outcomes)));
+    meets_pass_rate(params, outcomes, out)
+}
+
+/// Print the plan of a Test Anything Protocol stream and decide whether the tests passed.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `outcomes` - The outcome of the tests on each device.
+/// * `out` - Where to print the plan.
+///
+/// # Returns
+///
+/// An indicator of whether the tests passed.
+fn report_tap(params: &Params, outcomes: &[Outcome], out: &mut dyn Write) -> bool {
+    let num_run: usize = outcomes.iter().map(|o| o.num_run).sum();
+    let num_failed: u64 = outcomes.iter().map(|o| o.num_failed).sum();
+    // The plan comes last so that it matches the tests that ran when the run stops early
+    outln!(to out, "1..{}", num_run);
+    match params.min_pass_rate {
+        Some(min) => pass_rate(num_run, num_failed) >= min,
+        None => num_failed == 0
+    }
+}
+
+/// Decide whether the tests passed. The pass rate is printed when a minimum is given, and
+/// otherwise any failure fails the run.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `outcomes` - The outcome of the tests on each device.
+/// * `out` - Where to print the pass rate.
+///
+/// # Returns
+///
+/// An indicator of whether the tests passed.
+fn meets_pass_rate(params: &Params, outcomes: &[Outcome], out: &mut dyn Write) -> bool {
+    let fail = color::style(Style::new().bold().fg(Colour::Red));
+    let success = color::style(Style::new().bold().fg(Colour::Green));
+    let num_run: usize = outcomes.iter().map(|o| o.num_run).sum();
+    let num_failed: u64 = outcomes.iter().map(|o| o.num_failed).sum();
+    match params.min_pass_rate {
+        Some(min) => {
+            let rate = pass_rate(num_run, num_failed);
+            if rate >= min {
+                outln!(to out, "{}", success.paint(format!(
+                    "Pass rate of {:.3}% meets the minimum of {}%", rate, min)));
+                true
+            } else {
+                outln!(to out, "{}", fail.paint(format!(
+                    "Pass rate of {:.3}% is below the minimum of {}%", rate, min)));
+                false
+            }
+        },
+        None => num_failed == 0
+    }
+}
+
+/// Get the percentage of tests that passed.
+///
+/// # Arguments
+///
+/// * `num_run` - The number of tests that ran.
+/// * `num_failed` - The number of tests that failed.
+///
+/// # Returns
+///
+/// The pass rate, which is 100 when no tests ran.
+fn pass_rate(num_run: usize, num_failed: u64) -> f64 {
+    if num_run > 0 {
+        100.0 * (num_run as f64 - num_failed as f64) / num_run as f64
+    } else {
+        100.0
+    }
+}
+
+/// Compare the packets of a capture file with the expected payloads, without any hardware.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `path` - The path of the capture file.
+///
+/// # Returns
+///
+/// An indicator of whether every packet matched.
+fn compare_pcap(params: &Params, path: &str) -> bool {
+    let fail = color::style(Style::new().bold().fg(Colour::Red));
+    let success = color::style(Style::new().bold().fg(Colour::Green));
+    let payloads = match pcap::udp_payloads(path, params.dest_ip, params.dest_port) {
+        Ok(p) => p,
+        Err(msg) => fatal("Could not read capture file", msg)
+    };
+    // The capture does not hold the seeds and generators, so they must be known ahead
+    let mut test_cases = match (params.replay.as_ref(), params.seed, params.gen) {
+        (Some(replay), _, _) => match record::replay(replay, params) {
+            Ok(test_cases) => test_cases,
+            Err(msg) => fatal("Could not read replay file", msg)
+        },
+        (None, Some(seed), Some(gen)) => {
+            let mut test_cases = vec![TestCase::with_seed_gen(params, seed, gen)];
+            while test_cases.len() < payloads.len() {
+                let next = match test_cases.last() {
+                    Some(prev) if params.continuous => prev.next(),
+                    _ => TestCase::with_seed_gen(params, seed, gen)
+                };
+                test_cases.push(next);
+            }
+            test_cases
+        },
+        _ => fatal("Bad command line argument",
+            "Comparing a capture needs a replay file, or a seed and a generator")
+    };
+    if payloads.len() > test_cases.len() {
+        warn("Capture has extra packets", format!("{} packets were captured, but only {} are \
+            expected", payloads.len(), test_cases.len()));
+    }
+    let mut num_run = 0;
+    let mut num_failed = 0;
+    for (i, (payload, test_case)) in payloads.iter().zip(test_cases.iter_mut()).enumerate() {
+        num_run += 1;
+        test_case.seq = i as u16;
+        let expected = match test_case.expected() {
+            Ok(e) => e,
+            Err(msg) => {
+                num_failed += 1;
+                outln!("{}: {}", fail.paint(format!("Failed {} ({})", i + 1,
+                    case_string(test_case))), msg);
+                continue;
+            }
+        };
+        match compare_payload(params, &expected, payload) {
+            Ok(_) => if params.show_all {
+                outln!("{}", success.paint(format!("Passed {} ({})", i + 1,
+                    case_string(test_case))));
+            },
+            Err(mismatch) => {
+                num_failed += 1;
+                outln!("{}: {}\n{}", fail.paint(format!("Failed {} ({})", i + 1,
+                    case_string(test_case))), mismatch,
+                    compare::window(&expected, payload, mismatch.offset, params.tolerance));
+            }
+        }
+    }
+    if num_failed > 0 {
+        outln!("{}", fail.paint(format!("Failed {} of {} packets", num_failed, num_run)));
+    } else {
+        outln!("{}", success.paint(format!("Passed all {} packets", num_run)));
+    }
+    num_failed == 0
+}
+
+/// Try each standard baudrate with a few tests to find the baudrate of the firmware.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+///
+/// # Returns
+///
+/// An indicator of whether a test passed at any baudrate.
+fn baud_sweep(params: &Params) -> bool {
+    let fail = color::style(Style::new().bold().fg(Colour::Red));
+    let success = color::style(Style::new().bold().fg(Colour::Green));
+    let mut port = or_fatal(open_serial(params));
+    let (socket, _membership) = or_fatal(bind_socket(params));
+    let mut rng = params.rng();
+    let headers = if params.check_checksums { checksum::MAX_HEADER_LEN } else { 0 };
+    let mut buf = vec![0; params.packet_len() + headers + 1];
+    let mut frame = vec![];
+    let mut working = vec![];
+    for &baud in params::STANDARD_BAUDS {
+        let configured = port.reconfigure(&|settings| {
+            settings.set_baud_rate(BaudRate::from_speed(baud))?;
+            settings.set_char_size(params.data_bits);
+            settings.set_parity(params.parity);
+            settings.set_stop_bits(params.stop_bits);
+            settings.set_flow_control(params.flow_control);
+            Ok(())
+        });
+        if let Err(err) = configured {
+            outln!("{:>7} baud: {}", baud, fail.paint(format!("Not supported: {}", err)));
+            continue;
+        }
+        // Packets from the last baudrate must not be taken for packets at this one
+        if socket.set_nonblocking(true).is_ok() {
+            while socket.recv_from(&mut buf).is_ok() {}
+            let _ = socket.set_nonblocking(false);
+        }
+        let mut passed = 0;
+        for _ in 0..BAUD_SWEEP_TESTS {
+            let test_case = TestCase::new(params, &mut rng);
+            test_case.write_bytes(&mut frame);
+            if let Err(msg) = write_frame(&mut port, &frame) {
+                debug!("Could not write serial frame at {} baud: {}", baud, msg);
+                continue;
+            }
+            let size = match socket.recv_from(&mut buf) {
+                Ok((size, _)) => size,
+                Err(err) => {
+                    debug!("No packet at {} baud: {}", baud, err);
+                    continue;
+                }
+            };
+            let actual = if params.check_checksums {
+                checksum::strip(&buf[..size]).ok()
+            } else {
+                Some(&buf[..size])
+            };
+            let expected = or_fatal(test_case.expected());
+            if actual.is_some_and(|a| compare_payload(params, &expected, a).is_ok()) {
+                passed += 1;
+            }
+        }
+        let result = format!("Passed {} of {} tests", passed, BAUD_SWEEP_TESTS);
+        if passed > 0 {
+            outln!("{:>7} baud: {}", baud, success.paint(result));
+            working.push(baud);
+        } else {
+            outln!("{:>7} baud: {}", baud, fail.paint(result));
+        }
+    }
+    match working.first() {
+        Some(baud) => {
+            let list = working.iter().map(|b| b.to_string()).collect::<Vec<_>>().join(", ");
+            outln!("Working baudrates: {}", list);
+            outln!("{}", success.paint(format!("Found a working baudrate of {}", baud)));
+            true
+        },
+        None => {
+            outln!("{}", fail.paint("No baudrate passed a test"));
+            false
+        }
+    }
+}
+
+/// Grow the payload until the tests stop passing to find the largest payload that gets through.
+/// The size doubles from the bytes parameter until a size fails, and then the sizes between the
+/// last size that passed and the first that failed are bisected.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+///
+/// # Returns
+///
+/// An indicator of whether any size passed.
+fn mtu_discover(params: &Params) -> bool {
+    let fail = color::style(Style::new().bold().fg(Colour::Red));
+    let success = color::style(Style::new().bold().fg(Colour::Green));
+    let mut port = or_fatal(open_port(params));
+    let (socket, _membership) = or_fatal(bind_socket(params));
+    let mut rng = params.rng();
+    let max_bytes = params::MAX_UDP_PAYLOAD - params.payload_prefix.len()
+        - params.payload_suffix.len();
+    let headers = if params.check_checksums { checksum::MAX_HEADER_LEN } else { 0 };
+    let mut buf = vec![0; params::MAX_UDP_PAYLOAD + headers];
+    let mut frame = vec![];
+    let mut try_size = |bytes: usize| {
+        let mut sized = params.clone();
+        sized.bytes = bytes;
+        // Packets from the last size must not be taken for packets of this one
+        if socket.set_nonblocking(true).is_ok() {
+            while socket.recv_from(&mut buf).is_ok() {}
+            let _ = socket.set_nonblocking(false);
+        }
+        let mut passed = 0;
+        for _ in 0..MTU_DISCOVER_TESTS {
+            let test_case = TestCase::new(&sized, &mut rng);
+            test_case.write_bytes(&mut frame);
+            if let Err(msg) = write_frame(&mut *port, &frame) {
+                fatal("Could not write serial frame", msg)
+            }
+            let size = match socket.recv_from(&mut buf) {
+                Ok((size, _)) => size,
+                Err(err) => {
+                    debug!("No packet with {} bytes: {}", bytes, err);
+                    continue;
+                }
+            };
+            let actual = if params.check_checksums {
+                checksum::strip(&buf[..size]).ok()
+            } else {
+                Some(&buf[..size])
+            };
+            let expected = or_fatal(test_case.expected());
+            if actual.is_some_and(|a| compare_payload(&sized, &expected, a).is_ok()) {
+                passed += 1;
+            }
+        }
+        let result = format!("Passed {} of {} tests", passed, MTU_DISCOVER_TESTS);
+        if passed == MTU_DISCOVER_TESTS {
+            outln!("{:>5} bytes: {}", bytes, success.paint(result));
+        } else {
+            outln!("{:>5} bytes: {}", bytes, fail.paint(result));
+        }
+        passed == MTU_DISCOVER_TESTS
+    };
+    let mut largest = None;
+    let mut bytes = params.bytes.clamp(1, max_bytes);
+    let smallest_failed = loop {
+        if !try_size(bytes) {
+            break Some(bytes);
+        }
+        largest = Some(bytes);
+        if bytes == max_bytes {
+            break None;
+        }
+        bytes = (2 * bytes).min(max_bytes);
+    };
+    if let (Some(mut passed), Some(mut failed)) = (largest, smallest_failed) {
+        while failed - passed > 1 {
+            let bytes = passed + (failed - passed) / 2;
+            if try_size(bytes) {
+                passed = bytes;
+            } else {
+                failed = bytes;
+            }
+        }
+        largest = Some(passed);
+    }
+    match largest {
+        Some(bytes) => {
+            outln!("{}", success.paint(format!("Largest passing payload is {} bytes", bytes)));
+            true
+        },
+        None => {
+            outln!("{}", fail.paint("No payload size passed"));
+            false
+        }
+    }
+}
+
+/// Write a burst of serial frames without waiting for their packets, and then read the packets and
+/// match them with the frames. This shows whether the FPGA queues triggers that come faster than
+/// it sends, or whether it drops or reorders them.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+/// * `n` - The number of frames in the burst.
+///
+/// # Returns
+///
+/// An indicator of whether every packet arrived in order with the expected payload.
+fn burst(params: &Params, n: usize) -> bool {
+    let fail = color::style(Style::new().bold().fg(Colour::Red));
+    let success = color::style(Style::new().bold().fg(Colour::Green));
+    let mut port = or_fatal(open_port(params));
+    let (socket, _membership) = or_fatal(bind_socket(params));
+    let mut rng = params.rng();
+    let mut test_cases: Vec<TestCase> = Vec::with_capacity(n);
+    for i in 0..n {
+        let mut test_case = next_test_case(params, test_cases.last(), &mut rng);
+        test_case.seq = i as u16;
+        test_cases.push(test_case);
+    }
+    let expected: Vec<Vec<u8>> = or_fatal(test_cases.iter().map(|t| t.expected()).collect());
+    // Every frame is written before any packet is read, so the FPGA has to keep up on its own
+    let mut frame = vec![];
+    let start = Instant::now();
+    for test_case in &test_cases {
+        test_case.write_bytes(&mut frame);
+        if let Err(msg) = write_frame(&mut *port, &frame) {
+            fatal("Could not write serial frame", msg)
+        }
+    }
+    info!("Wrote {} serial frames in {} us", n, start.elapsed().as_micros());
+    let headers = if params.check_checksums { checksum::MAX_HEADER_LEN } else { 0 };
+    let mut buf = vec![0; params.packet_len() + headers + 1];
+    let mut matched = vec![false; n];
+    let mut order: Vec<usize> = vec![];
+    let mut received = 0;
+    let mut unmatched = 0;
+    while received < n {
+        let size = match socket.recv_from(&mut buf) {
+            Ok((size, _)) => size,
+            Err(ref err) if is_timeout(err) => break,
+            Err(err) => fatal("Could not read socket", err.to_string())
+        };
+        received += 1;
+        let actual = if params.check_checksums {
+            match checksum::strip(&buf[..size]) {
+                Ok(payload) => payload,
+                Err(msg) => {
+                    unmatched += 1;
+                    outln!("{}: {}", fail.paint(format!("Packet {}", received)), msg);
+                    continue;
+                }
+            }
+        } else {
+            &buf[..size]
+        };
+        // The packet belongs to the first frame that is still waiting for a packet like it
+        let frame = (0..n).find(|&i| !matched[i]
+            && compare_payload(params, &expected[i], actual).is_ok());
+        match frame {
+            Some(i) => {
+                matched[i] = true;
+                order.push(i);
+            },
+            None => {
+                unmatched += 1;
+                outln!("{}: Does not match any frame: {}",
+                    fail.paint(format!("Packet {}", received)), hex::string(actual));
+            }
+        }
+    }
+    // A packet is out of order when a packet of a later frame arrived before it
+    let mut latest = None;
+    let mut out_of_order = 0;
+    for &i in &order {
+        if latest.is_some_and(|l| i < l) {
+            out_of_order += 1;
+        } else {
+            latest = Some(i);
+        }
+    }
+    let missing: Vec<String> = (0..n).filter(|&i| !matched[i]).map(|i| (i + 1).to_string())
+        .collect();
+    outln!("Received {} of {} packets", received, n);
+    if !missing.is_empty() {
+        let mut list = missing.iter().take(MAX_LISTED_CASES).cloned().collect::<Vec<_>>()
+            .join(", ");
+        if missing.len() > MAX_LISTED_CASES {
+            list = format!("{}, and {} more", list, missing.len() - MAX_LISTED_CASES);
+        }
+        outln!("No packet for frames: {}", list);
+    }
+    if out_of_order > 0 {
+        outln!("{} packets arrived after a packet of a later frame", out_of_order);
+    } else if !order.is_empty() {
+        outln!("Packets arrived in order");
+    }
+    let passed = missing.is_empty() && unmatched == 0 && out_of_order == 0;
+    if passed {
+        outln!("{}", success.paint(format!("Passed burst of {} frames", n)));
+    } else {
+        outln!("{}", fail.paint(format!(
+            "Failed burst of {} frames: {} missing, {} unmatched, {} out of order", n,
+            missing.len(), unmatched, out_of_order)));
+    }
+    passed
+}
+
+/// Send frames one at a time from commands on standard input and show the packets that come
+/// back, until the input ends or the session is quit.
+///
+/// # Arguments
+///
+/// * `params` - The test parameters.
+fn interactive(params: &Params) {
+    let fail = color::style(Style::new().bold().fg(Colour::Red));
+    let success = color::style(Style::new().bold().fg(Colour::Green));
+    let mut port = or_fatal(open_port(params));
+    let (socket, _membership) = if !params.no_socket {
+        let (s, membership) = or_fatal(bind_socket(params));
+        (Some(s), membership)
+    } else {
+        (None, None)
+    };
+    let mut test_case = TestCase::new(params, &mut params.rng());
+    let mut frame = vec![];
+    // Hand-crafted frames may produce a packet of any size, so the buffer fits any datagram
+    let mut buf = vec![0; 1 << 16];
+    // Write a frame and show the packet that comes back, if any
+    let mut send = |frame: &[u8]| -> Option<Vec<u8>> {
+        if let Err(msg) = write_frame(&mut *port, frame) {
+            outln!("{}: {}", fail.paint("Failed"), msg);
+            return None;
+        }
+        outln!("Wrote {} serial bytes: {}", frame.len(), hex::string(frame));
+        let s = match socket {
+            Some(ref s) => s,
+            None => {
+                outln!("{}", success.paint("(No socket open)"));
+                return None;
+            }
+        };
+        match s.recv_from(&mut buf) {
+            Ok((size, socket_addr)) => {
+                outln!("Received {} bytes from {}", size, socket_addr);
+                outln!("{}", hex::dump(&buf[..size]));
+                Some(buf[..size].to_vec())
+            },
+            Err(err) => {
+                outln!("{}: Could not read socket: {}", fail.paint("Failed"), err);
+                None
+            }
+        }
+    };
+    outln!("Type `help` for the commands");
+    let stdin = io::stdin();
+    loop {
+        out!("> ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {},
+            Err(err) => fatal("Could not read command", err)
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {},
+            ["help"] => {
+                outln!("send          Send the frame of the test case and compare its packet");
+                outln!("send HEX      Write raw bytes to the serial port");
+                outln!("set seed N    Change the seed of the test case");
+                outln!("set gen N     Change the generator of the test case");
+                outln!("show          Print the test case and its frame");
+                outln!("quit          End the session");
+            },
+            ["quit"] | ["exit"] => break,
+            ["show"] => {
+                test_case.write_bytes(&mut frame);
+                outln!("Seed 0x{:02X}, Gen 0x{:02X}, {} bytes", test_case.seed, test_case.gen,
+                    params.bytes);
+                outln!("Frame: {}", hex::string(&frame));
+            },
+            ["set", "seed", v] => match params::parse_data_value(v, params.word_mode) {
+                Some(seed) => test_case.seed = seed,
+                None => outln!("Bad seed value: {}", v)
+            },
+            ["set", "gen", v] => match params::parse_data_value(v, params.word_mode) {
+                Some(gen) => test_case.gen = gen,
+                None => outln!("Bad gen value: {}", v)
+            },
+            ["send"] => {
+                let expected = match test_case.expected() {
+                    Ok(e) => e,
+                    Err(msg) => {
+                        outln!("{}: {}", fail.paint("Failed"), msg);
+                        continue;
+                    }
+                };
+                test_case.write_bytes(&mut frame);
+                if let Some(actual) = send(&frame) {
+                    match compare_payload(params, &expected, &actual) {
+                        Ok(_) => outln!("{}", success.paint("Passed")),
+                        Err(mismatch) => outln!("{}: {}\n{}", fail.paint("Failed"), mismatch,
+                            compare::window(&expected, &actual, mismatch.offset,
+                                params.tolerance))
+                    }
+                }
+            },
+            ["send", ..] => match hex::parse(&words[1..].concat()) {
+                Some(bytes) => {
+                    send(&bytes);
+                },
+                None => outln!("Bad hex value: {}", words[1..].join(" "))
+            },
+            _ => outln!("Unknown command: {}", line.trim())
+        }
+    }
+}
+
+/// Print the name and arguments of every profile.
+fn list_profiles() {
+    let profiles = match profile::all() {
+        Ok(p) => p,
+        Err(msg) => fatal("Could not read profiles", msg)
+    };
+    let width = profiles.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, args) in profiles {
+        outln!("{:<width$}  {}", name, args.join(" "), width = width);
+    }
+}
+
+/// Run the tests of one line of arguments on the serial port and socket that are kept open, which
+/// are opened again when the line needs other ones.
+///
+/// # Arguments
+///
+/// * `args` - The arguments that the line is added to, which start with the program name.
+/// * `line` - The arguments of the line, separated by spaces.
+/// * `kept` - The serial port and socket that are kept open.
+/// * `out` - Where to print the results and the summary of the tests.
+///
+/// # Returns
+///
+/// An indicator of whether the tests passed with the outcome of the tests, or an error message
+/// when the arguments of the line are bad or its serial port or socket cannot be set up.
+fn run_line(args: &[String], line: &str, kept: &mut KeptOpen, out: &mut dyn Write)
+    -> Result<(bool, Outcome), String> {
+    let line_start = Instant::now();
+    let mut line_args = args.to_vec();
+    line_args.extend(line.split_whitespace().map(String::from));
+    let line_params = match Params::from_args(&line_args) {
+        Ok(ref p) if p.devices.len() > 1 => Err(format!("A line may only test one device, but \
+            got {} destinations", p.devices.len())),
+        Ok(ref p) if p.dump_expected => Err("A line cannot dump the expected payload"
+            .to_string()),
+        result => result
+    }?;
+    check_params(&line_params, slice::from_ref(&line_params));
+
+    // The loopback depends on the parameters of the line, so it is always started again
+    let port_key = (line_params.serial_port.clone(), line_params.serial_baud.speed());
+    if line_params.loopback || kept.port.as_ref().map_or(true, |(key, _)| *key != port_key) {
+        // The old port is closed first in case the line uses it at another baudrate
+        drop(kept.port.take());
+        kept.port = Some((port_key, open_port(&line_params)?));
+    }
+    let socket_key = (line_params.bind_ip(), line_params.dest_port, line_params.rcvbuf,
+        line_params.multicast.map(|group| (group, line_params.membership_ip())));
+    if line_params.no_socket {
+        // Another program reads the port, so the socket must not hold it
+        kept.socket = None;
+    } else if kept.socket.as_ref().map_or(true, |(key, _, _)| *key != socket_key) {
+        drop(kept.socket.take());
+        let (s, membership) = bind_socket(&line_params)?;
+        kept.socket = Some((socket_key, s, membership));
+    } else if let Some((_, ref s, _)) = kept.socket {
+        // A duration on an earlier line may have shortened the read timeout
+        s.set_read_timeout(Some(line_params.timeout))
+            .map_err(|err| format!("Could not set socket read timeout: {}", err))?;
+    }
+    send_control(&line_params, &line_params.control_start)?;
+    let outcome = match kept.port {
+        Some((_, ref mut p)) => run_tests(&line_params, "", &mut **p,
+            kept.socket.as_ref().map_or(&[], |(_, s, _)| slice::from_ref(s)), out)?,
+        None => unreachable!()
+    };
+    send_control(&line_params, &line_params.control_stop)?;
+    // A batch replaces the summary of each line with its own at the end
+    if let Some(ref path) = line_params.summary_json {
+        let mut summary = metrics(slice::from_ref(&outcome), line_start.elapsed());
+        if let Err(msg) = summary.write_json(path) {
+            warn("Could not write summary file", msg);
+        }
+    }
+    let passed = report(&line_params, slice::from_ref(&outcome), out);
+    Ok((passed, outcome))
+}
+
+/// Run the tests of every parameter set on standard input in turn. The serial port and socket
+/// stay open between lines that use the same ones, so a sweep does not pay to reopen them.
+///
+/// # Arguments
+///
+/// * `params` - The parameters from the command line.
+/// * `main_start` - The time that the program started.
+///
+/// # Returns
+///
+/// An indicator of whether every line ran and passed.
+fn run_batch(params: &Params, main_start: Instant) -> bool {
+    let title = color::style(Style::new().bold().fg(Colour::Blue));
+    let fail = color::style(Style::new().bold().fg(Colour::Red));
+    let success = color::style(Style::new().bold().fg(Colour::Green));
+    // Every line is added to the command line, which holds the arguments that the lines share
+    let args: Vec<String> = env::args().filter(|arg| arg != "--batch").collect();
+    let mut kept = KeptOpen::default();
+    let mut results: Vec<(usize, bool)> = vec![];
+    let mut outcomes: Vec<Outcome> = vec![];
+    let mut skipped = 0;
+    let stdin = io::stdin();
+    for (i, line) in stdin.lock().lines().enumerate() {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            break;
+        }
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => fatal("Could not read batch", err)
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        outln!("{}", title.paint(format!("Batch line {}: {}", i + 1, line)));
+        let (passed, outcome) = match run_line(&args, line, &mut kept, &mut io::stdout()) {
+            Ok(result) => result,
+            Err(msg) => {
+                warn(format!("Skipped batch line {}", i + 1), msg);
+                outln!();
+                skipped += 1;
+                continue;
+            }
+        };
+        outln!();
+        results.push((i + 1, passed));
+        outcomes.push(outcome);
+    }
+
+    // Total up the lines so that the failing lines stand out in a long batch
+    outln!("{}", title.paint("Batch Summary"));
+    outln!("{}", title.paint("-------------"));
+    let num_run: usize = outcomes.iter().map(|o| o.num_run).sum();
+    let num_failed: u64 = outcomes.iter().map(|o| o.num_failed).sum();
+    let lines = |n: usize| format!("{} line{}", n, if n == 1 { "" } else { "s" });
+    if num_failed > 0 {
+        outln!("{}", fail.paint(format!("Failed {} of {} tests in {}", num_failed, num_run,
+            lines(results.len()))));
+    } else {
+        outln!("{}", success.paint(format!("Passed all {} tests in {}", num_run,
+            lines(results.len()))));
+    }
+    for (&(line, passed), o) in results.iter().zip(&outcomes) {
+        if !passed {
+            outln!("  Line {}: {}", line,
+                fail.paint(format!("Failed {} of {} tests", o.num_failed, o.num_run)));
+        }
+    }
+    if skipped > 0 {
+        outln!("{}", fail.paint(format!("Skipped {} that could not run", lines(skipped))));
+    }
+    if let Some(ref path) = params.metrics {
+        write_metrics(path, &outcomes, main_start.elapsed());
+    }
+    if let Some(ref path) = params.summary_json {
+        write_summary_json(path, &outcomes, main_start.elapsed());
+    }
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        warn("Interrupted", "The batch stopped before all of its lines ran");
+    }
+    skipped == 0 && results.iter().all(|&(_, passed)| passed)
+}
+
+/// Serve test requests from clients on a Unix socket until the program is stopped. Each request
+/// is a line of arguments that is run like a batch line, and its output goes back to the client.
+/// The serial port and socket stay open between requests that use the same ones, so the port is
+/// not opened again for every run, which resets the FPGA on some boards.
+///
+/// # Arguments
+///
+/// * `path` - The path of the socket to listen on.
+#[cfg(unix)]
+fn serve(path: &str) {
+    // Every request is added to the command line, which holds the arguments that they share
+    let mut args: Vec<String> = vec![];
+    let mut rest = env::args();
+    while let Some(arg) = rest.next() {
+        if arg == "--daemon" {
+            rest.next();
+        } else if !arg.starts_with("--daemon=") {
+            args.push(arg);
+        }
+    }
+    let listener = match daemon::listen(path) {
+        Ok(l) => l,
+        Err(msg) => fatal("Could not start daemon", msg)
+    };
+    outln!("Listening for requests on {}", path);
+    let mut kept = KeptOpen::default();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(err) => {
+                warn("Could not accept request", err);
+                continue;
+            }
+        };
+        let line = match daemon::read_request(&stream) {
+            Ok(line) => line,
+            Err(err) => {
+                warn("Could not read request", err);
+                continue;
+            }
+        };
+        info!("Request: {}", line);
+        // The output of the request goes back to the client rather than to the daemon
+        let mut out = &stream;
+        let passed = match run_line(&args, &line, &mut kept, &mut out) {
+            Ok((passed, _)) => passed,
+            Err(msg) => {
+                warn_to(&mut out, "Skipped request", msg);
+                false
+            }
+        };
+        // A client that went away does not need its status
+        let _ = daemon::send_status(&stream, passed);
+    }
+}
+
+/// Send the other arguments to a daemon as a request, and exit with its status.
+///
+/// # Arguments
+///
+/// * `args` - The command line arguments.
+#[cfg(unix)]
+fn client(args: &[String]) {
+    let mut path = None;
+    let mut request = vec![];
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        if arg == "--client" {
+            path = rest.next().cloned();
+        } else if let Some(p) = arg.strip_prefix("--client=") {
+            path = Some(p.to_string());
+        } else {
+            request.push(arg.clone());
+        }
+    }
+    let path = match path {
+        Some(p) => p,
+        None => fatal("Bad command line argument", "The client needs the path of a daemon")
+    };
+    match daemon::request(&path, &request, &mut io::stdout()) {
+        Ok(true) => {},
+        Ok(false) => std::process::exit(1),
+        Err(msg) => fatal("Could not reach daemon", msg)
+    }
+}
+
+/// Serve test requests from clients. The requests come in on a Unix socket, which this platform
+/// does not have.
+///
+/// # Arguments
+///
+/// * `path` - The path of the socket to listen on.
+#[cfg(not(unix))]
+fn serve(_path: &str) {
+    fatal("Bad command line argument", "The daemon needs Unix sockets")
+}
+
+/// Send a request to a daemon. The daemon listens on a Unix socket, which this platform does not
+/// have.
+///
+/// # Arguments
+///
+/// * `args` - The command line arguments.
+#[cfg(not(unix))]
+fn client(_args: &[String]) {
+    fatal("Bad command line argument", "The client needs Unix sockets")
+}
+
+fn main() {
+    let main_start = Instant::now();
+    // The profiles are listed on their own, since none of the other arguments are needed for it
+    if env::args().any(|arg| arg == "--list-profiles") {
+        list_profiles();
+        return;
+    }
+    // A client only passes its arguments on, so they are checked by the daemon
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|arg| arg == "--client" || arg.starts_with("--client=")) {
+        client(&args);
+        return;
+    }
+    // Get the command line arguments
+    color::set_enabled(true);
+    let params = match Params::get() {
+        Ok(p) => p,
+        Err(msg) => fatal("Bad command line argument", msg)
+    };
+    color::set_enabled(!params.no_color);
+    logger::init(params.verbosity);
+    output::set_quiet(params.quiet);
+
+    // Print the expected payload on its own when asked, since no hardware is needed for it
+    if params.dump_expected {
+        let test_case = TestCase::new(&params, &mut params.rng());
+        outln!("Seed 0x{:02X}, Gen 0x{:02X}, {} bytes", test_case.seed, test_case.gen,
+            params.bytes);
+        outln!("{}", hex::dump(&or_fatal(test_case.expected())));
+        return;
+    }
+
+    let title = color::style(Style::new().bold().fg(Colour::Blue));
+    // A capture is compared on its own, since no hardware is needed for it
+    if let Some(ref path) = params.compare_pcap {
+        let passed = compare_pcap(&params, path);
+        finish(&params, passed, "Capture does not match the expected payloads");
+        return;
+    }
+    if params.interactive {
+        if params.devices.len() > 1 {
+            fatal("Bad command line argument", "Interactive mode tests only one device")
+        }
+        interactive(&params);
+        return;
+    }
+    if params.batch {
+        // The summary file must be written even when the batch is cut short
+        catch_interrupt();
+        let passed = run_batch(&params, main_start);
+        finish(&params, passed, "Batch failed");
+        return;
+    }
+    if let Some(ref path) = params.daemon {
+        serve(path);
+        return;
+    }
+    let devices = params.device_params();
+    check_params(&params, &devices);
+
+    // Print the test parameters, which are left out of a TAP stream
+    if params.format != Format::Tap {
+        outln!("{}", title.paint("Parameters"));
+        outln!("{}", title.paint("----------"));
+        if params.format == Format::Table {
+            outln!("{}", table::format(&parameter_rows(&params, &devices)));
+        } else {
+            print_parameters(&params, &devices);
+        }
+        outln!();
+    }
+    if params.check_endian {
+        outln!("{}", title.paint("Byte Order"));
+        outln!("{}", title.paint("----------"));
+        print_byte_order(&params);
+        outln!();
+    }
+
+    if params.baud_sweep {
+        if params.devices.len() > 1 {
+            fatal("Bad command line argument", "A baudrate sweep tests only one device")
+        }
+        outln!("{}", title.paint("Results"));
+        outln!("{}", title.paint("-------"));
+        let passed = baud_sweep(&params);
+        finish(&params, passed, "No baudrate passed");
+        return;
+    }
+    if params.mtu_discover {
+        if params.devices.len() > 1 {
+            fatal("Bad command line argument", "MTU discovery tests only one device")
+        }
+        outln!("{}", title.paint("Results"));
+        outln!("{}", title.paint("-------"));
+        let passed = mtu_discover(&params);
+        finish(&params, passed, "No payload size passed");
+        return;
+    }
+    if let Some(n) = params.burst {
+        outln!("{}", title.paint("Results"));
+        outln!("{}", title.paint("-------"));
+        or_fatal(send_control(&params, &params.control_start));
+        let passed = burst(&params, n);
+        or_fatal(send_control(&params, &params.control_stop));
+        finish(&params, passed, "Burst failed");
+        return;
+    }
+
+    // Run the tests, with one thread per device when there are several. The summary file must be
+    // written even when the run is cut short.
+    catch_interrupt();
+    if params.format != Format::Tap {
+        outln!("{}", title.paint("Results"));
+        outln!("{}", title.paint("-------"));
+    }
+    or_fatal(send_control(&params, &params.control_start));
+    let mut errors: Vec<String> = vec![];
+    let outcomes: Vec<Outcome> = if params.round_robin {
+        // The destinations share the serial port, so they are tested together
+        vec![or_fatal(run(&params, ""))]
+    } else if devices.len() == 1 {
+        vec![or_fatal(run(&devices[0], ""))]
+    } else {
+        // A device that cannot be tested must not stop the threads of the others
+        let handles: Vec<_> = devices.into_iter().map(|device| {
+            thread::spawn(move || {
+                let label = device.label();
+                info!("Run seed of {}: {}", label, device.run_seed);
+                run(&device, &label).map_err(|msg| format!("{}: {}", label, msg))
+            })
+        }).collect();
+        let mut outcomes = vec![];
+        for h in handles {
+            match h.join() {
+                Ok(Ok(outcome)) => outcomes.push(outcome),
+                Ok(Err(msg)) => errors.push(msg),
+                Err(_) => fatal("Device thread panicked", "Results are incomplete")
+            }
+        }
+        outcomes
+    };
+    or_fatal(send_control(&params, &params.control_stop));
+
+    // Print a summary of what happened
+    if let Some(ref path) = params.metrics {
+        write_metrics(path, &outcomes, main_start.elapsed());
+    }
+    if let Some(ref path) = params.summary_json {
+        write_summary_json(path, &outcomes, main_start.elapsed());
+    }
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        warn("Interrupted", "The tests stopped before all of them ran");
+    }
+    let passed = report(&params, &outcomes, &mut io::stdout());
+    // The devices that could not be tested come after the summary of the others
+    for msg in &errors {
+        error(msg);
+    }
+    let num_run: usize = outcomes.iter().map(|o| o.num_run).sum();
+    let num_failed: u64 = outcomes.iter().map(|o| o.num_failed).sum();
+    let failure = if errors.is_empty() {
+        format!("Failed {} of {} tests", num_failed, num_run)
+    } else {
+        format!("Could not test {} devices", errors.len())
+    };
+    finish(&params, passed && errors.is_empty(), &failure);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A packet whose CRC differs from that of the expected payload falls back to the byte by
+    /// byte comparison, which reports the first wrong byte.
+    #[test]
+    fn compare_hash_fallback() {
+        let params = params::test_params(&["--compare-hash", "--bytes", "16"]);
+        let expected: Vec<u8> = (0..16).collect();
+        let crc = Some(crc::crc32(&expected));
+        assert!(crc_matches(&params, &expected, crc, &expected));
+        let mut actual = expected.clone();
+        actual[5] ^= 0x10;
+        actual[9] ^= 0x01;
+        assert!(!crc_matches(&params, &expected, crc, &actual));
+        let mismatch = compare_payload(&params, &expected, &actual).unwrap_err();
+        assert_eq!((mismatch.offset, mismatch.expected, mismatch.actual), (5, Some(5), Some(0x15)));
+        // A packet that is cut short never matches, even when its bytes are right
+        assert!(!crc_matches(&params, &expected, crc, &expected[..8]));
+        assert_eq!(compare_payload(&params, &expected, &expected[..8]).unwrap_err().offset, 8);
+        // Without the CRC, the bytes are always compared
+        assert!(!crc_matches(&params, &expected, None, &expected));
+    }
+
+    /// A port that takes one byte for each write, and is interrupted before every other byte.
+    #[derive(Default)]
+    struct Trickle {
+        /// The bytes that were written.
+        written: Vec<u8>,
+
+        /// The number of writes so far.
+        calls: usize
+    }
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls % 2 == 1 {
+                return Err(io::Error::from(io::ErrorKind::Interrupted));
+            }
+            self.written.push(buf[0]);
+            Ok(1)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A port that takes some bytes and then stops taking any, like a port whose device is gone.
+    struct Stall {
+        /// The number of bytes that are taken before the port stops.
+        left: usize
+    }
+
+    impl Write for Stall {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.left);
+            self.left -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A frame that the port takes one byte at a time, with interrupted writes between the bytes,
+    /// is written whole.
+    #[test]
+    fn write_frame_trickle() {
+        let frame: Vec<u8> = (0..26).collect();
+        let mut port = Trickle::default();
+        assert_eq!(write_frame(&mut port, &frame), Ok(()));
+        assert_eq!(port.written, frame);
+    }
+
+    /// A port that stops taking bytes is a partial write with the number of bytes that were
+    /// taken, rather than a loop that never ends.
+    #[test]
+    fn write_frame_zero() {
+        let frame: Vec<u8> = (0..26).collect();
+        assert_eq!(write_frame(&mut Stall { left: 0 }, &frame),
+            Err("Partial serial write: 0/26 bytes".to_string()));
+        assert_eq!(write_frame(&mut Stall { left: 10 }, &frame),
+            Err("Partial serial write: 10/26 bytes".to_string()));
+    }
+
+    /// The payload of a packet is found after the headers of a raw frame and after the checked
+    /// headers, so that it can be compared with the payload of the expected frame.
+    #[test]
+    fn packet_payload_headers() {
+        let mut params = params::test_params(&[]);
+        let test_case = TestCase::new(&params, &mut rand::thread_rng());
+        let payload = test_case.expected().unwrap();
+        assert_eq!(packet_payload(&params, &payload), &payload[..]);
+        let frame = raw::frame(&test_case, &payload);
+        params.raw = true;
+        assert_eq!(packet_payload(&params, &frame), &payload[..]);
+        assert_eq!(packet_payload(&params, &frame[..4]), &[][..]);
+        params.raw = false;
+        params.check_checksums = true;
+        let packet = &frame[raw::HEADER_LEN - 28..];
+        assert_eq!(packet_payload(&params, packet), &payload[..]);
+        // A packet whose headers are bad is compared whole
+        assert_eq!(packet_payload(&params, &[0; 8]), &[0; 8][..]);
+    }
+
+    /// Late packets are found across the wrap of the sequence number from 0xFFFF to 0, up to
+    /// half of the range back, and never from tests that were not sent.
+    #[test]
+    fn late_seq_wrap() {
+        let payload = |seq: u16| seq.to_be_bytes().to_vec();
+        let many = 1 << 20;
+        assert_eq!(late_seq(&payload(0xFFFF), 0, many), Some(0xFFFF));
+        assert_eq!(late_seq(&payload(0xFFFE), 1, many), Some(0xFFFE));
+        assert_eq!(late_seq(&payload(0), 0, many), None);
+        // A packet from after the current test is not late
+        assert_eq!(late_seq(&payload(0), 0xFFFF, many), None);
+        assert_eq!(late_seq(&payload(2), 1, many), None);
+        // Half of the range back is the oldest packet that counts as late
+        assert_eq!(late_seq(&payload(0x8001), 0, many), Some(0x8001));
+        assert_eq!(late_seq(&payload(0x8000), 0, many), None);
+        assert_eq!(late_seq(&payload(0x7FFF), 0xFFFE, many), Some(0x7FFF));
+        assert_eq!(late_seq(&payload(0x7FFE), 0xFFFE, many), None);
+        // Only the tests that were sent can have late packets
+        assert_eq!(late_seq(&payload(0xFFFE), 1, 4), Some(0xFFFE));
+        assert_eq!(late_seq(&payload(0xFFFD), 1, 4), None);
+        assert_eq!(late_seq(&[0], 1, many), None);
+    }
+
+    /// The payloads of a continuous run follow on from each other like one long ramp, which is
+    /// what the counter in the firmware sends, across the wrap of the bytes and words.
+    #[test]
+    fn continuous_wrap() {
+        const TESTS: usize = 40;
+        for extra in &[&["--gen", "7"][..], &["--gen", "7", "--ramp-wrap-at", "100"],
+                &["--gen", "3", "--bytes", "7"], &["--gen", "0x1234", "--word-mode"],
+                &["--gen", "0x1234", "--word-mode", "--frame-endian", "little"]] {
+            let params = params::test_params(&[&["--continuous", "--run-seed", "300"][..], extra]
+                .concat());
+            let mut rng = params.rng();
+            let mut test_cases: Vec<TestCase> = vec![];
+            for _ in 0..TESTS {
+                let test_case = next_test_case(&params, test_cases.last(), &mut rng);
+                test_cases.push(test_case);
+            }
+            let actual: Vec<u8> = test_cases.iter().flat_map(|t| t.expected().unwrap()).collect();
+            let (seed, gen, bytes) = (test_cases[0].seed, test_cases[0].gen, TESTS * params.bytes);
+            let expected = if params.word_mode {
+                test_case::word_ramp(seed, gen, bytes, params.frame_endian)
+            } else {
+                test_case::ramp(seed as u8, gen as u8, bytes, params.ramp_wrap_at)
+            };
+            assert_eq!(actual, expected, "{:?}", extra);
+            assert!(test_cases.iter().all(|t| t.gen == gen), "{:?}", extra);
+        }
+    }
+
+    /// Time the serial frame and the receive loop over the loopback interface with buffers that
+    /// are reused and with buffers that are allocated for every test. The best of a few rounds is
+    /// printed because the socket calls are noisy. This is slow, so it only runs with
+    /// `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_buffer_reuse() {
+        const REPS: usize = 200_000;
+        const ROUNDS: usize = 5;
+        let params = params::test_params(&["--bytes", "1400"]);
+        let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        tx.connect(rx.local_addr().unwrap()).unwrap();
+        let payload = vec![0xA5; params.bytes];
+        let cases: Vec<TestCase> = (0..256).map(|i| TestCase::with_seed_gen(&params, i, 1))
+            .collect();
+        let best = |f: &mut dyn FnMut(bool), reuse: bool| (0..ROUNDS).map(|_| {
+            let start = Instant::now();
+            f(reuse);
+            start.elapsed().as_secs_f64() * 1e9 / REPS as f64
+        }).fold(f64::INFINITY, f64::min);
+        let mut frame = vec![];
+        let mut write = |reuse: bool| for rep in 0..REPS {
+            if !reuse {
+                frame = vec![];
+            }
+            cases[rep % cases.len()].write_bytes(&mut frame);
+        };
+        let mut buf = vec![0; params.packet_len() + 1];
+        let mut receive = |reuse: bool| for _ in 0..REPS {
+            if !reuse {
+                buf = vec![0; params.packet_len() + 1];
+            }
+            tx.send(&payload).unwrap();
+            assert_eq!(rx.recv_from(&mut buf).unwrap().0, payload.len());
+        };
+        for &reuse in &[false, true] {
+            println!("{}: write_bytes {:.0} ns/test, receive {:.0} ns/test",
+                if reuse { "reused" } else { "allocated" }, best(&mut write, reuse),
+                best(&mut receive, reuse));
         }
     }
-    println!();
 }
@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+
+/// The quantiles of the latency summary.
+const QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+
+/// The totals of a run that are written as Prometheus metrics or as a JSON summary.
+pub struct Metrics {
+    /// The number of tests that were run.
+    pub tests: u64,
+
+    /// The number of tests that failed.
+    pub failures: u64,
+
+    /// The number of packets received.
+    pub packets: u64,
+
+    /// The number of payload bytes received.
+    pub bytes: u64,
+
+    /// The time from writing each serial frame to receiving its packet.
+    pub latencies: Vec<Duration>,
+
+    /// The time that the whole run took.
+    pub duration: Duration,
+
+    /// Indicates that the run was interrupted before all of the tests ran.
+    pub interrupted: bool
+}
+
+impl Metrics {
+    /// Write the metrics to a file in the Prometheus text format, replacing any existing file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error message on a failed write.
+    pub fn write(&mut self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|err| format!("{}: {}", path, err))?;
+        let mut out = BufWriter::new(file);
+        self.format(&mut out).and_then(|_| out.flush()).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    /// Write the summary of the run to a file as a JSON object, replacing any existing file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error message on a failed write.
+    pub fn write_json(&mut self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|err| format!("{}: {}", path, err))?;
+        let mut out = BufWriter::new(file);
+        self.format_json(&mut out)
+            .and_then(|_| out.flush())
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    /// Format the summary of the run as a JSON object.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer to format the summary to.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error on a failed write.
+    fn format_json<W: Write>(&mut self, out: &mut W) -> std::io::Result<()> {
+        let rate = if self.tests > 0 {
+            100.0 * (self.tests - self.failures) as f64 / self.tests as f64
+        } else {
+            100.0
+        };
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"tests\": {},", self.tests)?;
+        writeln!(out, "  \"passed\": {},", self.tests - self.failures)?;
+        writeln!(out, "  \"failed\": {},", self.failures)?;
+        writeln!(out, "  \"pass_rate\": {},", rate)?;
+        writeln!(out, "  \"duration_seconds\": {},", self.duration.as_secs_f64())?;
+        writeln!(out, "  \"interrupted\": {},", self.interrupted)?;
+        // The quantiles are left out when no packet was received, rather than made up
+        self.latencies.sort();
+        let quantiles: Vec<String> = if self.latencies.is_empty() {
+            vec![]
+        } else {
+            QUANTILES.iter().map(|&q| {
+                let i = ((self.latencies.len() - 1) as f64 * q).round() as usize;
+                format!("\"{}\": {}", q, self.latencies[i].as_secs_f64())
+            }).collect()
+        };
+        writeln!(out, "  \"latency_seconds\": {{{}}}", quantiles.join(", "))?;
+        writeln!(out, "}}")
+    }
+
+    /// Format the metrics in the Prometheus text format.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer to format the metrics to.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error on a failed write.
+    fn format<W: Write>(&mut self, out: &mut W) -> std::io::Result<()> {
+        let counters = [
+            ("tests_total", "The number of tests that were run.", self.tests),
+            ("failures_total", "The number of tests that failed.", self.failures),
+            ("packets_total", "The number of packets received.", self.packets),
+            ("bytes_total", "The number of payload bytes received.", self.bytes)
+        ];
+        for &(name, help, value) in &counters {
+            writeln!(out, "# HELP ether_tester_{} {}", name, help)?;
+            writeln!(out, "# TYPE ether_tester_{} counter", name)?;
+            writeln!(out, "ether_tester_{} {}", name, value)?;
+        }
+        self.latencies.sort();
+        writeln!(out, "# HELP ether_tester_latency_seconds The time from writing a serial frame to \
+            receiving its packet.")?;
+        writeln!(out, "# TYPE ether_tester_latency_seconds summary")?;
+        if !self.latencies.is_empty() {
+            for &q in &QUANTILES {
+                let i = ((self.latencies.len() - 1) as f64 * q).round() as usize;
+                writeln!(out, "ether_tester_latency_seconds{{quantile=\"{}\"}} {}", q,
+                    self.latencies[i].as_secs_f64())?;
+            }
+        }
+        let sum: f64 = self.latencies.iter().map(|l| l.as_secs_f64()).sum();
+        writeln!(out, "ether_tester_latency_seconds_sum {}", sum)?;
+        writeln!(out, "ether_tester_latency_seconds_count {}", self.latencies.len())?;
+        writeln!(out, "# HELP ether_tester_duration_seconds The time that the run took.")?;
+        writeln!(out, "# TYPE ether_tester_duration_seconds gauge")?;
+        writeln!(out, "ether_tester_duration_seconds {}", self.duration.as_secs_f64())
+    }
+}
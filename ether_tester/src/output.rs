@@ -0,0 +1,58 @@
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Indicates that the output is dropped for quiet mode.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Drop or keep the output. Output from every thread goes through here, so nothing else needs to
+/// check for quiet mode before printing.
+///
+/// # Arguments
+///
+/// * `quiet` - Whether the output should be dropped.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Print to standard output, unless the output is dropped.
+///
+/// # Arguments
+///
+/// * `args` - The formatted text.
+pub fn print(args: fmt::Arguments) {
+    if !QUIET.load(Ordering::Relaxed) {
+        print!("{}", args);
+    }
+}
+
+/// Write to a stream, such as the connection of a daemon client, unless the output is dropped.
+///
+/// # Arguments
+///
+/// * `out` - The stream to write to.
+/// * `args` - The formatted text.
+pub fn write(out: &mut dyn Write, args: fmt::Arguments) {
+    if !QUIET.load(Ordering::Relaxed) {
+        // A stream that went away, such as a client that left, does not stop the tests
+        let _ = out.write_fmt(args);
+    }
+}
+
+/// Print to standard output, or to a stream given by `to`, unless the output is dropped for quiet
+/// mode.
+macro_rules! out {
+    (to $out:expr, $($arg:tt)*) => { $crate::output::write($out, format_args!($($arg)*)) };
+    ($($arg:tt)*) => { $crate::output::print(format_args!($($arg)*)) }
+}
+
+/// Print a line to standard output, or to a stream given by `to`, unless the output is dropped
+/// for quiet mode.
+macro_rules! outln {
+    (to $out:expr) => { $crate::output::write($out, format_args!("\n")) };
+    (to $out:expr, $($arg:tt)*) => {
+        $crate::output::write($out, format_args!("{}\n", format_args!($($arg)*)))
+    };
+    () => { $crate::output::print(format_args!("\n")) };
+    ($($arg:tt)*) => { $crate::output::print(format_args!("{}\n", format_args!($($arg)*))) }
+}
@@ -1,8 +1,30 @@
 extern crate ansi_term;
-use clap::{App, ArgMatches};
+use clap::{App, AppSettings, ArgMatches};
+use rand;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use regex::Regex;
 use serial::*;
+use std::env;
+use std::net::{Ipv4Addr, SocketAddrV4};
 use std::result::Result;
+use std::time::Duration;
+use super::frame;
+use super::frame::Field;
+use super::hex;
+use super::profile;
+use super::socket;
+use super::test_case;
+use super::test_case::TIMESTAMP_LEN;
+use ether_tester::Generator;
+
+/// The arguments that fall back to an environment variable and the name of each variable, which
+/// must match the `env` of the arguments in `app.yml`.
+const ENV_ARGS: &[(&str, &str)] = &[
+    ("dest", "ETHER_DEST"),
+    ("serial-port", "ETHER_SERIAL"),
+    ("src", "ETHER_SRC")
+];
 
 /// The regex pattern for matching a string of the form
 ///
@@ -13,20 +35,190 @@ use std::result::Result;
 /// Where the `i`s are IP address, `p`s are port, and `m`s are MAC address.
 const IP_PORT_MAC_REGEX: &str = r"^(\d+)\.(\d+)\.(\d+)\.(\d+):(\d+),([0-9a-fA-F]{2}):([0-9a-fA-F]{2}):([0-9a-fA-F]{2}):([0-9a-fA-F]{2}):([0-9a-fA-F]{2}):([0-9a-fA-F]{2})$";
 
+/// The largest payload that fits in a single UDP datagram.
+pub const MAX_UDP_PAYLOAD: usize = 65507;
+
+/// The largest UDP payload that fits in a 9000 byte jumbo frame without fragmenting.
+const JUMBO_PAYLOAD: usize = 8972;
+
+/// The size of the socket receive buffer for jumbo frames when none is given, which holds a few
+/// hundred packets.
+const JUMBO_RCVBUF: usize = 2 * 1024 * 1024;
+
+/// The largest UDP payload that fits in a standard 1500 byte Ethernet frame without fragmenting.
+const MTU_PAYLOAD: usize = 1472;
+
+/// The odd constant that spreads the index of a device over the bits of its run seed, which is
+/// 2^64 divided by the golden ratio.
+const SEED_SPREAD: u64 = 0x9E37_79B9_7F4A_7C15;
+
 /// The regex pattern for matching a serial port name and a baudrate of the form
 ///
 /// ```
 /// port:baud
 /// ```
-/// where `port` is the name of a port and `baud` is an integer for the baudrate.
-const SERIAL_BAUD_REGEX: &str = r"^([^:]+):(\d+)$";
+/// where `port` is the name of a port and `baud` is a number for the baudrate. The baudrate may
+/// have a fraction and a `k` or `M` suffix, so `115200`, `115.2k`, and `1M` are all accepted.
+const SERIAL_BAUD_REGEX: &str = r"^([^:]+):(\d+)(?:\.(\d+))?([kKmM]?)$";
+
+/// The standard baudrates that serial adapters are expected to support.
+pub const STANDARD_BAUDS: &[usize] = &[
+    110, 300, 600, 1200, 2400, 4800, 9600, 14400, 19200, 38400, 57600, 115200, 230400, 460800,
+    921600, 1000000, 2000000, 3000000
+];
+
+/// The way that a received payload is compared with the expected payload.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CompareMode {
+    /// Every byte is compared in order.
+    Exact,
+
+    /// The bytes are compared after sorting, so their order does not matter.
+    Sorted,
+
+    /// Only the distinct byte values are compared, so their order and count do not matter.
+    Set
+}
+
+/// The layout of the parameters and summary.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Headings with the values beside them.
+    Human,
+
+    /// Aligned tables.
+    Table,
+
+    /// A Test Anything Protocol stream.
+    Tap
+}
+
+/// The byte order of the multi-byte fields in the serial frame.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Endian {
+    /// The most significant byte is sent first.
+    Big,
+
+    /// The least significant byte is sent first.
+    Little
+}
+
+/// What to do when no packet arrives before the timeout.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TimeoutPolicy {
+    /// The test fails and testing continues.
+    Fail,
+
+    /// The serial frame is sent again, and the test fails once the retries run out.
+    Retry,
+
+    /// The test fails and the run stops.
+    Abort
+}
+
+/// A device to test, which is an FPGA on its own serial port that sends to its own destination.
+#[derive(Clone)]
+pub struct Device {
+    /// The host IP address, which stays the unicast address of the host with a multicast group.
+    pub dest_ip: u32,
+
+    /// The host port.
+    pub dest_port: u16,
+
+    /// The host MAC address.
+    pub dest_mac: u64,
+
+    /// The serial port to use.
+    pub serial_port: String,
+
+    /// The baudrate of the serial port.
+    pub serial_baud: BaudRate
+}
 
 /// The parameters to the program.
+#[derive(Clone)]
 pub struct Params {
+    /// Indicates that failed payloads should be checked for a rotation.
+    pub align_check: bool,
+
+    /// Indicates that multicast and broadcast MAC addresses are not warned about.
+    pub allow_group_mac: bool,
+
+    /// Indicates that a datagram is sent to the source before testing.
+    pub arp_warmup: bool,
+
+    /// Indicates that parameter sets are read from standard input, one per line.
+    pub batch: bool,
+
+    /// Indicates that the standard baudrates are tried to find the baudrate of the firmware.
+    pub baud_sweep: bool,
+
+    /// Indicates that the bit error rate is shown.
+    pub ber: bool,
+
+    /// The IP address of the host interface to join the multicast group on, if it is not the
+    /// host IP address.
+    pub bind: Option<u32>,
+
+    /// The number of times to retry binding the socket.
+    pub bind_retries: u32,
+
+    /// The number of serial frames to write before reading any packets, if testing a burst.
+    pub burst: Option<usize>,
+
     /// The number of bytes per test packet.
     pub bytes: usize,
 
-    /// The host IP address.
+    /// Indicates that packets carry their IPv4 and UDP headers, whose checksums are checked.
+    pub check_checksums: bool,
+
+    /// Indicates that the destination in the headers of each packet is checked.
+    pub check_dest: bool,
+
+    /// Indicates that the byte order of the host and the encoded source IP address are printed.
+    pub check_endian: bool,
+
+    /// Indicates that packets from addresses other than the source are rejected.
+    pub check_source: bool,
+
+    /// The number of bytes at the start of each packet to compare, or `None` to compare them all.
+    pub compare_bytes: Option<usize>,
+
+    /// Indicates that the byte order of the payload words is found from the packets.
+    pub compare_endian_auto: bool,
+
+    /// Indicates that a CRC of the payloads is compared before their bytes.
+    pub compare_hash: bool,
+
+    /// The way that a received payload is compared with the expected payload.
+    pub compare_mode: CompareMode,
+
+    /// The capture file to compare with the expected payloads instead of testing, if any.
+    pub compare_pcap: Option<String>,
+
+    /// Indicates that the payload sequence continues across repetitions.
+    pub continuous: bool,
+
+    /// The UDP control port of the firmware, if any.
+    pub control: Option<SocketAddrV4>,
+
+    /// The command that is sent to the control port before the tests, if any.
+    pub control_start: Option<Vec<u8>>,
+
+    /// The command that is sent to the control port after the tests, if any.
+    pub control_stop: Option<Vec<u8>>,
+
+    /// The fraction of the tests that flip a bit of the serial frame, if any.
+    pub corrupt_frame: Option<f64>,
+
+    /// The number of data bits of each serial character.
+    pub data_bits: CharSize,
+
+    /// The path of the Unix socket to serve test requests on, if any.
+    pub daemon: Option<String>,
+
+    /// The host IP address. With a multicast group, this is still the unicast address of the host,
+    /// which picks the interface that joins the group, while the packets are sent to the group.
     pub dest_ip: u32,
 
     /// The host port.
@@ -35,11 +227,202 @@ pub struct Params {
     /// The host MAC address.
     pub dest_mac: u64,
 
+    /// Indicates that a shift of the payload by part of a byte is looked for on failure.
+    pub detect_bitshift: bool,
+
+    /// Indicates that a packet that is the same as the one before it is reported and skipped.
+    pub detect_duplicates: bool,
+
+    /// Indicates that a constant offset of the payload is looked for on failure.
+    pub detect_offset: bool,
+
+    /// The devices to test in parallel.
+    pub devices: Vec<Device>,
+
+    /// Indicates that packets to the wrong destination are looked for after a timeout.
+    pub diagnose_port: bool,
+
+    /// Indicates that a diff should be shown for failed tests.
+    pub diff: bool,
+
+    /// The time to run the tests for, if any.
+    pub duration: Option<Duration>,
+
+    /// Indicates that the expected payload should be printed instead of testing.
+    pub dump_expected: bool,
+
+    /// Indicates that every received packet should be printed.
+    pub echo: bool,
+
+    /// The file to write a line for every test to, if any.
+    pub event_log: Option<String>,
+
+    /// The payloads that a packet may match, or none when there is only the usual payload.
+    pub expect_any: Vec<Generator>,
+
+    /// The environment variables that arguments were taken from.
+    pub from_env: Vec<&'static str>,
+
+    /// The flow control of the serial port.
+    pub flow_control: FlowControl,
+
+    /// The layout of the parameters and summary.
+    pub format: Format,
+
+    /// Indicates that the FPGA sends back a timestamp and its own latency in the payload.
+    pub fpga_timestamp: bool,
+
+    /// The byte order of the fields in the serial frame.
+    pub frame_endian: Endian,
+
+    /// The fields of the serial frame in the order that they are sent.
+    pub frame_layout: Vec<Field>,
+
+    /// Indicates that the test device addresses are random for every test.
+    pub fuzz: bool,
+
+    /// Indicates that some tests send a malformed frame when fuzzing.
+    pub fuzz_malformed: bool,
+
+    /// The data generator to use for every test, or `None` for random generators.
+    pub gen: Option<u16>,
+
+    /// The external command that makes the payload of each test instead of a ramp, if any.
+    pub gen_command: Option<String>,
+
+    /// Indicates that every generator is crossed with every seed of a seed sweep.
+    pub gen_sweep: bool,
+
+    /// Indicates that payload mismatches are grouped by their signature.
+    pub group_failures: bool,
+
+    /// The number of times to send a serial frame again when the FPGA does not echo it, or `None`
+    /// to not wait for the echo.
+    pub handshake_retries: Option<u32>,
+
+    /// Indicates that frames are sent one at a time from commands on standard input.
+    pub interactive: bool,
+
+    /// The name and IPv4 address of the interface to bind the socket to, if any.
+    pub interface: Option<(String, u32)>,
+
+    /// Indicates that the network carries jumbo frames.
+    pub jumbo: bool,
+
+    /// Indicates that the FPGA is emulated instead of using the serial port.
+    pub loopback: bool,
+
+    /// The number of failures that stops the run, or 0 for no limit.
+    pub max_failures: u64,
+
+    /// The file to write Prometheus metrics to at the end of the run, if any.
+    pub metrics: Option<String>,
+
+    /// The minimum percentage of tests that must pass, if any.
+    pub min_pass_rate: Option<f64>,
+
+    /// Indicates that the largest payload that gets through is searched for instead of testing.
+    pub mtu_discover: bool,
+
+    /// The multicast group to join, if any. Only the destination of the packets changes, so the
+    /// source and destination IP addresses keep their unicast meaning.
+    pub multicast: Option<u32>,
+
+    /// The netmask of the subnet that the host and the FPGA are on.
+    pub netmask: u32,
+
+    /// Indicates that the output should not use colors.
+    pub no_color: bool,
+
+    /// Indicates that the DTR line should be cleared when the serial port is opened.
+    pub no_dtr: bool,
+
+    /// Indicates that the RTS line should be cleared when the serial port is opened.
+    pub no_rts: bool,
+
     /// Indicates that no socket should be created.
     pub no_socket: bool,
 
-    /// The number of tests to run.
-    pub reps: usize,
+    /// The shell command to run when a test fails, if any.
+    pub on_failure: Option<String>,
+
+    /// The number of times that the failure command may run.
+    pub on_failure_limit: usize,
+
+    /// The number of times to retry opening the serial port.
+    pub open_retries: u32,
+
+    /// The parity of each serial character.
+    pub parity: Parity,
+
+    /// The bytes that the FPGA repeats to fill the payload instead of generating a ramp, if any.
+    pub pattern: Option<Vec<u8>>,
+
+    /// The file to write the bytes of every received packet to, if any.
+    pub payload_out: Option<String>,
+
+    /// Indicates that each packet in the payload file follows its length.
+    pub payload_out_lengths: bool,
+
+    /// The fixed bytes that the FPGA sends before the generated payload.
+    pub payload_prefix: Vec<u8>,
+
+    /// The fixed bytes that the FPGA sends after the generated payload.
+    pub payload_suffix: Vec<u8>,
+
+    /// Indicates that nothing is printed except a line on failure.
+    pub quiet: bool,
+
+    /// The modulus that the payload wraps at, or `None` for the natural 8-bit wrap.
+    pub ramp_wrap_at: Option<u16>,
+
+    /// The rate to start the tests at in tests per second, if any.
+    pub rate: Option<f64>,
+
+    /// Indicates that whole Ethernet frames are received and compared.
+    pub raw: bool,
+
+    /// The requested size of the socket receive buffer, if any.
+    pub rcvbuf: Option<usize>,
+
+    /// The file to record the test cases to, if any.
+    pub record: Option<String>,
+
+    /// The file to replay the test cases from, if any.
+    pub replay: Option<String>,
+
+    /// The number of tests between summaries of the tests so far, if any.
+    pub report_every: Option<usize>,
+
+    /// The file to write a script that runs the first failed test again to, if any.
+    pub repro_script: Option<String>,
+
+    /// The number of tests to run, or `None` to run until the duration ends.
+    pub reps: Option<usize>,
+
+    /// The number of tests that must receive a packet before the run is stopped, if any.
+    pub require_first: Option<usize>,
+
+    /// The number of extra packets to read when a packet is wrong.
+    pub reread: usize,
+
+    /// Indicates that the tests are sent to each of the destinations in turn.
+    pub round_robin: bool,
+
+    /// The seed of the random number generator of the run.
+    pub run_seed: u64,
+
+    /// The data seed to use for every test, or `None` for random seeds.
+    pub seed: Option<u16>,
+
+    /// Indicates that one test is run for every seed in order.
+    pub seed_sweep: bool,
+
+    /// Indicates that each packet carries a sequence number in its first two bytes.
+    pub seqnum: bool,
+
+    /// The file to log the bytes read from the serial port to, if any.
+    pub serial_log: Option<String>,
 
     /// The serial port to use.
     pub serial_port: String,
@@ -50,7 +433,11 @@ pub struct Params {
     /// Indicates whether all results, not just failures, should be shown.
     pub show_all: bool,
 
-    /// The test device IP address.
+    /// The interface that the source was taken from when it is `auto`, if any.
+    pub src_auto: Option<String>,
+
+    /// The test device IP address. With a multicast group, this is still the unicast address of the
+    /// FPGA, which is the source of the group traffic.
     pub src_ip: u32,
 
     /// The test device port.
@@ -58,6 +445,46 @@ pub struct Params {
 
     /// The test device MAC address.
     pub src_mac: u64,
+
+    /// The number of stop bits of each serial character.
+    pub stop_bits: StopBits,
+
+    /// Indicates that failed payloads should be checked for an endianness bug.
+    pub suggest_endian: bool,
+
+    /// Indicates that failed payloads should be checked for inverted bits.
+    pub suggest_inverted: bool,
+
+    /// The file to write the summary of the run to as JSON, if any.
+    pub summary_json: Option<String>,
+
+    /// Indicates that the throughput should be shown.
+    pub throughput: bool,
+
+    /// The time to wait for each packet.
+    pub timeout: Duration,
+
+    /// What to do when no packet arrives before the timeout.
+    pub timeout_policy: TimeoutPolicy,
+
+    /// The number of times to send the serial frame again after a timeout.
+    pub timeout_retries: usize,
+
+    /// The largest difference between an expected and actual byte that still counts as a match.
+    pub tolerance: u8,
+
+    /// The verbosity of the log.
+    pub verbosity: usize,
+
+    /// The offset and width of a length field in the payload to check, if any.
+    pub verify_length_field: Option<(usize, usize)>,
+
+    /// The time without a finished test after which to warn that the run is stuck, if any.
+    pub watchdog: Option<Duration>,
+
+    /// Indicates whether the payload is generated as 16-bit words with a 16-bit seed and
+    /// generator.
+    pub word_mode: bool
 }
 
 impl Params {
@@ -68,25 +495,259 @@ impl Params {
     /// The program parameters or an error message.
     pub fn get() -> Result<Params, String> {
         let yml = load_yaml!("app.yml");
-        let matches = App::from_yaml(yml).get_matches();
+        let args: Vec<String> = env::args().collect();
+        // The arguments that are given after those of the profile override them
+        let matches = match profile::expand(&args)? {
+            Some(expanded) => App::from_yaml(yml)
+                .setting(AppSettings::AllArgsOverrideSelf)
+                .get_matches_from(expanded),
+            None => App::from_yaml(yml).get_matches()
+        };
+        Params::from_matches(&matches)
+    }
+
+    /// Get the parameters from a list of arguments, such as a line of a batch, without exiting
+    /// on a bad argument. An argument that is given more than once takes its last value, so a
+    /// line can override the arguments from the command line.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments, where the first is the name of the program.
+    ///
+    /// # Returns
+    ///
+    /// The program parameters or an error message.
+    pub fn from_args(args: &[String]) -> Result<Params, String> {
+        let yml = load_yaml!("app.yml");
+        let args = profile::expand(args)?.unwrap_or_else(|| args.to_vec());
+        let matches = App::from_yaml(yml)
+            .setting(AppSettings::AllArgsOverrideSelf)
+            .get_matches_from_safe(args)
+            .map_err(|err| err.message.lines().next().unwrap_or_default()
+                .trim_start_matches("error: ").to_string())?;
+        Params::from_matches(&matches)
+    }
+
+    /// Get the parameters from parsed arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `matches` - The matches from the arguments.
+    ///
+    /// # Returns
+    ///
+    /// The program parameters or an error message.
+    fn from_matches(matches: &ArgMatches) -> Result<Params, String> {
         // Get the parameters
-        let (dest_ip, dest_port, dest_mac) = parse_ip_port_mac(&matches, "dest".to_string())?;
-        let (src_ip, src_port, src_mac) = parse_ip_port_mac(&matches, "src".to_string())?;
-        let (serial_port, serial_baud) = parse_serial_port_baud(&matches)?;
-        return Ok(Params {
-            bytes: parse_bytes(&matches)?,
-            dest_ip: dest_ip,
-            dest_port: dest_port,
-            dest_mac: dest_mac,
-            no_socket: parse_no_socket(&matches)?,
-            reps: parse_reps(&matches)?,
-            serial_port: serial_port,
-            serial_baud: serial_baud,
-            show_all: parse_show_all(&matches)?,
-            src_ip: src_ip,
-            src_port: src_port,
-            src_mac: src_mac
-        })
+        let devices = parse_devices(matches)?;
+        // The first device is tested when there is only one
+        let Device { dest_ip, dest_port, dest_mac, ref serial_port, serial_baud } = devices[0];
+        let serial_port = serial_port.clone();
+        // The source is only missing when the expected payload is dumped without testing
+        let (src_auto, (src_ip, src_port, src_mac)) = match values_of(matches, "src") {
+            Some(ref vs) if vs[0] == "auto" => {
+                let (name, src) = parse_auto_src(matches, dest_ip, dest_port)?;
+                (Some(name), src)
+            },
+            Some(vs) => (None, parse_ip_port_mac(vs[0])?),
+            None => (None, (0, 0, 0))
+        };
+        let params = Params {
+            align_check: parse_align_check(matches)?,
+            allow_group_mac: parse_allow_group_mac(matches)?,
+            arp_warmup: parse_arp_warmup(matches)?,
+            batch: parse_batch(matches)?,
+            baud_sweep: parse_baud_sweep(matches)?,
+            ber: parse_ber(matches)?,
+            bind: parse_bind(matches)?,
+            bind_retries: parse_bind_retries(matches)?,
+            burst: parse_burst(matches)?,
+            bytes: parse_bytes(matches)?,
+            check_checksums: parse_check_checksums(matches)?,
+            check_dest: parse_check_dest(matches)?,
+            check_endian: parse_check_endian(matches)?,
+            check_source: parse_check_source(matches)?,
+            compare_bytes: parse_compare_bytes(matches)?,
+            compare_endian_auto: parse_compare_endian_auto(matches)?,
+            compare_hash: parse_compare_hash(matches)?,
+            compare_mode: parse_compare_mode(matches)?,
+            compare_pcap: parse_compare_pcap(matches)?,
+            continuous: parse_continuous(matches)?,
+            control: parse_control(matches)?,
+            control_start: parse_control_command(matches, "control-start")?,
+            control_stop: parse_control_command(matches, "control-stop")?,
+            corrupt_frame: parse_corrupt_frame(matches)?,
+            daemon: parse_daemon(matches)?,
+            data_bits: parse_data_bits(matches)?,
+            dest_ip,
+            dest_port,
+            dest_mac,
+            detect_bitshift: parse_detect_bitshift(matches)?,
+            detect_duplicates: parse_detect_duplicates(matches)?,
+            detect_offset: parse_detect_offset(matches)?,
+            devices,
+            diagnose_port: parse_diagnose_port(matches)?,
+            diff: parse_diff(matches)?,
+            dump_expected: parse_dump_expected(matches)?,
+            duration: parse_duration(matches)?,
+            echo: parse_echo(matches)?,
+            event_log: parse_event_log(matches)?,
+            expect_any: parse_expect_any(matches)?,
+            from_env: parse_from_env(matches)?,
+            flow_control: parse_flow_control(matches)?,
+            format: parse_format(matches)?,
+            fpga_timestamp: parse_fpga_timestamp(matches)?,
+            frame_endian: parse_frame_endian(matches)?,
+            frame_layout: parse_frame_layout(matches)?,
+            fuzz: parse_fuzz(matches)?,
+            fuzz_malformed: parse_fuzz_malformed(matches)?,
+            gen: parse_gen(matches)?,
+            gen_command: parse_gen_command(matches)?,
+            gen_sweep: parse_gen_sweep(matches)?,
+            group_failures: parse_group_failures(matches)?,
+            handshake_retries: parse_handshake_retries(matches)?,
+            interactive: parse_interactive(matches)?,
+            interface: parse_interface(matches, src_ip)?,
+            jumbo: parse_jumbo(matches)?,
+            loopback: parse_loopback(matches)?,
+            max_failures: parse_max_failures(matches)?,
+            metrics: parse_metrics(matches)?,
+            min_pass_rate: parse_min_pass_rate(matches)?,
+            mtu_discover: parse_mtu_discover(matches)?,
+            multicast: parse_multicast(matches)?,
+            netmask: parse_netmask(matches)?,
+            no_color: parse_no_color(matches)?,
+            no_dtr: parse_no_dtr(matches)?,
+            no_rts: parse_no_rts(matches)?,
+            no_socket: parse_no_socket(matches)?,
+            on_failure: parse_on_failure(matches)?,
+            on_failure_limit: parse_on_failure_limit(matches)?,
+            open_retries: parse_open_retries(matches)?,
+            parity: parse_parity(matches)?,
+            pattern: parse_pattern(matches)?,
+            payload_out: parse_payload_out(matches)?,
+            payload_out_lengths: parse_payload_out_lengths(matches)?,
+            payload_prefix: parse_payload_bytes(matches, "payload-prefix")?,
+            payload_suffix: parse_payload_bytes(matches, "payload-suffix")?,
+            quiet: parse_quiet(matches)?,
+            ramp_wrap_at: parse_ramp_wrap_at(matches)?,
+            rate: parse_rate(matches)?,
+            raw: parse_raw(matches)?,
+            rcvbuf: parse_rcvbuf(matches, parse_jumbo(matches)?)?,
+            record: parse_record(matches)?,
+            replay: parse_replay(matches)?,
+            report_every: parse_report_every(matches)?,
+            repro_script: parse_repro_script(matches)?,
+            reps: parse_reps(matches)?,
+            require_first: parse_require_first(matches)?,
+            reread: parse_reread(matches)?,
+            round_robin: parse_round_robin(matches)?,
+            run_seed: parse_run_seed(matches)?,
+            seed: parse_seed(matches)?,
+            seed_sweep: parse_seed_sweep(matches)?,
+            seqnum: parse_seqnum(matches)?,
+            serial_log: parse_serial_log(matches)?,
+            serial_port,
+            serial_baud,
+            show_all: parse_show_all(matches)?,
+            src_auto,
+            src_ip,
+            src_port,
+            src_mac,
+            stop_bits: parse_stop_bits(matches)?,
+            suggest_endian: parse_suggest_endian(matches)?,
+            suggest_inverted: parse_suggest_inverted(matches)?,
+            summary_json: parse_summary_json(matches)?,
+            throughput: parse_throughput(matches)?,
+            timeout: parse_timeout(matches)?,
+            timeout_policy: parse_timeout_policy(matches)?,
+            timeout_retries: parse_timeout_retries(matches)?,
+            tolerance: parse_tolerance(matches)?,
+            verbosity: parse_verbosity(matches)?,
+            verify_length_field: parse_verify_length_field(matches)?,
+            watchdog: parse_watchdog(matches)?,
+            word_mode: parse_word_mode(matches)?
+        };
+        // A normal run would never end without a number of tests or a time limit
+        let chosen = params.batch || params.baud_sweep || params.burst.is_some()
+            || params.compare_pcap.is_some() || params.daemon.is_some() || params.dump_expected
+            || params.interactive || params.mtu_discover || params.replay.is_some()
+            || params.seed_sweep;
+        if params.reps.is_none() && params.duration.is_none() && !chosen {
+            return Err("One of --reps or --duration is required".to_string());
+        }
+        // A packet that is too big for a jumbo frame is never received whole
+        if params.jumbo && params.packet_len() > JUMBO_PAYLOAD {
+            return Err(format!("{} bytes do not fit in a jumbo frame, which holds at most {} bytes",
+                params.packet_len(), JUMBO_PAYLOAD));
+        }
+        // Every line of a TAP stream must come from one run of numbered tests
+        let other_mode = params.batch || params.baud_sweep || params.burst.is_some()
+            || params.compare_pcap.is_some() || params.daemon.is_some() || params.interactive
+            || params.mtu_discover;
+        if params.format == Format::Tap && (other_mode || params.devices.len() > 1) {
+            return Err("The tap format only works for a normal run of one device".to_string());
+        }
+        if params.no_rts && params.flow_control == FlowHardware {
+            return Err("RTS cannot be cleared with hardware flow control, which drives it"
+                .to_string());
+        }
+        if params.handshake_retries.is_some() && !cfg!(unix) {
+            return Err("The serial handshake polls the port, which needs Unix".to_string());
+        }
+        if params.serial_log.is_some() && !cfg!(unix) {
+            return Err("The serial log polls the port, which needs Unix".to_string());
+        }
+        if params.raw && !cfg!(target_os = "linux") {
+            return Err("Raw frames need a packet socket, which only Linux has".to_string());
+        }
+        // The loopback sends through the operating system, which builds headers of its own
+        if params.raw && params.loopback {
+            return Err("Raw frames cannot be checked against the loopback, which does not build \
+                the headers of the FPGA".to_string());
+        }
+        // The timestamps must not overlap the sequence number
+        let stamped_bytes = TIMESTAMP_LEN + if params.seqnum { 2 } else { 0 };
+        if params.fpga_timestamp && params.bytes < stamped_bytes {
+            return Err(format!("FPGA timestamps need at least {} bytes, but got {}", stamped_bytes,
+                params.bytes));
+        }
+        Ok(params)
+    }
+
+    /// Get the parameters for each device on its own.
+    ///
+    /// # Returns
+    ///
+    /// A copy of the parameters for each device, where the destination and serial port are those
+    /// of the device. Each device gets a run seed of its own, so the devices do not all send the
+    /// same test cases, and the first device keeps the run seed as it is.
+    pub fn device_params(&self) -> Vec<Params> {
+        self.devices.iter().enumerate().map(|(i, device)| {
+            let mut params = self.clone();
+            params.run_seed = self.run_seed ^ (i as u64).wrapping_mul(SEED_SPREAD);
+            params.dest_ip = device.dest_ip;
+            params.dest_port = device.dest_port;
+            params.dest_mac = device.dest_mac;
+            params.serial_port = device.serial_port.clone();
+            params.serial_baud = device.serial_baud;
+            params.devices = vec![device.clone()];
+            params
+        }).collect()
+    }
+
+    /// Get a short name for the device, which is the serial port, or the destination port when
+    /// there is no serial port.
+    ///
+    /// # Returns
+    ///
+    /// The name of the device.
+    pub fn label(&self) -> String {
+        if self.serial_port.is_empty() {
+            format!("Port {}", self.dest_port)
+        } else {
+            self.serial_port.clone()
+        }
     }
 
     /// Get the destination IP address as a string.
@@ -107,6 +768,108 @@ impl Params {
         format_mac(&self.dest_mac)
     }
 
+    /// Get the multicast group as a string, with the interface that it is joined on when that is
+    /// given apart.
+    ///
+    /// # Returns
+    ///
+    /// A string with the multicast group, or `None` if multicast is not used.
+    pub fn multicast_string(&self) -> Option<String> {
+        self.multicast.as_ref().map(|group| match self.bind {
+            Some(ref ip) => format!("{} on {}", format_ip(group), format_ip(ip)),
+            None => format_ip(group)
+        })
+    }
+
+    /// Get a note for the parameters that shows that an argument was taken from an environment
+    /// variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `var` - The name of the environment variable.
+    ///
+    /// # Returns
+    ///
+    /// The note, or an empty string if the argument was given on the command line.
+    pub fn env_note(&self, var: &str) -> String {
+        if self.from_env.contains(&var) {
+            format!(" (From {})", var)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Get the IP address of the host to bind the socket to, which is the address of the interface
+    /// when one is given and the destination IP address otherwise.
+    ///
+    /// # Returns
+    ///
+    /// The IP address.
+    pub fn bind_ip(&self) -> u32 {
+        self.interface.as_ref().map_or(self.dest_ip, |&(_, ip)| ip)
+    }
+
+    /// Get the IP address of the host interface to join the multicast group on, which is the
+    /// address from `--bind` when one is given and the bind IP address otherwise.
+    ///
+    /// # Returns
+    ///
+    /// The IP address.
+    pub fn membership_ip(&self) -> u32 {
+        self.bind.unwrap_or_else(|| self.bind_ip())
+    }
+
+    /// Check whether the source and the host are on the same subnet.
+    ///
+    /// # Returns
+    ///
+    /// Whether the addresses are on the same subnet.
+    pub fn has_same_subnet(&self) -> bool {
+        (self.src_ip ^ self.bind_ip()) & self.netmask == 0
+    }
+
+    /// Check whether the baudrate is one of the standard rates. Other rates may still work, but
+    /// not every serial adapter supports them.
+    ///
+    /// # Returns
+    ///
+    /// Whether the baudrate is standard.
+    pub fn has_standard_baud(&self) -> bool {
+        STANDARD_BAUDS.contains(&self.serial_baud.speed())
+    }
+
+    /// Check whether the payload fits in a standard Ethernet frame. Larger payloads must be
+    /// fragmented, which the FPGA may not support.
+    ///
+    /// # Returns
+    ///
+    /// Whether the payload fits in a frame.
+    pub fn fits_in_frame(&self) -> bool {
+        self.packet_len() <= if self.jumbo { JUMBO_PAYLOAD } else { MTU_PAYLOAD }
+    }
+
+    /// Get the number of bytes in each packet, which is the generated payload with its prefix and
+    /// suffix.
+    ///
+    /// # Returns
+    ///
+    /// The size of a packet.
+    pub fn packet_len(&self) -> usize {
+        self.payload_prefix.len() + self.bytes + self.payload_suffix.len()
+    }
+
+    /// Create the random number generator of a run, which gives the same sequence of random
+    /// values for every run with the same run seed.
+    ///
+    /// # Returns
+    ///
+    /// The random number generator.
+    pub fn rng(&self) -> StdRng {
+        let mut seed = [0; 32];
+        seed[..8].copy_from_slice(&self.run_seed.to_le_bytes());
+        StdRng::from_seed(seed)
+    }
+
     /// Get the source IP address as a string.
     ///
     /// # Returns
@@ -116,6 +879,43 @@ impl Params {
         format_ip(&self.src_ip)
     }
 
+    /// Get the serial character settings as a string, such as `8N1, no flow control`.
+    ///
+    /// # Returns
+    ///
+    /// A string with the data bits, parity, stop bits, and flow control, and the control lines
+    /// that are cleared.
+    pub fn serial_settings_string(&self) -> String {
+        let data_bits = match self.data_bits {
+            Bits5 => 5,
+            Bits6 => 6,
+            Bits7 => 7,
+            Bits8 => 8
+        };
+        let parity = match self.parity {
+            ParityNone => 'N',
+            ParityOdd => 'O',
+            ParityEven => 'E'
+        };
+        let stop_bits = match self.stop_bits {
+            Stop1 => 1,
+            Stop2 => 2
+        };
+        let flow_control = match self.flow_control {
+            FlowNone => "no",
+            FlowSoftware => "software",
+            FlowHardware => "hardware"
+        };
+        let mut s = format!("{}{}{}, {} flow control", data_bits, parity, stop_bits, flow_control);
+        match (self.no_dtr, self.no_rts) {
+            (true, true) => s.push_str(", DTR and RTS cleared"),
+            (true, false) => s.push_str(", DTR cleared"),
+            (false, true) => s.push_str(", RTS cleared"),
+            (false, false) => {}
+        }
+        s
+    }
+
     /// Get the source MAC address as a string.
     ///
     /// # Returns
@@ -126,7 +926,7 @@ impl Params {
     }
 }
 
-/// Parse the bytes parameter.
+/// Parse the alignment check parameter.
 ///
 /// # Arguments
 ///
@@ -134,72 +934,1500 @@ impl Params {
 ///
 /// # Returns
 ///
-/// The number of bytes or an error message.
-fn parse_bytes(matches: &ArgMatches) -> Result<usize, String> {
-    let v = matches.value_of("bytes").unwrap();
-    match v.parse::<usize>() {
-        Ok(b) => Ok(b),
-        _ => Err(format!("Bad bytes value: {}", v))
-    }
+/// An indicator of whether failed payloads should be checked for a rotation.
+fn parse_align_check(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("align-check"))
 }
 
-/// Parse an IP address, port, and MAC address in the parameter with the given name.
+/// Parse the allow group MAC parameter.
 ///
 /// # Arguments
 ///
 /// * `matches` - The matches from the command line arguments.
-/// * `name` - The name of the match to parse.
 ///
 /// # Returns
 ///
-/// The IP address, port, and MAC address, or an error message.
-fn parse_ip_port_mac(matches: &ArgMatches, name: String) -> Result<(u32, u16, u64), String> {
-    // Get the raw argument string
-    let v = matches.value_of(name).unwrap();
-    let raw = match v.parse::<String>() {
-        Ok(r) => r,
-        _ => return Err(format!("Bad IP, port, and MAC value. {}", v))
-    };
-    // Parse out the IP, port, and MAC address
-    let re = Regex::new(IP_PORT_MAC_REGEX).unwrap();
-    let captures = match re.captures(&raw) {
-        Some(c) => c,
-        None => return Err(format!("Bad IP, port, and MAC specification: {}", raw))
-    };
-    // Build up the IP, port, and MAC
-    let ip = {
-        let mut temp_ip: u32 = 0;
-        for i in 0..4 {
-            match captures.get(1 + i).unwrap().as_str().parse::<u32>() {
-                Ok(n) => {
-                    if n > 255 {
-                        return Err(format!("Invalid IP address: {}", raw))
-                    }
-                    temp_ip |= n << ((3 - i) * 8);
-                },
-                _ => return Err(format!("Invalid IP address: {}", raw))
-            };
+/// An indicator of whether multicast and broadcast MAC addresses are not warned about.
+fn parse_allow_group_mac(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("allow-group-mac"))
+}
+
+/// Parse the ARP warmup parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether a datagram is sent to the source before testing.
+fn parse_arp_warmup(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("arp-warmup"))
+}
+
+/// Find the source address of the host for a source of `auto`, which is the address of the
+/// interface from `--interface`, or of the interface that has the destination IP address.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+/// * `dest_ip` - The host IP address.
+/// * `dest_port` - The host port, which is also used as the source port.
+///
+/// # Returns
+///
+/// The name of the interface and the IP address, port, and MAC address, or an error message.
+fn parse_auto_src(matches: &ArgMatches, dest_ip: u32, dest_port: u16)
+    -> Result<(String, (u32, u16, u64)), String> {
+    let name = match matches.value_of("interface") {
+        Some(name) => name.to_string(),
+        None => match socket::interface_with_addr(Ipv4Addr::from(dest_ip)) {
+            Ok(Some(name)) => name,
+            Ok(None) => return Err(format!("No interface has the destination IP address {} for \
+                an auto source", Ipv4Addr::from(dest_ip))),
+            Err(err) => return Err(format!("Could not look up interfaces: {}", err))
         }
-        temp_ip
     };
-    let port = match captures.get(5).unwrap().as_str().parse::<u16>() {
-        Ok(p) => p,
-        _ => return Err("Bad port number".to_string())
+    let addrs = match socket::interface_addrs(&name) {
+        Ok(Some(addrs)) => addrs,
+        Ok(None) => return Err(format!("Unknown interface: {}", name)),
+        Err(err) => return Err(format!("Could not look up interface {}: {}", name, err))
     };
-    let mac = {
-        let mut temp_mac: u64 = 0;
-        for i in 0..6 {
-            match u64::from_str_radix(captures.get(6 + i).unwrap().as_str(), 16) {
-                Ok(n) => temp_mac |= n << ((5 - i) * 8),
-                _ => return Err(format!("Invalid MAC address: {}", raw))
-            };
-        }
-        temp_mac
+    // The destination IP address is preferred, since it is the address that the packets reach
+    let host = addrs.iter().find(|&&(ip, _)| u32::from(ip) == dest_ip);
+    let ip = match host.or_else(|| addrs.first()) {
+        Some(&(ip, _)) => u32::from(ip),
+        None => return Err(format!("Interface {} has no IPv4 address", name))
+    };
+    let mac = match socket::interface_mac(&name) {
+        Ok(mac) => mac.unwrap_or(0),
+        Err(err) => return Err(format!("Could not look up the MAC address of {}: {}", name, err))
+    };
+    Ok((name, (ip, dest_port, mac)))
+}
+
+/// Parse the batch parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether parameter sets are read from standard input.
+fn parse_batch(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("batch"))
+}
+
+/// Parse the baudrate sweep parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the standard baudrates are tried.
+fn parse_baud_sweep(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("baud-sweep"))
+}
+
+/// Parse the bit error rate parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the bit error rate is shown.
+fn parse_ber(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("ber"))
+}
+
+/// Parse the IP address of the host interface to join the multicast group on.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The IP address if one was given, or an error message.
+fn parse_bind(matches: &ArgMatches) -> Result<Option<u32>, String> {
+    let v = match matches.value_of("bind") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match v.parse::<Ipv4Addr>() {
+        Ok(ip) => Ok(Some(ip.into())),
+        _ => Err(format!("Bad bind IP address: {}", v))
+    }
+}
+
+/// Parse the number of socket bind retries.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of retries or an error message.
+fn parse_bind_retries(matches: &ArgMatches) -> Result<u32, String> {
+    let v = matches.value_of("bind-retries").unwrap();
+    match v.parse::<u32>() {
+        Ok(n) => Ok(n),
+        _ => Err(format!("Bad bind retries value: {}", v))
+    }
+}
+
+/// Parse the burst parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of frames in the burst if one was given, or an error message.
+fn parse_burst(matches: &ArgMatches) -> Result<Option<usize>, String> {
+    let v = match matches.value_of("burst") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    // The packets of one burst are matched with the frames of the same device
+    if values_of(matches, "dest").is_some_and(|vs| vs.len() > 1) {
+        return Err("A burst tests only one device".to_string());
+    }
+    match v.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(Some(n)),
+        _ => Err(format!("Bad burst value: {}", v))
+    }
+}
+
+/// Parse the bytes parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of bytes or an error message.
+fn parse_bytes(matches: &ArgMatches) -> Result<usize, String> {
+    // The number of bytes is only missing in batch mode, where each line gives it
+    let v = match matches.value_of("bytes") {
+        Some(v) => v,
+        None => return Ok(0)
+    };
+    match v.parse::<usize>() {
+        Ok(b) if b > MAX_UDP_PAYLOAD => Err(format!(
+            "{} bytes do not fit in a UDP datagram, which holds at most {} bytes", b,
+            MAX_UDP_PAYLOAD)),
+        Ok(b) => Ok(b),
+        _ => Err(format!("Bad bytes value: {}", v))
+    }
+}
+
+/// Parse the check checksums parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the checksums of the packet headers are checked.
+fn parse_check_checksums(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("check-checksums"))
+}
+
+/// Parse the check destination parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the destination in the headers of each packet is checked.
+fn parse_check_dest(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("check-dest"))
+}
+
+/// Parse the check endian parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the byte order of the host and the encoded source IP address are
+/// printed.
+fn parse_check_endian(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("check-endian"))
+}
+
+/// Parse the check source parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether packets from other addresses than the source are rejected.
+fn parse_check_source(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("check-source"))
+}
+
+/// Parse the number of bytes to compare.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of bytes at the start of each packet to compare if it was given, or an error
+/// message.
+fn parse_compare_bytes(matches: &ArgMatches) -> Result<Option<usize>, String> {
+    match matches.value_of("compare-bytes") {
+        Some(v) => match v.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Some(n)),
+            _ => Err(format!("Bad compare bytes value: {}", v))
+        },
+        None => Ok(None)
+    }
+}
+
+/// Parse the compare endian auto parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the byte order of the payload words is found from the packets.
+fn parse_compare_endian_auto(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("compare-endian-auto"))
+}
+
+/// Parse the compare hash parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether a CRC of the payloads is compared before their bytes.
+fn parse_compare_hash(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("compare-hash"))
+}
+
+/// Parse the capture file to compare.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The path of the capture file if one was given, or an error message.
+fn parse_compare_pcap(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("compare-pcap").map(|v| v.to_string()))
+}
+
+/// Parse the continuous parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the payload sequence continues across repetitions.
+fn parse_continuous(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("continuous"))
+}
+
+/// Parse the control port.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The IP address and port of the control port if there is one, or an error message.
+fn parse_control(matches: &ArgMatches) -> Result<Option<SocketAddrV4>, String> {
+    let v = match matches.value_of("control") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match v.parse::<SocketAddrV4>() {
+        Ok(addr) => Ok(Some(addr)),
+        _ => Err(format!("Bad control value: {}", v))
+    }
+}
+
+/// Parse a command for the control port.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+/// * `arg` - The name of the argument.
+///
+/// # Returns
+///
+/// The bytes of the command if it was given, or an error message.
+fn parse_control_command(matches: &ArgMatches, arg: &str) -> Result<Option<Vec<u8>>, String> {
+    let v = match matches.value_of(arg) {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match hex::parse(v) {
+        Some(ref bytes) if !bytes.is_empty() => Ok(Some(bytes.clone())),
+        _ => Err(format!("Bad {} value: {}", arg.replace('-', " "), v))
+    }
+}
+
+/// Parse the fraction of the tests that corrupt the serial frame.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The fraction from 0 to 1 if one was given, or an error message.
+fn parse_corrupt_frame(matches: &ArgMatches) -> Result<Option<f64>, String> {
+    let v = match matches.value_of("corrupt-frame") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match v.parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(Some(rate)),
+        _ => Err(format!("Bad corrupt frame rate: {}", v))
+    }
+}
+
+/// Parse the path of the Unix socket to serve test requests on.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The path of the socket if one was given, or an error message.
+fn parse_daemon(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("daemon").map(|v| v.to_string()))
+}
+
+/// Parse the number of data bits of each serial character.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The character size or an error message.
+fn parse_data_bits(matches: &ArgMatches) -> Result<CharSize, String> {
+    match matches.value_of("data-bits").unwrap() {
+        "5" => Ok(Bits5),
+        "6" => Ok(Bits6),
+        "7" => Ok(Bits7),
+        "8" => Ok(Bits8),
+        v => Err(format!("Bad data bits value: {}", v))
+    }
+}
+
+/// Parse the devices, which pair up the destinations with the serial ports in order.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The devices or an error message. The port names are empty when the loopback is used without
+/// serial ports.
+fn parse_devices(matches: &ArgMatches) -> Result<Vec<Device>, String> {
+    // The destination is only missing when the expected payload is dumped without testing
+    let dests = match values_of(matches, "dest") {
+        Some(vs) => vs.into_iter().map(parse_ip_port_mac).collect::<Result<Vec<_>, _>>()?,
+        None => vec![(0, 0, 0)]
+    };
+    let mut serial_ports = match values_of(matches, "serial-port") {
+        Some(vs) => vs.into_iter().map(parse_serial_port_baud).collect::<Result<Vec<_>, _>>()?,
+        None => vec![(String::new(), Baud115200); dests.len()]
+    };
+    // The destinations of a round robin share one serial port
+    let round_robin = matches.is_present("round-robin");
+    if round_robin && serial_ports.len() == 1 {
+        serial_ports = vec![serial_ports[0].clone(); dests.len()];
+    } else if round_robin && serial_ports.len() > 1 && matches.is_present("serial-port") {
+        return Err("Round robin sends every test through one serial port".to_string());
+    }
+    if serial_ports.len() != dests.len() {
+        return Err(format!("Got {} serial ports but {} destinations",
+            serial_ports.len(), dests.len()));
+    }
+    // Each device needs its own socket, so the destinations cannot be shared
+    for (i, a) in dests.iter().enumerate() {
+        if dests[..i].iter().any(|b| a.0 == b.0 && a.1 == b.1) {
+            return Err(format!("Destination {}:{} is used more than once",
+                format_ip(&a.0), a.1));
+        }
+    }
+    if dests.len() > 1 && !round_robin && matches.is_present("record") {
+        return Err("Cannot record test cases from more than one device".to_string());
+    }
+    Ok(dests.into_iter().zip(serial_ports).map(|(dest, serial)| Device {
+        dest_ip: dest.0,
+        dest_port: dest.1,
+        dest_mac: dest.2,
+        serial_port: serial.0,
+        serial_baud: serial.1
+    }).collect())
+}
+
+/// Parse the detect bit shift parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether a shift of the payload by part of a byte is looked for on failure.
+fn parse_detect_bitshift(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("detect-bitshift"))
+}
+
+/// Parse the detect duplicates parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether duplicate packets are reported and skipped.
+fn parse_detect_duplicates(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("detect-duplicates"))
+}
+
+/// Parse the detect offset parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether a constant offset of the payload is looked for on failure.
+fn parse_detect_offset(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("detect-offset"))
+}
+
+/// Parse the diagnose port parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether packets to the wrong destination are looked for after a timeout.
+fn parse_diagnose_port(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("diagnose-port"))
+}
+
+/// Parse the diff parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether a diff should be shown for failed tests.
+fn parse_diff(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("diff"))
+}
+
+/// Parse the dump expected parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the expected payload should be printed instead of testing.
+fn parse_dump_expected(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("dump-expected"))
+}
+
+/// Parse the duration.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The duration if one was given, or an error message.
+fn parse_duration(matches: &ArgMatches) -> Result<Option<Duration>, String> {
+    let v = match matches.value_of("duration") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match v.parse::<f64>() {
+        Ok(secs) if secs > 0.0 && secs.is_finite() => Ok(Some(Duration::from_secs_f64(secs))),
+        _ => Err(format!("Bad duration value: {}", v))
+    }
+}
+
+/// Parse the echo parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether every received packet should be printed.
+fn parse_echo(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("echo"))
+}
+
+/// Parse the event file.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The path to write a line for every test to, if any.
+fn parse_event_log(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("eventlog").map(|v| v.to_string()))
+}
+
+/// Parse the payloads that a packet may match.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The generator of each payload, which is empty when none are given, or an error message.
+fn parse_expect_any(matches: &ArgMatches) -> Result<Vec<Generator>, String> {
+    let v = match matches.value_of("expect-any") {
+        Some(v) => v,
+        None => return Ok(vec![])
+    };
+    let wrap_at = parse_ramp_wrap_at(matches)?;
+    v.split(',').map(|name| match name.trim() {
+        "ramp" => Ok(Generator::Ramp(wrap_at)),
+        "big-words" => Ok(Generator::BigWords),
+        "little-words" => Ok(Generator::LittleWords),
+        _ => Err(format!("Bad expected payload value: {}", name))
+    }).collect()
+}
+
+/// Get the name of a payload that a packet may match.
+///
+/// # Arguments
+///
+/// * `generator` - The generator of the payload.
+///
+/// # Returns
+///
+/// The name, as it is given to `--expect-any`.
+pub fn candidate_name(generator: Generator) -> &'static str {
+    match generator {
+        Generator::Ramp(_) => "ramp",
+        Generator::BigWords => "big-words",
+        Generator::LittleWords => "little-words"
+    }
+}
+
+/// Get the values of an argument that may fall back to an environment variable. The value of the
+/// variable is added to the values from the command line even when the argument is given, so it
+/// is dropped in that case to let the command line take precedence.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+/// * `arg` - The name of the argument, which must take one value per occurrence.
+///
+/// # Returns
+///
+/// The values of the argument, or `None` if it has no values.
+fn values_of<'a>(matches: &'a ArgMatches, arg: &str) -> Option<Vec<&'a str>> {
+    let values = matches.values_of(arg)?;
+    Some(match matches.occurrences_of(arg) {
+        0 => values.collect(),
+        n => values.take(n as usize).collect()
+    })
+}
+
+/// Find the arguments that were taken from environment variables.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The names of the environment variables that were used.
+fn parse_from_env(matches: &ArgMatches) -> Result<Vec<&'static str>, String> {
+    // An argument from the environment has a value without appearing on the command line
+    Ok(ENV_ARGS.iter()
+        .filter(|&&(arg, _)| matches.value_of(arg).is_some() && matches.occurrences_of(arg) == 0)
+        .map(|&(_, var)| var)
+        .collect())
+}
+
+/// Parse the flow control of the serial port.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The flow control or an error message.
+fn parse_flow_control(matches: &ArgMatches) -> Result<FlowControl, String> {
+    match matches.value_of("flow-control").unwrap() {
+        "none" => Ok(FlowNone),
+        // The XON and XOFF characters are 7-bit ASCII, so smaller characters cannot carry them
+        "software" => match matches.value_of("data-bits").unwrap() {
+            "7" | "8" => Ok(FlowSoftware),
+            v => Err(format!("Software flow control needs 7 or 8 data bits, not {}", v))
+        },
+        "hardware" => Ok(FlowHardware),
+        v => Err(format!("Bad flow control value: {}", v))
+    }
+}
+
+/// Parse the compare mode.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The compare mode or an error message.
+fn parse_compare_mode(matches: &ArgMatches) -> Result<CompareMode, String> {
+    match matches.value_of("compare-mode").unwrap() {
+        "exact" => Ok(CompareMode::Exact),
+        "sorted" => Ok(CompareMode::Sorted),
+        "set" => Ok(CompareMode::Set),
+        v => Err(format!("Bad compare mode value: {}", v))
+    }
+}
+
+/// Parse the output format.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The output format or an error message.
+fn parse_format(matches: &ArgMatches) -> Result<Format, String> {
+    match matches.value_of("format").unwrap() {
+        "human" => Ok(Format::Human),
+        "table" => Ok(Format::Table),
+        "tap" => Ok(Format::Tap),
+        v => Err(format!("Bad format value: {}", v))
+    }
+}
+
+/// Parse the FPGA timestamp parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the FPGA sends back a timestamp and its own latency.
+fn parse_fpga_timestamp(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("fpga-timestamp"))
+}
+
+/// Parse the byte order of the serial frame.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The byte order or an error message.
+fn parse_frame_endian(matches: &ArgMatches) -> Result<Endian, String> {
+    match matches.value_of("frame-endian").unwrap() {
+        "big" => Ok(Endian::Big),
+        "little" => Ok(Endian::Little),
+        v => Err(format!("Bad frame endian value: {}", v))
+    }
+}
+
+/// Parse the layout of the serial frame.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The fields of the frame in order or an error message.
+fn parse_frame_layout(matches: &ArgMatches) -> Result<Vec<Field>, String> {
+    let mut layout = frame::parse_layout(matches.value_of("frame-layout").unwrap())?;
+    // The firmware needs these values to generate the same payload
+    if matches.is_present("ramp-wrap-at") && !layout.contains(&Field::Wrap) {
+        layout.push(Field::Wrap);
+    }
+    if matches.is_present("seqnum") && !layout.contains(&Field::Seq) {
+        layout.push(Field::Seq);
+    }
+    if matches.is_present("payload-prefix") && !layout.contains(&Field::PrefixLen) {
+        layout.push(Field::PrefixLen);
+    }
+    if matches.is_present("payload-suffix") && !layout.contains(&Field::SuffixLen) {
+        layout.push(Field::SuffixLen);
+    }
+    if matches.is_present("mtu-discover") && !layout.contains(&Field::Len) {
+        layout.push(Field::Len);
+    }
+    if matches.is_present("fpga-timestamp") && !layout.contains(&Field::Timestamp) {
+        layout.push(Field::Timestamp);
+    }
+    // The pattern field takes as many bytes as the pattern
+    let pattern_len = parse_pattern(matches)?.map(|pattern| pattern.len());
+    match (layout.iter().position(|&f| f == Field::Pattern(0)), pattern_len) {
+        (Some(i), Some(n)) => layout[i] = Field::Pattern(n),
+        (None, Some(n)) => layout.push(Field::Pattern(n)),
+        (Some(_), None) => return Err("The pattern frame field needs a pattern".to_string()),
+        (None, None) => ()
+    }
+    // The seed and generator take two bytes each in word mode
+    if matches.is_present("word-mode") {
+        for field in &mut layout {
+            *field = match *field {
+                Field::Seed => Field::SeedWord,
+                Field::Gen => Field::GenWord,
+                f => f
+            };
+        }
+    }
+    Ok(layout)
+}
+
+/// Parse the fuzz parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the test device addresses are random for every test.
+fn parse_fuzz(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("fuzz"))
+}
+
+/// Parse the fuzz malformed parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether some tests send a malformed frame.
+fn parse_fuzz_malformed(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("fuzz-malformed"))
+}
+
+/// Parse the data generator.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The data generator if one was given, or an error message.
+fn parse_gen(matches: &ArgMatches) -> Result<Option<u16>, String> {
+    match matches.value_of("gen") {
+        Some(v) => parse_data_value(v, matches.is_present("word-mode"))
+            .map(Some)
+            .ok_or_else(|| format!("Bad gen value: {}", v)),
+        None => Ok(None)
+    }
+}
+
+/// Parse the command that makes the payload of each test.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The command if one was given.
+fn parse_gen_command(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("gen-command").map(|v| v.to_string()))
+}
+
+/// Parse the generator sweep parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether every generator is crossed with every seed.
+fn parse_gen_sweep(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("gen-sweep"))
+}
+
+/// Parse the group failures parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether payload mismatches are grouped by their signature.
+fn parse_group_failures(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("group-failures"))
+}
+
+/// Parse the number of serial handshake retries.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of retries, `None` if there is no handshake, or an error message.
+fn parse_handshake_retries(matches: &ArgMatches) -> Result<Option<u32>, String> {
+    match matches.value_of("handshake-retries") {
+        Some(v) => match v.parse::<u32>() {
+            Ok(n) => Ok(Some(n)),
+            _ => Err(format!("Bad handshake retries value: {}", v))
+        },
+        None => Ok(None)
+    }
+}
+
+/// Parse the interactive parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether frames are sent from commands on standard input.
+fn parse_interactive(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("interactive"))
+}
+
+/// Parse the interface to bind the socket to and look up its IPv4 address. When the interface has
+/// several addresses, the one on the same subnet as the source is used.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+/// * `src_ip` - The test device IP address.
+///
+/// # Returns
+///
+/// The name and IPv4 address of the interface if one was given, or an error message.
+fn parse_interface(matches: &ArgMatches, src_ip: u32) -> Result<Option<(String, u32)>, String> {
+    let name = match matches.value_of("interface") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    let addrs = match socket::interface_addrs(name) {
+        Ok(Some(addrs)) => addrs,
+        Ok(None) => return Err(format!("Unknown interface: {}", name)),
+        Err(err) => return Err(format!("Could not look up interface {}: {}", name, err))
+    };
+    let same_subnet = |&&(ip, mask): &&(Ipv4Addr, Ipv4Addr)| {
+        (u32::from(ip) ^ src_ip) & u32::from(mask) == 0
+    };
+    match addrs.iter().find(same_subnet).or_else(|| addrs.first()) {
+        Some(&(ip, _)) => Ok(Some((name.to_string(), ip.into()))),
+        None => Err(format!("Interface {} has no IPv4 address", name))
+    }
+}
+
+/// Parse an IP address, port, and MAC address.
+///
+/// # Arguments
+///
+/// * `v` - The value of the argument to parse.
+///
+/// # Returns
+///
+/// The IP address, port, and MAC address, or an error message.
+fn parse_ip_port_mac(v: &str) -> Result<(u32, u16, u64), String> {
+    // Get the raw argument string
+    let raw = match v.parse::<String>() {
+        Ok(r) => r,
+        _ => return Err(format!("Bad IP, port, and MAC value. {}", v))
+    };
+    // Parse out the IP, port, and MAC address
+    let re = Regex::new(IP_PORT_MAC_REGEX).unwrap();
+    let captures = match re.captures(&raw) {
+        Some(c) => c,
+        None => return Err(format!("Bad IP, port, and MAC specification: {}", raw))
+    };
+    // Build up the IP, port, and MAC
+    let ip = {
+        let mut temp_ip: u32 = 0;
+        for i in 0..4 {
+            match captures.get(1 + i).unwrap().as_str().parse::<u32>() {
+                Ok(n) => {
+                    if n > 255 {
+                        return Err(format!("Invalid IP address: {}", raw))
+                    }
+                    temp_ip |= n << ((3 - i) * 8);
+                },
+                _ => return Err(format!("Invalid IP address: {}", raw))
+            };
+        }
+        temp_ip
+    };
+    let port = match captures.get(5).unwrap().as_str().parse::<u16>() {
+        Ok(p) => p,
+        _ => return Err("Bad port number".to_string())
+    };
+    let mac = {
+        let mut temp_mac: u64 = 0;
+        for i in 0..6 {
+            match u64::from_str_radix(captures.get(6 + i).unwrap().as_str(), 16) {
+                Ok(n) => temp_mac |= n << ((5 - i) * 8),
+                _ => return Err(format!("Invalid MAC address: {}", raw))
+            };
+        }
+        temp_mac
+    };
+    Ok((ip, port, mac))
+}
+
+/// Parse the jumbo parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the network carries jumbo frames.
+fn parse_jumbo(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("jumbo"))
+}
+
+/// Parse the loopback parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the FPGA should be emulated.
+fn parse_loopback(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("loopback"))
+}
+
+/// Parse the maximum number of failures.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The maximum number of failures or an error message.
+fn parse_max_failures(matches: &ArgMatches) -> Result<u64, String> {
+    let v = matches.value_of("max-failures").unwrap();
+    match v.parse::<u64>() {
+        Ok(n) => Ok(n),
+        _ => Err(format!("Bad max failures value: {}", v))
+    }
+}
+
+/// Parse the metrics file.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The path to write the metrics to, if any.
+fn parse_metrics(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("metrics").map(|v| v.to_string()))
+}
+
+/// Parse the minimum pass rate.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The minimum pass rate as a percentage if one was given, or an error message.
+fn parse_min_pass_rate(matches: &ArgMatches) -> Result<Option<f64>, String> {
+    let v = match matches.value_of("min-pass-rate") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match v.trim_end_matches('%').parse::<f64>() {
+        Ok(rate) if (0.0..=100.0).contains(&rate) => Ok(Some(rate)),
+        _ => Err(format!("Bad minimum pass rate: {}", v))
+    }
+}
+
+/// Parse the MTU discovery parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the largest payload that gets through is searched for.
+fn parse_mtu_discover(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("mtu-discover"))
+}
+
+/// Parse the multicast group.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The multicast group if one was given, or an error message.
+fn parse_multicast(matches: &ArgMatches) -> Result<Option<u32>, String> {
+    let v = match matches.value_of("multicast") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match v.parse::<Ipv4Addr>() {
+        Ok(group) if group.is_multicast() => Ok(Some(group.into())),
+        Ok(_) => Err(format!("Not a multicast group: {}", v)),
+        _ => Err(format!("Bad multicast group: {}", v))
+    }
+}
+
+/// Parse the netmask, which is either an address or a prefix length such as `24`.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The netmask or an error message.
+fn parse_netmask(matches: &ArgMatches) -> Result<u32, String> {
+    let v = matches.value_of("netmask").unwrap();
+    let mask = match (v.parse::<Ipv4Addr>(), v.parse::<u32>()) {
+        (Ok(ip), _) => u32::from(ip),
+        (_, Ok(0)) => 0,
+        (_, Ok(n)) if n <= 32 => !0 << (32 - n),
+        _ => return Err(format!("Bad netmask value: {}", v))
+    };
+    // The ones of a netmask must all come before the zeros
+    if mask.leading_ones() + mask.trailing_zeros() != 32 {
+        return Err(format!("Bad netmask value: {}", v));
+    }
+    Ok(mask)
+}
+
+/// Parse the no color indicator.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// Whether the output should not use colors.
+fn parse_no_color(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("no-color"))
+}
+
+/// Parse the no DTR indicator.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// Whether the DTR line should be cleared when the serial port is opened.
+fn parse_no_dtr(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("no-dtr"))
+}
+
+/// Parse the no RTS indicator.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// Whether the RTS line should be cleared when the serial port is opened.
+fn parse_no_rts(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("no-rts"))
+}
+
+/// Parse the no socket indicator.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// Whether a socket should be created or an error message.
+fn parse_no_socket(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("no-socket"))
+}
+
+/// Parse the failure command.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The shell command to run when a test fails, if any.
+fn parse_on_failure(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("on-failure").map(|v| v.to_string()))
+}
+
+/// Parse the number of times that the failure command may run.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of times or an error message.
+fn parse_on_failure_limit(matches: &ArgMatches) -> Result<usize, String> {
+    let v = matches.value_of("on-failure-limit").unwrap();
+    match v.parse::<usize>() {
+        Ok(n) => Ok(n),
+        _ => Err(format!("Bad failure command limit value: {}", v))
+    }
+}
+
+/// Parse the number of serial port open retries.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of retries or an error message.
+fn parse_open_retries(matches: &ArgMatches) -> Result<u32, String> {
+    let v = matches.value_of("open-retries").unwrap();
+    match v.parse::<u32>() {
+        Ok(n) => Ok(n),
+        _ => Err(format!("Bad open retries value: {}", v))
+    }
+}
+
+/// Parse the parity of each serial character.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The parity or an error message.
+fn parse_parity(matches: &ArgMatches) -> Result<Parity, String> {
+    match matches.value_of("parity").unwrap() {
+        "none" => Ok(ParityNone),
+        "odd" => Ok(ParityOdd),
+        "even" => Ok(ParityEven),
+        v => Err(format!("Bad parity value: {}", v))
+    }
+}
+
+/// Parse the pattern that the FPGA repeats to fill the payload.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The bytes of the pattern if one was given, or an error message.
+fn parse_pattern(matches: &ArgMatches) -> Result<Option<Vec<u8>>, String> {
+    let v = match matches.value_of("pattern") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match hex::parse(v) {
+        Some(ref bytes) if bytes.is_empty() => Err("The pattern is empty".to_string()),
+        // The pattern is sent in every serial frame, so it is kept as short as a prefix
+        Some(ref bytes) if bytes.len() > 255 => Err(format!("Too many pattern bytes: {}",
+            bytes.len())),
+        Some(bytes) => Ok(Some(bytes)),
+        None => Err(format!("Bad pattern value: {}", v))
+    }
+}
+
+/// Parse the payload file.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The path of the file to write the received packets to if one was given.
+fn parse_payload_out(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("payload-out").map(|v| v.to_string()))
+}
+
+/// Parse the payload file lengths parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether each packet in the payload file follows its length.
+fn parse_payload_out_lengths(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("payload-out-lengths"))
+}
+
+/// Parse the fixed bytes of a payload prefix or suffix.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+/// * `arg` - The name of the argument.
+///
+/// # Returns
+///
+/// The bytes, which are empty if the argument was not given, or an error message.
+fn parse_payload_bytes(matches: &ArgMatches, arg: &str) -> Result<Vec<u8>, String> {
+    let v = match matches.value_of(arg) {
+        Some(v) => v,
+        None => return Ok(vec![])
+    };
+    let name = arg.replace('-', " ");
+    match hex::parse(v) {
+        // The length must fit in its one byte frame field
+        Some(ref bytes) if bytes.len() > 255 => Err(format!("Too many {} bytes: {}", name,
+            bytes.len())),
+        Some(bytes) => Ok(bytes),
+        None => Err(format!("Bad {} value: {}", name, v))
+    }
+}
+
+/// Parse the quiet parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether output is left out except for a line on failure.
+fn parse_quiet(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("quiet"))
+}
+
+/// Parse the modulus that the payload wraps at.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The modulus if one was given, or an error message.
+fn parse_ramp_wrap_at(matches: &ArgMatches) -> Result<Option<u16>, String> {
+    match matches.value_of("ramp-wrap-at") {
+        Some(v) => match v.parse::<u16>() {
+            Ok(n) if (1..=256).contains(&n) => Ok(Some(n)),
+            _ => Err(format!("Bad ramp wrap value: {}", v))
+        },
+        None => Ok(None)
+    }
+}
+
+/// Parse the rate to start the tests at.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The rate in tests per second if one was given, or an error message.
+fn parse_rate(matches: &ArgMatches) -> Result<Option<f64>, String> {
+    let v = match matches.value_of("rate") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match v.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(Some(rate)),
+        _ => Err(format!("Bad rate value: {}", v))
+    }
+}
+
+/// Parse the raw parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether whole Ethernet frames are received and compared.
+fn parse_raw(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("raw"))
+}
+
+/// Parse the size of the socket receive buffer.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+/// * `jumbo` - Indicates that the network carries jumbo frames, which need a larger buffer.
+///
+/// # Returns
+///
+/// The size in bytes if one was given or jumbo frames need one, or an error message.
+fn parse_rcvbuf(matches: &ArgMatches, jumbo: bool) -> Result<Option<usize>, String> {
+    match matches.value_of("rcvbuf") {
+        Some(v) => match v.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Some(n)),
+            _ => Err(format!("Bad receive buffer value: {}", v))
+        },
+        None if jumbo => Ok(Some(JUMBO_RCVBUF)),
+        None => Ok(None)
+    }
+}
+
+/// Parse the record file.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The path to record the test cases to, if any.
+fn parse_record(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("record").map(|v| v.to_string()))
+}
+
+/// Parse the replay file.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The path to replay the test cases from, if any.
+fn parse_replay(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("replay").map(|v| v.to_string()))
+}
+
+/// Parse the number of tests between summaries of the tests so far.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of tests if one was given, or an error message.
+fn parse_report_every(matches: &ArgMatches) -> Result<Option<usize>, String> {
+    match matches.value_of("report-every") {
+        Some(v) => match v.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Some(n)),
+            _ => Err(format!("Bad report every value: {}", v))
+        },
+        None => Ok(None)
+    }
+}
+
+/// Parse the file to write the script that runs the first failed test again to.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The path to write the script to, if any.
+fn parse_repro_script(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("repro-script").map(|v| v.to_string()))
+}
+
+/// Parse the number of repetitions.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of repetitions if it was given, or an error message.
+fn parse_reps(matches: &ArgMatches) -> Result<Option<usize>, String> {
+    let v = match matches.value_of("reps") {
+        Some(v) => v,
+        None => return Ok(None)
     };
-    return Ok((ip, port, mac))
+    match v.parse::<usize>() {
+        Ok(r) => Ok(Some(r)),
+        _ => Err(format!("Bad reps value. {}", v))
+    }
 }
 
-/// Parse the no socket indicator.
+/// Parse the number of tests that must receive a packet.
 ///
 /// # Arguments
 ///
@@ -207,11 +2435,18 @@ fn parse_ip_port_mac(matches: &ArgMatches, name: String) -> Result<(u32, u16, u6
 ///
 /// # Returns
 ///
-/// Whether a socket should be created or an error message.
-fn parse_no_socket(matches: &ArgMatches) -> Result<bool, String> {
-    Ok(matches.is_present("no-socket"))
+/// The number of tests if one was given, or an error message.
+fn parse_require_first(matches: &ArgMatches) -> Result<Option<usize>, String> {
+    match matches.value_of("require-first") {
+        Some(v) => match v.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Some(n)),
+            _ => Err(format!("Bad require first value: {}", v))
+        },
+        None => Ok(None)
+    }
 }
-/// Parse the number of repetitions.
+
+/// Parse the number of extra packets to read when a packet is wrong.
 ///
 /// # Arguments
 ///
@@ -219,16 +2454,97 @@ fn parse_no_socket(matches: &ArgMatches) -> Result<bool, String> {
 ///
 /// # Returns
 ///
-/// The number of repetitions or an error message.
-fn parse_reps(matches: &ArgMatches) -> Result<usize, String> {
-    let v = matches.value_of("reps").unwrap();
+/// The number of packets or an error message.
+fn parse_reread(matches: &ArgMatches) -> Result<usize, String> {
+    let v = matches.value_of("reread").unwrap();
     match v.parse::<usize>() {
-        Ok(r) => Ok(r),
-        _ => Err(format!("Bad reps value. {}", v))
+        Ok(n) => Ok(n),
+        _ => Err(format!("Bad reread value: {}", v))
+    }
+}
+
+/// Parse the round robin parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the tests are sent to each destination in turn, or an error message.
+fn parse_round_robin(matches: &ArgMatches) -> Result<bool, String> {
+    if !matches.is_present("round-robin") {
+        return Ok(false);
+    }
+    // The firmware can only pick the destination from the frame
+    let layout = frame::parse_layout(matches.value_of("frame-layout").unwrap())?;
+    if !layout.contains(&Field::DestIp) || !layout.contains(&Field::DestPort) {
+        return Err("Round robin needs the dest_ip and dest_port frame fields".to_string());
+    }
+    Ok(true)
+}
+
+/// Parse the seed of the random number generator of the run.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The run seed, which is random if one was not given, or an error message.
+fn parse_run_seed(matches: &ArgMatches) -> Result<u64, String> {
+    match matches.value_of("run-seed") {
+        Some(v) => v.parse::<u64>().map_err(|_| format!("Bad run seed value: {}", v)),
+        None => Ok(test_case::gen_u64(&mut rand::thread_rng()))
+    }
+}
+
+/// Parse the data seed.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The data seed if one was given, or an error message.
+fn parse_seed(matches: &ArgMatches) -> Result<Option<u16>, String> {
+    match matches.value_of("seed") {
+        Some(v) => parse_data_value(v, matches.is_present("word-mode"))
+            .map(Some)
+            .ok_or_else(|| format!("Bad seed value: {}", v)),
+        None => Ok(None)
     }
 }
 
-/// Parse the serial port and baudrate.
+/// Parse the seed sweep parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether one test is run for every seed.
+fn parse_seed_sweep(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("seed-sweep"))
+}
+
+/// Parse the sequence number parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether packets carry sequence numbers.
+fn parse_seqnum(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("seqnum"))
+}
+
+/// Parse the serial log file.
 ///
 /// # Arguments
 ///
@@ -236,10 +2552,22 @@ fn parse_reps(matches: &ArgMatches) -> Result<usize, String> {
 ///
 /// # Returns
 ///
+/// The path of the file to log the serial bytes to if one was given.
+fn parse_serial_log(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("serial-log").map(|v| v.to_string()))
+}
+
+/// Parse a serial port and baudrate.
+///
+/// # Arguments
+///
+/// * `v` - The value of the argument to parse.
+///
+/// # Returns
+///
 /// The serial port and baudrate or an error message.
-fn parse_serial_port_baud(matches: &ArgMatches) -> Result<(String, BaudRate), String> {
+fn parse_serial_port_baud(v: &str) -> Result<(String, BaudRate), String> {
     // Get the raw argument string
-    let v = matches.value_of("serial-port").unwrap();
     let raw = match v.parse::<String>() {
         Ok(r) => r,
         _ => return Err(format!("Bad IP, port, and MAC value. {}", v))
@@ -252,11 +2580,45 @@ fn parse_serial_port_baud(matches: &ArgMatches) -> Result<(String, BaudRate), St
     };
     // Get the port name and baud
     let port = captures.get(1).unwrap().as_str().to_string();
-    let baud = match captures.get(2).unwrap().as_str().parse::<usize>() {
-        Ok(speed) => BaudRate::from_speed(speed),
-        _ => return Err("Bad baudrate".to_string())
+    let baud = match parse_baud(
+        captures.get(2).unwrap().as_str(),
+        captures.get(3).map_or("", |m| m.as_str()),
+        captures.get(4).unwrap().as_str()
+    ) {
+        Some(speed) => BaudRate::from_speed(speed),
+        None => return Err(format!("Bad baudrate: {}", raw))
+    };
+    Ok((port, baud))
+}
+
+/// Parse a baudrate from its parts.
+///
+/// # Arguments
+///
+/// * `whole` - The digits of the whole part.
+/// * `fraction` - The digits of the fraction, which may be empty.
+/// * `suffix` - The `k` or `M` suffix, which may be empty.
+///
+/// # Returns
+///
+/// The baudrate, or `None` if it is not a whole number or is out of range.
+fn parse_baud(whole: &str, fraction: &str, suffix: &str) -> Option<usize> {
+    let multiplier: usize = match suffix {
+        "k" | "K" => 1_000,
+        "m" | "M" => 1_000_000,
+        _ => 1
     };
-    return Ok((port, baud))
+    let whole = whole.parse::<usize>().ok()?.checked_mul(multiplier)?;
+    if fraction.is_empty() {
+        return Some(whole);
+    }
+    // The fraction must scale to a whole number, so `1.5k` is allowed but `1.5` is not
+    let scale = 10usize.checked_pow(fraction.len() as u32)?;
+    let fraction = fraction.parse::<usize>().ok()?.checked_mul(multiplier)?;
+    if fraction % scale != 0 {
+        return None;
+    }
+    whole.checked_add(fraction / scale)
 }
 
 /// Parse the show all parameter.
@@ -272,6 +2634,232 @@ fn parse_show_all(matches: &ArgMatches) -> Result<bool, String> {
     Ok(matches.is_present("show-all"))
 }
 
+/// Parse the number of stop bits of each serial character.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The stop bits or an error message.
+fn parse_stop_bits(matches: &ArgMatches) -> Result<StopBits, String> {
+    match matches.value_of("stop-bits").unwrap() {
+        "1" => Ok(Stop1),
+        "2" => Ok(Stop2),
+        v => Err(format!("Bad stop bits value: {}", v))
+    }
+}
+
+/// Parse the suggest endian parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether failed payloads should be checked for an endianness bug.
+fn parse_suggest_endian(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("suggest-endian"))
+}
+
+/// Parse the suggest inverted parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether failed payloads should be checked for inverted bits.
+fn parse_suggest_inverted(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("suggest-inverted"))
+}
+
+/// Parse the summary JSON file.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The path of the file to write the summary to if one was given.
+fn parse_summary_json(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.value_of("summary-json").map(|v| v.to_string()))
+}
+
+/// Parse the throughput parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the throughput should be shown.
+fn parse_throughput(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("throughput"))
+}
+
+/// Parse the time to wait for each packet.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The timeout or an error message.
+fn parse_timeout(matches: &ArgMatches) -> Result<Duration, String> {
+    let v = matches.value_of("timeout").unwrap();
+    match v.parse::<f64>() {
+        Ok(secs) if secs > 0.0 && secs.is_finite() => Ok(Duration::from_secs_f64(secs)),
+        _ => Err(format!("Bad timeout value: {}", v))
+    }
+}
+
+/// Parse what to do when no packet arrives before the timeout.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The timeout policy or an error message.
+fn parse_timeout_policy(matches: &ArgMatches) -> Result<TimeoutPolicy, String> {
+    match matches.value_of("timeout-policy").unwrap() {
+        "fail" => Ok(TimeoutPolicy::Fail),
+        "retry" => Ok(TimeoutPolicy::Retry),
+        "abort" => Ok(TimeoutPolicy::Abort),
+        v => Err(format!("Bad timeout policy value: {}", v))
+    }
+}
+
+/// Parse the number of times to send the serial frame again after a timeout.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The number of retries or an error message.
+fn parse_timeout_retries(matches: &ArgMatches) -> Result<usize, String> {
+    let v = matches.value_of("timeout-retries").unwrap();
+    match v.parse::<usize>() {
+        Ok(n) => Ok(n),
+        _ => Err(format!("Bad timeout retries value: {}", v))
+    }
+}
+
+/// Parse the comparison tolerance.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The tolerance or an error message.
+fn parse_tolerance(matches: &ArgMatches) -> Result<u8, String> {
+    let v = matches.value_of("tolerance").unwrap();
+    match v.parse::<u8>() {
+        Ok(n) => Ok(n),
+        _ => Err(format!("Bad tolerance value: {}", v))
+    }
+}
+
+/// Parse the verbosity.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The verbosity, which is the number of times the verbose flag was given.
+fn parse_verbosity(matches: &ArgMatches) -> Result<usize, String> {
+    Ok(matches.occurrences_of("verbose") as usize)
+}
+
+/// Parse the length field of the payload to check.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The offset and width of the field in bytes if one was given, or an error message.
+fn parse_verify_length_field(matches: &ArgMatches) -> Result<Option<(usize, usize)>, String> {
+    let v = match matches.value_of("verify-length-field") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    let field = v.split_once(':').and_then(|(offset, width)| {
+        Some((offset.parse::<usize>().ok()?, width.parse::<usize>().ok()?))
+    });
+    match field {
+        Some((offset, width)) if (1..=8).contains(&width) => Ok(Some((offset, width))),
+        _ => Err(format!("Bad length field value: {}", v))
+    }
+}
+
+/// Parse the time without a finished test after which to warn that the run is stuck.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// The time if one was given, or an error message.
+fn parse_watchdog(matches: &ArgMatches) -> Result<Option<Duration>, String> {
+    let v = match matches.value_of("watchdog") {
+        Some(v) => v,
+        None => return Ok(None)
+    };
+    match v.parse::<f64>() {
+        Ok(secs) if secs > 0.0 && secs.is_finite() => Ok(Some(Duration::from_secs_f64(secs))),
+        _ => Err(format!("Bad watchdog value: {}", v))
+    }
+}
+
+/// Parse the word mode parameter.
+///
+/// # Arguments
+///
+/// * `matches` - The matches from the command line arguments.
+///
+/// # Returns
+///
+/// An indicator of whether the payload is generated as 16-bit words.
+fn parse_word_mode(matches: &ArgMatches) -> Result<bool, String> {
+    Ok(matches.is_present("word-mode"))
+}
+
+/// Parse a data seed or generator that is either decimal or hexadecimal with a `0x` prefix.
+///
+/// # Arguments
+///
+/// * `v` - The value to parse.
+/// * `word_mode` - Whether the value may be 16 bits instead of 8 bits.
+///
+/// # Returns
+///
+/// The value, or `None` if it is not valid.
+pub fn parse_data_value(v: &str, word_mode: bool) -> Option<u16> {
+    let n = if v.starts_with("0x") || v.starts_with("0X") {
+        u16::from_str_radix(&v[2..], 16).ok()?
+    } else {
+        v.parse::<u16>().ok()?
+    };
+    if word_mode || n <= 0xFF { Some(n) } else { None }
+}
+
 /// Format an IP address.
 ///
 /// # Arguments
@@ -282,7 +2870,7 @@ fn parse_show_all(matches: &ArgMatches) -> Result<bool, String> {
 ///
 /// A formatted IP address.
 fn format_ip(ip: &u32) -> String {
-    let f = |n| ((ip  >> (8 * n)) & 0xFFu32);
+    let f = |n| (ip >> (8 * n)) & 0xFFu32;
     format!("{}.{}.{}.{}", f(3), f(2), f(1), f(0))
 }
 
@@ -296,6 +2884,146 @@ fn format_ip(ip: &u32) -> String {
 ///
 /// A formatted MAC address.
 fn format_mac(mac: &u64) -> String {
-    let f = |n| ((mac >> (8 * n)) & 0xFFu64);
+    let f = |n| (mac >> (8 * n)) & 0xFFu64;
     format!("{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}", f(5), f(4), f(3), f(2), f(1), f(0))
 }
+
+/// Get the arguments of a loopback run of one 8 byte test, followed by more arguments, which
+/// override the first ones.
+///
+/// # Arguments
+///
+/// * `extra` - The arguments to add.
+///
+/// # Returns
+///
+/// The arguments, where the first is the name of the program.
+#[cfg(test)]
+pub fn test_args(extra: &[&str]) -> Vec<String> {
+    ["ether_tester", "--loopback", "--bytes", "8", "--reps", "1", "--src",
+        "10.0.0.2:4096,aa:bb:cc:dd:ee:ff", "--dest", "10.0.0.1:5555,00:11:22:33:44:55"].iter()
+        .chain(extra)
+        .map(|arg| arg.to_string())
+        .collect()
+}
+
+/// Get the parameters of a loopback run of one 8 byte test with more arguments.
+///
+/// # Arguments
+///
+/// * `extra` - The arguments to add.
+///
+/// # Returns
+///
+/// The parameters.
+#[cfg(test)]
+pub fn test_params(extra: &[&str]) -> Params {
+    Params::from_args(&test_args(extra)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Baudrates are whole numbers with an optional `k` or `M` suffix.
+    #[test]
+    fn serial_port_baud() {
+        let re = Regex::new(SERIAL_BAUD_REGEX).unwrap();
+        let speed = |v: &str| re.captures(v).and_then(|c| parse_baud(
+            c.get(2).unwrap().as_str(),
+            c.get(3).map_or("", |m| m.as_str()),
+            c.get(4).unwrap().as_str()
+        ));
+        assert_eq!(speed("/dev/ttyUSB0:115200"), Some(115_200));
+        assert_eq!(speed("port:1M"), Some(1_000_000));
+        assert_eq!(speed("port:115.2k"), Some(115_200));
+        assert_eq!(speed("port:1.5k"), Some(1_500));
+        // A rate that the adapter may not support is still parsed, and only warned about
+        assert_eq!(speed("port:12345"), Some(12_345));
+        assert!(!STANDARD_BAUDS.contains(&12_345));
+        // A suffix without digits, or a fraction of a baud
+        assert_eq!(speed("port:k"), None);
+        assert_eq!(speed("port:1.5"), None);
+        assert_eq!(speed("port:1.0005k"), None);
+        // Too large for a usize, before and after the suffix
+        assert_eq!(speed("port:99999999999999999999"), None);
+        assert_eq!(speed("port:18446744073709551615k"), None);
+        assert_eq!(speed("port:18446744073709551.616k"), None);
+    }
+
+    /// A pattern must have at least one byte.
+    #[test]
+    fn empty_pattern() {
+        let params = Params::from_args(&test_args(&["--pattern", "a1b2c3"])).unwrap();
+        assert_eq!(params.pattern, Some(vec![0xA1, 0xB2, 0xC3]));
+        assert_eq!(Params::from_args(&test_args(&["--pattern", ""])).err(),
+            Some("The pattern is empty".to_string()));
+    }
+
+    /// Raw frames are rejected in loopback mode.
+    #[test]
+    fn raw_loopback() {
+        assert!(Params::from_args(&test_args(&[])).is_ok());
+        assert!(Params::from_args(&test_args(&["--raw"])).is_err());
+    }
+
+    /// The candidate payloads are built from 8-bit seeds and generators, so they cannot be used
+    /// with the 16-bit seeds and generators of word mode.
+    #[test]
+    fn expect_any_word_mode() {
+        assert!(Params::from_args(&test_args(&["--expect-any", "ramp,big-words"])).is_ok());
+        assert!(Params::from_args(&test_args(&["--expect-any", "ramp", "--word-mode"])).is_err());
+    }
+
+    /// The candidate payloads replace the expected payload, so the other ways of making it are
+    /// rejected rather than ignored.
+    #[test]
+    fn expect_any_conflicts() {
+        for extra in &[&["--pattern", "0102"][..], &["--gen-command", "cat"], &["--word-mode"]] {
+            assert!(Params::from_args(&test_args(extra)).is_ok(), "{:?}", extra);
+            let args = test_args(&[&["--expect-any", "ramp"][..], extra].concat());
+            assert!(Params::from_args(&args).is_err(), "{:?}", extra);
+        }
+    }
+
+    /// A normal run needs a number of tests or a time limit, unless another mode chooses the tests.
+    #[test]
+    fn reps_or_duration() {
+        let mut args = test_args(&[]);
+        let i = args.iter().position(|arg| arg == "--reps").unwrap();
+        args.drain(i..i + 2);
+        assert_eq!(Params::from_args(&args).err(),
+            Some("One of --reps or --duration is required".to_string()));
+        for extra in &["--duration", "--reps"] {
+            let args = [&args[..], &[extra.to_string(), "5".to_string()]].concat();
+            assert!(Params::from_args(&args).is_ok(), "{}", extra);
+        }
+        args.push("--seed-sweep".to_string());
+        assert!(Params::from_args(&args).is_ok());
+    }
+
+    /// Each device gets a run seed of its own, and the first device keeps the run seed, so a run
+    /// of that device on its own sends the same test cases.
+    #[test]
+    fn device_run_seeds() {
+        let params = test_params(&["--run-seed", "349", "--dest",
+            "10.0.0.1:5556,00:11:22:33:44:56"]);
+        let seeds: Vec<u64> = params.device_params().iter().map(|p| p.run_seed).collect();
+        assert_eq!(seeds, vec![349, 349 ^ SEED_SPREAD]);
+    }
+
+    /// The multicast group is joined on the interface from `--bind`, which needs a group, and on
+    /// the host IP address otherwise.
+    #[test]
+    fn bind_membership() {
+        let params = test_params(&["--multicast", "239.1.2.3"]);
+        assert_eq!(params.membership_ip(), params.dest_ip);
+        let params = test_params(&["--multicast", "239.1.2.3", "--bind", "192.168.5.1"]);
+        assert_eq!(params.membership_ip(), 0xC0A8_0501);
+        assert_eq!(params.bind_ip(), params.dest_ip);
+        assert!(Params::from_args(&test_args(&["--bind", "192.168.5.1"])).is_err());
+        let args = test_args(&["--multicast", "239.1.2.3", "--bind", "192.168.5"]);
+        assert_eq!(Params::from_args(&args).err(),
+            Some("Bad bind IP address: 192.168.5".to_string()));
+    }
+}
@@ -0,0 +1,56 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Writes the bytes of every received packet to a binary file for processing outside of the
+/// tester, whether or not the tests pass.
+pub struct PayloadLog {
+    /// The payload file.
+    file: BufWriter<File>,
+
+    /// Indicates that each packet is written after its length as a 4-byte big endian number, so
+    /// that the packets can be split apart again.
+    lengths: bool
+}
+
+impl PayloadLog {
+    /// Create a new payload file, replacing any existing file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    /// * `lengths` - Whether each packet is written after its length.
+    ///
+    /// # Returns
+    ///
+    /// The payload log or an error message.
+    pub fn create(path: &str, lengths: bool) -> Result<PayloadLog, String> {
+        let file = File::create(path).map_err(|err| format!("{}: {}", path, err))?;
+        Ok(PayloadLog { file: BufWriter::new(file), lengths })
+    }
+
+    /// Add the bytes of a packet to the end of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - The received bytes.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error message on a failed write.
+    pub fn write(&mut self, packet: &[u8]) -> Result<(), String> {
+        if self.lengths {
+            self.file.write_all(&(packet.len() as u32).to_be_bytes())
+                .map_err(|err| err.to_string())?;
+        }
+        self.file.write_all(packet).map_err(|err| err.to_string())
+    }
+
+    /// Write out any buffered packets.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error message on a failed write.
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.file.flush().map_err(|err| err.to_string())
+    }
+}
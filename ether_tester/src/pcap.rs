@@ -0,0 +1,194 @@
+use std::fs::File;
+use std::io::Read;
+
+/// The size of the header at the start of a capture file.
+const FILE_HEADER_LEN: usize = 24;
+
+/// The link type of captures with Ethernet headers.
+const LINKTYPE_ETHERNET: u32 = 1;
+
+/// The link type of captures from the Linux `any` device.
+const LINKTYPE_LINUX_SLL: u32 = 113;
+
+/// The link type of captures that start at the IP header.
+const LINKTYPE_RAW: u32 = 101;
+
+/// The size of the header before each packet of a capture file.
+const RECORD_HEADER_LEN: usize = 16;
+
+/// Read the payloads of the UDP packets to a destination from a pcap capture file. Fragmented
+/// packets are left out because they are not reassembled.
+///
+/// # Arguments
+///
+/// * `path` - The path of the capture file.
+/// * `dest_ip` - The destination IP address of the packets to read.
+/// * `dest_port` - The destination port of the packets to read.
+///
+/// # Returns
+///
+/// The payloads in the order that they were captured, or an error message.
+pub fn udp_payloads(path: &str, dest_ip: u32, dest_port: u16) -> Result<Vec<Vec<u8>>, String> {
+    let mut data = vec![];
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut data))
+        .map_err(|err| format!("{}: {}", path, err))?;
+    parse(&data, dest_ip, dest_port).map_err(|msg| format!("{}: {}", path, msg))
+}
+
+/// Read the payloads of the UDP packets to a destination from the bytes of a pcap capture.
+///
+/// # Arguments
+///
+/// * `data` - The bytes of the capture.
+/// * `dest_ip` - The destination IP address of the packets to read.
+/// * `dest_port` - The destination port of the packets to read.
+///
+/// # Returns
+///
+/// The payloads in the order that they were captured, or an error message.
+fn parse(data: &[u8], dest_ip: u32, dest_port: u16) -> Result<Vec<Vec<u8>>, String> {
+    if data.len() < FILE_HEADER_LEN {
+        return Err("Too short for a pcap file".to_string());
+    }
+    // The magic number gives the byte order of the headers, and both microsecond and nanosecond
+    // captures have the same layout
+    let little = match data[..4] {
+        [0xD4, 0xC3, 0xB2, 0xA1] | [0x4D, 0x3C, 0xB2, 0xA1] => true,
+        [0xA1, 0xB2, 0xC3, 0xD4] | [0xA1, 0xB2, 0x3C, 0x4D] => false,
+        _ => return Err("Not a pcap file".to_string())
+    };
+    let u32_at = |bytes: &[u8], i: usize| {
+        let b = [bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]];
+        if little { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) }
+    };
+    let link_type = u32_at(data, 20);
+    let link_len = match link_type {
+        LINKTYPE_ETHERNET => 14,
+        LINKTYPE_LINUX_SLL => 16,
+        LINKTYPE_RAW => 0,
+        _ => return Err(format!("Unsupported link type {}", link_type))
+    };
+    let mut payloads = vec![];
+    let mut i = FILE_HEADER_LEN;
+    while i + RECORD_HEADER_LEN <= data.len() {
+        let len = u32_at(data, i + 8) as usize;
+        let start = i + RECORD_HEADER_LEN;
+        if start + len > data.len() {
+            return Err(format!("Packet at byte {} is cut short", i));
+        }
+        if len > link_len {
+            if let Some(payload) = udp_payload(&data[start + link_len..start + len], dest_ip,
+                dest_port) {
+                payloads.push(payload.to_vec());
+            }
+        }
+        i = start + len;
+    }
+    Ok(payloads)
+}
+
+/// Get the payload of an IPv4 UDP packet to a destination.
+///
+/// # Arguments
+///
+/// * `packet` - The packet, which starts with the IP header.
+/// * `dest_ip` - The destination IP address.
+/// * `dest_port` - The destination port.
+///
+/// # Returns
+///
+/// The UDP payload, or `None` if the packet is not a whole UDP packet to the destination.
+fn udp_payload(packet: &[u8], dest_ip: u32, dest_port: u16) -> Option<&[u8]> {
+    if packet.len() < 20 || packet[0] >> 4 != 4 || packet[9] != 17 {
+        return None;
+    }
+    // A fragment has the more fragments flag or an offset
+    if (u16::from(packet[6]) << 8 | u16::from(packet[7])) & 0x3FFF != 0 {
+        return None;
+    }
+    let ip_len = usize::from(packet[0] & 0x0F) * 4;
+    let ip = u32::from_be_bytes([packet[16], packet[17], packet[18], packet[19]]);
+    let udp = packet.get(ip_len..)?;
+    if ip != dest_ip || udp.len() < 8 || u16::from_be_bytes([udp[2], udp[3]]) != dest_port {
+        return None;
+    }
+    // The capture may pad short frames, so the UDP length gives the end of the payload
+    let udp_len = usize::from(u16::from_be_bytes([udp[4], udp[5]]));
+    udp.get(8..udp_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::checksum;
+
+    /// The destination of the packets in the captures.
+    const DEST: (u32, u16) = (0x0A00_0001, 5555);
+
+    /// Write a capture of packets in the byte order of the host, like the capture tools do.
+    ///
+    /// # Arguments
+    ///
+    /// * `link_type` - The link type of the capture.
+    /// * `packets` - The packets, which start with the header of the link type.
+    ///
+    /// # Returns
+    ///
+    /// The bytes of the capture.
+    fn capture(link_type: u32, packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![];
+        for &field in &[0xA1B2_C3D4, 0x0004_0002, 0, 0, 65535, link_type] {
+            data.extend_from_slice(&u32::to_ne_bytes(field));
+        }
+        for (i, packet) in packets.iter().enumerate() {
+            let len = packet.len() as u32;
+            for &field in &[1_600_000_000 + i as u32, 0, len, len] {
+                data.extend_from_slice(&u32::to_ne_bytes(field));
+            }
+            data.extend_from_slice(packet);
+        }
+        data
+    }
+
+    /// A big endian capture that was written out by hand, with the magic number and link type in
+    /// the file header, and the time, captured length, and original length in the record header.
+    #[test]
+    fn big_endian() {
+        let mut data = vec![
+            0xA1, 0xB2, 0xC3, 0xD4, 0x00, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x65,
+            0x5F, 0x5E, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F,
+            0x00, 0x00, 0x00, 0x1F];
+        data.extend(checksum::wrap((0x0A00_0002, 4096), DEST, &[1, 2, 3]));
+        assert_eq!(parse(&data, DEST.0, DEST.1), Ok(vec![vec![1, 2, 3]]));
+    }
+
+    /// Only the payloads of the packets to the destination are read, with the link headers
+    /// skipped.
+    #[test]
+    fn payloads() {
+        let to_dest = checksum::wrap((0x0A00_0002, 4096), DEST, &[1, 2, 3]);
+        let elsewhere = checksum::wrap((0x0A00_0002, 4096), (DEST.0, 5556), &[4, 5, 6]);
+        let data = capture(LINKTYPE_RAW, &[to_dest.clone(), elsewhere.clone()]);
+        assert_eq!(parse(&data, DEST.0, DEST.1), Ok(vec![vec![1, 2, 3]]));
+        let ethernet = |p: &Vec<u8>| [&[0; 12][..], &[0x08, 0x00], p].concat();
+        let data = capture(LINKTYPE_ETHERNET, &[ethernet(&elsewhere), ethernet(&to_dest)]);
+        assert_eq!(parse(&data, DEST.0, DEST.1), Ok(vec![vec![1, 2, 3]]));
+    }
+
+    /// A file that is not a capture of a supported link type, or that is cut short, is an error.
+    #[test]
+    fn bad_captures() {
+        let packet = checksum::wrap((0x0A00_0002, 4096), DEST, &[1, 2, 3]);
+        let data = capture(LINKTYPE_RAW, &[packet]);
+        assert_eq!(parse(&data[..10], DEST.0, DEST.1),
+            Err("Too short for a pcap file".to_string()));
+        assert_eq!(parse(&[&[0; 4][..], &data[4..]].concat(), DEST.0, DEST.1),
+            Err("Not a pcap file".to_string()));
+        assert_eq!(parse(&capture(228, &[]), DEST.0, DEST.1),
+            Err("Unsupported link type 228".to_string()));
+        assert_eq!(parse(&data[..data.len() - 1], DEST.0, DEST.1),
+            Err(format!("Packet at byte {} is cut short", FILE_HEADER_LEN)));
+    }
+}
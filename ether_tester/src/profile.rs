@@ -0,0 +1,81 @@
+use std::env;
+use std::fs;
+
+/// The profiles that are built into the program, as the name and the arguments of each.
+const BUILT_IN: &[(&str, &str)] = &[
+    ("constant-fill", "--bytes 1024 --gen 0 --reps 100"),
+    ("jumbo", "--jumbo --bytes 8972 --reps 100"),
+    ("long-ramp", "--bytes 1472 --reps 1000"),
+    ("short-ramp", "--bytes 64 --reps 1000"),
+    ("word-ramp", "--word-mode --bytes 1024 --reps 100")
+];
+
+/// The environment variable with the path of a file of more profiles.
+const PROFILES_VAR: &str = "ETHER_PROFILES";
+
+/// Get every profile, which are the built in profiles followed by those in the file from the
+/// `ETHER_PROFILES` environment variable. Each line of the file is a name followed by the
+/// arguments of the profile, separated by spaces, and blank lines and lines starting with `#` are
+/// ignored. A profile in the file replaces a built in profile with the same name.
+///
+/// # Returns
+///
+/// The name and arguments of every profile in order, or an error message.
+pub fn all() -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut profiles: Vec<(String, Vec<String>)> = BUILT_IN.iter()
+        .map(|&(name, args)| (name.to_string(), args.split_whitespace().map(String::from)
+            .collect()))
+        .collect();
+    let path = match env::var(PROFILES_VAR) {
+        Ok(path) => path,
+        Err(_) => return Ok(profiles)
+    };
+    let text = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))?;
+    for line in text.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace().map(String::from);
+        let name = words.next().unwrap();
+        let args = words.collect();
+        match profiles.iter_mut().find(|(n, _)| *n == name) {
+            Some(profile) => profile.1 = args,
+            None => profiles.push((name, args))
+        }
+    }
+    Ok(profiles)
+}
+
+/// Put the arguments of the profile that the arguments name before the other arguments, so that
+/// the arguments that are given override those of the profile.
+///
+/// # Arguments
+///
+/// * `args` - The arguments, where the first is the name of the program.
+///
+/// # Returns
+///
+/// The expanded arguments, `None` if no profile is named, or an error message if the profile does
+/// not exist.
+pub fn expand(args: &[String]) -> Result<Option<Vec<String>>, String> {
+    // The last profile is used, like any other argument that is given more than once
+    let name = args.iter().enumerate().skip(1).filter_map(|(i, arg)| {
+        if arg == "--profile" {
+            args.get(i + 1).cloned()
+        } else {
+            arg.strip_prefix("--profile=").map(String::from)
+        }
+    }).next_back();
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(None)
+    };
+    let profile_args = match all()?.into_iter().find(|(n, _)| *n == name) {
+        Some((_, profile_args)) => profile_args,
+        None => return Err(format!("Unknown profile: {}, see --list-profiles", name))
+    };
+    let mut expanded = vec![args[0].clone()];
+    expanded.extend(profile_args);
+    expanded.extend(args[1..].iter().cloned());
+    Ok(Some(expanded))
+}
@@ -0,0 +1,440 @@
+#[cfg(target_os = "linux")]
+use std::ffi::CString;
+use std::io;
+#[cfg(target_os = "linux")]
+use std::mem;
+#[cfg(target_os = "linux")]
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::time::Duration;
+use super::checksum;
+use super::test_case::TestCase;
+
+/// The EtherType of IPv4.
+const ETHER_TYPE_IPV4: u16 = 0x0800;
+
+/// The size of an Ethernet header.
+const ETHERNET_HEADER_LEN: usize = 14;
+
+/// The size of the Ethernet, IPv4, and UDP headers that the FPGA sends before the payload.
+pub const HEADER_LEN: usize = ETHERNET_HEADER_LEN + 20 + 8;
+
+/// The largest number of bytes that a received frame adds to the payload, which is the headers
+/// with the most IP options and the padding of the smallest Ethernet frame.
+pub const MAX_EXTRA_LEN: usize = ETHERNET_HEADER_LEN + checksum::MAX_HEADER_LEN + 46;
+
+/// The IP time to live that the FPGA sends.
+const IP_TTL: u8 = 0xFF;
+
+/// The packet type of a frame that the host sent rather than received.
+#[cfg(target_os = "linux")]
+const PACKET_OUTGOING: u8 = 4;
+
+/// The IP protocol number of UDP.
+const UDP_PROTOCOL: u8 = 17;
+
+/// Build the whole Ethernet frame that the FPGA sends for a test, without the frame check
+/// sequence. The headers are built like the firmware builds them, with an identification, flags,
+/// and fragment offset of 0, a time to live of 255, and no UDP checksum.
+///
+/// # Arguments
+///
+/// * `test_case` - The test case, which gives the addresses.
+/// * `payload` - The UDP payload.
+///
+/// # Returns
+///
+/// The frame.
+pub fn frame(test_case: &TestCase, payload: &[u8]) -> Vec<u8> {
+    let udp_len = 8 + payload.len() as u16;
+    let ip_len = 20 + udp_len;
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+    frame.extend_from_slice(&test_case.dest_mac.to_be_bytes()[2..]);
+    frame.extend_from_slice(&test_case.src_mac.to_be_bytes()[2..]);
+    frame.extend_from_slice(&ETHER_TYPE_IPV4.to_be_bytes());
+    frame.extend_from_slice(&[0x45, 0]);
+    frame.extend_from_slice(&ip_len.to_be_bytes());
+    frame.extend_from_slice(&[0, 0, 0, 0, IP_TTL, UDP_PROTOCOL, 0, 0]);
+    frame.extend_from_slice(&test_case.src_ip.to_be_bytes());
+    frame.extend_from_slice(&test_case.dest_ip.to_be_bytes());
+    let ip_checksum = checksum::header_checksum(&frame[ETHERNET_HEADER_LEN..]);
+    frame[ETHERNET_HEADER_LEN + 10..ETHERNET_HEADER_LEN + 12]
+        .copy_from_slice(&ip_checksum.to_be_bytes());
+    frame.extend_from_slice(&test_case.src_port.to_be_bytes());
+    frame.extend_from_slice(&test_case.dest_port.to_be_bytes());
+    frame.extend_from_slice(&udp_len.to_be_bytes());
+    frame.extend_from_slice(&[0, 0]);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// The UDP frames that a packet socket receives.
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy)]
+enum Filter {
+    /// The frames to a destination IP address and port.
+    To(u32, u16),
+
+    /// The frames from a source IP address to any destination.
+    From(u32)
+}
+
+/// A packet socket that receives whole Ethernet frames, which needs raw socket privileges.
+#[cfg(target_os = "linux")]
+pub struct RawSocket {
+    /// The socket descriptor.
+    fd: libc::c_int,
+
+    /// The UDP frames to receive.
+    filter: Filter
+}
+
+/// A UDP frame that a packet socket received.
+#[cfg(target_os = "linux")]
+struct UdpFrame {
+    /// The size of the frame without any Ethernet padding.
+    len: usize,
+
+    /// The source IP address and port.
+    src: SocketAddr,
+
+    /// The destination IP address and port.
+    dest: (u32, u16)
+}
+
+#[cfg(target_os = "linux")]
+impl RawSocket {
+    /// Open a packet socket for the IPv4 frames to a destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The name of the interface to receive on, or `None` for every interface.
+    /// * `dest` - The destination IP address and port of the frames to receive.
+    ///
+    /// # Returns
+    ///
+    /// The socket or an error.
+    pub fn open(interface: Option<&str>, dest: (u32, u16)) -> io::Result<RawSocket> {
+        RawSocket::open_filter(interface, Filter::To(dest.0, dest.1))
+    }
+
+    /// Open a packet socket for the UDP frames from a source to any destination, which shows
+    /// where the source sends when its packets do not arrive.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The name of the interface to receive on, or `None` for every interface.
+    /// * `src_ip` - The source IP address of the frames to receive.
+    ///
+    /// # Returns
+    ///
+    /// The socket or an error.
+    pub fn sniff(interface: Option<&str>, src_ip: u32) -> io::Result<RawSocket> {
+        RawSocket::open_filter(interface, Filter::From(src_ip))
+    }
+
+    /// Open a packet socket for the IPv4 frames that pass a filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The name of the interface to receive on, or `None` for every interface.
+    /// * `filter` - The UDP frames to receive.
+    ///
+    /// # Returns
+    ///
+    /// The socket or an error.
+    fn open_filter(interface: Option<&str>, filter: Filter) -> io::Result<RawSocket> {
+        let protocol = (libc::ETH_P_IP as u16).to_be();
+        let ifindex = match interface {
+            Some(name) => {
+                let name = CString::new(name)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+                match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+                    0 => return Err(io::Error::last_os_error()),
+                    i => i as libc::c_int
+                }
+            },
+            None => 0
+        };
+        unsafe {
+            let fd = libc::socket(libc::AF_PACKET, libc::SOCK_RAW, libc::c_int::from(protocol));
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let socket = RawSocket { fd, filter };
+            let mut sll: libc::sockaddr_ll = mem::zeroed();
+            sll.sll_family = libc::AF_PACKET as libc::c_ushort;
+            sll.sll_protocol = protocol;
+            sll.sll_ifindex = ifindex;
+            if libc::bind(
+                fd,
+                &sll as *const libc::sockaddr_ll as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t
+            ) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(socket)
+        }
+    }
+
+    /// Set the time that a read waits for a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The time to wait.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success or an error.
+    pub fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        // A timeout of 0 would wait forever
+        let timeout = timeout.max(Duration::from_micros(1));
+        let tv = libc::timeval {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_usec: libc::suseconds_t::from(timeout.subsec_micros())
+        };
+        let result = unsafe {
+            libc::setsockopt(
+                self.fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &tv as *const libc::timeval as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Receive the next UDP frame to the destination. Other frames, and the frames that the host
+    /// sends, are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer to read the frame into.
+    ///
+    /// # Returns
+    ///
+    /// The size of the frame without any Ethernet padding and the source address from its
+    /// headers, or an error. A frame that does not fit in the buffer is cut short.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.recv_udp(buf, 0).map(|frame| (frame.len, frame.src))
+    }
+
+    /// Receive the next UDP frame that has already arrived, without waiting.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer to read the frame into.
+    ///
+    /// # Returns
+    ///
+    /// The destination IP address and port of the frame, or an error, which is a
+    /// `WouldBlock` error when no frame has arrived.
+    pub fn try_recv_dest(&self, buf: &mut [u8]) -> io::Result<(u32, u16)> {
+        self.recv_udp(buf, libc::MSG_DONTWAIT).map(|frame| frame.dest)
+    }
+
+    /// Receive the next UDP frame that passes the filter. Other frames, and the frames that the
+    /// host sends, are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer to read the frame into.
+    /// * `flags` - The flags of the read.
+    ///
+    /// # Returns
+    ///
+    /// The frame or an error.
+    fn recv_udp(&self, buf: &mut [u8], flags: libc::c_int) -> io::Result<UdpFrame> {
+        loop {
+            let mut sll: libc::sockaddr_ll = unsafe { mem::zeroed() };
+            let mut len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+            let size = unsafe {
+                libc::recvfrom(
+                    self.fd,
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    flags,
+                    &mut sll as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                    &mut len
+                )
+            };
+            if size < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if sll.sll_pkttype == PACKET_OUTGOING {
+                continue;
+            }
+            let frame = &buf[..size as usize];
+            if let Some(received) = udp_frame(frame) {
+                let wanted = match self.filter {
+                    Filter::To(ip, port) => received.dest == (ip, port),
+                    Filter::From(ip) => received.src.ip() == Ipv4Addr::from(ip)
+                };
+                if wanted {
+                    return Ok(received);
+                }
+            }
+        }
+    }
+
+}
+
+/// Read the headers of a frame that holds a UDP packet.
+///
+/// # Arguments
+///
+/// * `frame` - The received frame.
+///
+/// # Returns
+///
+/// The frame, or `None` if the frame does not hold a UDP packet.
+#[cfg(target_os = "linux")]
+fn udp_frame(frame: &[u8]) -> Option<UdpFrame> {
+    if frame.len() < HEADER_LEN || frame[12..14] != ETHER_TYPE_IPV4.to_be_bytes() {
+        return None;
+    }
+    let ip = &frame[ETHERNET_HEADER_LEN..];
+    if ip[0] >> 4 != 4 || ip[9] != UDP_PROTOCOL {
+        return None;
+    }
+    let ip_len = usize::from(ip[0] & 0x0F) * 4;
+    let udp = ip.get(ip_len..ip_len + 8)?;
+    let dest_ip = u32::from_be_bytes([ip[16], ip[17], ip[18], ip[19]]);
+    let dest_port = u16::from_be_bytes([udp[2], udp[3]]);
+    // Short frames are padded to the smallest Ethernet frame, which the total length leaves out
+    let total_len = usize::from(u16::from_be_bytes([ip[2], ip[3]]));
+    let src = SocketAddr::from((Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]),
+        u16::from_be_bytes([udp[0], udp[1]])));
+    Some(UdpFrame {
+        len: (ETHERNET_HEADER_LEN + total_len).min(frame.len()),
+        src,
+        dest: (dest_ip, dest_port)
+    })
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for RawSocket {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// A packet socket, which only Linux has, so it can never be opened on this platform.
+#[cfg(not(target_os = "linux"))]
+pub struct RawSocket;
+
+#[cfg(not(target_os = "linux"))]
+impl RawSocket {
+    /// Open a packet socket for the IPv4 frames to a destination. This is not supported on this
+    /// platform.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The name of the interface to receive on, or `None` for every interface.
+    /// * `dest` - The destination IP address and port of the frames to receive.
+    ///
+    /// # Returns
+    ///
+    /// An error.
+    pub fn open(_interface: Option<&str>, _dest: (u32, u16)) -> io::Result<RawSocket> {
+        Err(unsupported())
+    }
+
+    /// Open a packet socket for the UDP frames from a source to any destination. This is not
+    /// supported on this platform.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The name of the interface to receive on, or `None` for every interface.
+    /// * `src_ip` - The source IP address of the frames to receive.
+    ///
+    /// # Returns
+    ///
+    /// An error.
+    pub fn sniff(_interface: Option<&str>, _src_ip: u32) -> io::Result<RawSocket> {
+        Err(unsupported())
+    }
+
+    /// Set the time that a read waits for a frame. This is not supported on this platform.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The time to wait.
+    ///
+    /// # Returns
+    ///
+    /// An error.
+    pub fn set_read_timeout(&self, _timeout: Duration) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    /// Receive the next UDP frame to the destination. This is not supported on this platform.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer to read the frame into.
+    ///
+    /// # Returns
+    ///
+    /// An error.
+    pub fn recv_from(&self, _buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        Err(unsupported())
+    }
+
+    /// Receive the next UDP frame that has already arrived. This is not supported on this
+    /// platform.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer to read the frame into.
+    ///
+    /// # Returns
+    ///
+    /// An error.
+    pub fn try_recv_dest(&self, _buf: &mut [u8]) -> io::Result<(u32, u16)> {
+        Err(unsupported())
+    }
+}
+
+/// Get the error of a packet socket on a platform that does not have them.
+///
+/// # Returns
+///
+/// The error.
+#[cfg(not(target_os = "linux"))]
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "Packet sockets are only supported on Linux")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::params;
+
+    /// A frame from 10.0.0.2:4096 to 10.0.0.1:5555 with a 4 byte payload, which was checked by
+    /// hand.
+    #[test]
+    fn frame_bytes() {
+        let params = params::test_params(&[]);
+        let test_case = TestCase::with_seed_gen(&params, 0, 0);
+        let expected = vec![
+            // Ethernet: destination MAC, source MAC, and EtherType
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF, 0x08, 0x00,
+            // IPv4: version and header length, DSCP, length, ID, flags, TTL, protocol, checksum
+            0x45, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x11, 0xA7, 0xCA,
+            // IPv4: source and destination addresses
+            0x0A, 0x00, 0x00, 0x02, 0x0A, 0x00, 0x00, 0x01,
+            // UDP: source port, destination port, length, and no checksum
+            0x10, 0x00, 0x15, 0xB3, 0x00, 0x0C, 0x00, 0x00,
+            // Payload
+            0x01, 0x02, 0x03, 0x04];
+        assert_eq!(frame(&test_case, &[1, 2, 3, 4]), expected);
+        assert_eq!(checksum::header_checksum(&expected[ETHERNET_HEADER_LEN..HEADER_LEN - 8]), 0);
+    }
+}
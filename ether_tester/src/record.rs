@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use super::params::Params;
+use super::test_case::TestCase;
+
+/// The first line of a record file, which names the columns.
+const HEADER: &str = "# seed gen bytes";
+
+/// Writes every test case of a run to a file so that the run can be replayed. Each line holds the
+/// seed and generator in hexadecimal followed by the number of bytes in decimal.
+pub struct Recorder {
+    /// The record file.
+    file: BufWriter<File>
+}
+
+impl Recorder {
+    /// Create a new record file, replacing any existing file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// The recorder or an error message.
+    pub fn create(path: &str) -> Result<Recorder, String> {
+        let file = File::create(path).map_err(|err| format!("{}: {}", path, err))?;
+        let mut recorder = Recorder { file: BufWriter::new(file) };
+        writeln!(recorder.file, "{}", HEADER).map_err(|err| err.to_string())?;
+        Ok(recorder)
+    }
+
+    /// Add a test case to the record.
+    ///
+    /// # Arguments
+    ///
+    /// * `test_case` - The test case.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error message on a failed write.
+    pub fn record(&mut self, test_case: &TestCase) -> Result<(), String> {
+        writeln!(self.file, "{:02X} {:02X} {}", test_case.seed, test_case.gen,
+            test_case.params.bytes).map_err(|err| err.to_string())
+    }
+
+    /// Write out any buffered test cases.
+    ///
+    /// # Returns
+    ///
+    /// Nothing on success and an error message on a failed write.
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.file.flush().map_err(|err| err.to_string())
+    }
+}
+
+/// Read the test cases from a record file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+/// * `params` - The test parameters to create the tests with.
+///
+/// # Returns
+///
+/// The recorded test cases in order or an error message.
+pub fn replay<'a>(path: &str, params: &'a Params) -> Result<Vec<TestCase<'a>>, String> {
+    let file = File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+    let mut test_cases = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|err| format!("{}: {}", path, err))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad_line = || format!("{}:{}: Bad test case: {}", path, i + 1, line);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 3 {
+            return Err(bad_line());
+        }
+        let seed = u16::from_str_radix(fields[0], 16).map_err(|_| bad_line())?;
+        let gen = u16::from_str_radix(fields[1], 16).map_err(|_| bad_line())?;
+        // Only word mode has seeds and generators that do not fit in a byte
+        if !params.word_mode && (seed > 0xFF || gen > 0xFF) {
+            return Err(format!("{}:{}: Recorded in word mode, but testing without it", path,
+                i + 1));
+        }
+        let bytes = fields[2].parse::<usize>().map_err(|_| bad_line())?;
+        // The number of bytes is fixed by the firmware, so it cannot change during a replay
+        if bytes != params.bytes {
+            return Err(format!("{}:{}: Recorded with {} bytes, but testing with {} bytes",
+                path, i + 1, bytes, params.bytes));
+        }
+        test_cases.push(TestCase::with_seed_gen(params, seed, gen));
+    }
+    Ok(test_cases)
+}
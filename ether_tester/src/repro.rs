@@ -0,0 +1,105 @@
+use regex::Regex;
+use std::env;
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+/// The arguments that choose which tests run, with a value, which are replaced by the pinned
+/// test.
+const TEST_ARGS: &[&str] = &["--duration", "--gen", "-g", "--record", "--replay", "--reps", "-r",
+    "--repro-script", "--seed"];
+
+/// The arguments that choose which tests run, without a value.
+const TEST_FLAGS: &[&str] = &["--gen-sweep", "--seed-sweep"];
+
+/// Pin the arguments of a run to a single test. The arguments that choose the tests, or that
+/// would overwrite the files of the run, are removed and replaced by the seed and generator of
+/// the test and a single repetition.
+///
+/// # Arguments
+///
+/// * `args` - The arguments of the run, where the first is the name of the program.
+/// * `seed` - The seed of the test.
+/// * `gen` - The generator of the test.
+///
+/// # Returns
+///
+/// The arguments that run only the test.
+pub fn pin_args(args: &[String], seed: u16, gen: u16) -> Vec<String> {
+    let mut pinned = vec![args[0].clone()];
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        if TEST_ARGS.contains(&arg.as_str()) {
+            rest.next();
+            continue;
+        }
+        // Values that are joined to the argument, such as `--seed=5` and `-r5`
+        let joined = TEST_ARGS.iter().any(|name| if name.starts_with("--") {
+            arg.starts_with(&format!("{}=", name))
+        } else {
+            arg.starts_with(name) && !arg.starts_with("--")
+        });
+        if joined || TEST_FLAGS.contains(&arg.as_str()) {
+            continue;
+        }
+        pinned.push(arg.clone());
+    }
+    pinned.extend(vec!["--seed".to_string(), format!("0x{:02X}", seed), "--gen".to_string(),
+        format!("0x{:02X}", gen), "--reps".to_string(), "1".to_string()]);
+    pinned
+}
+
+/// Write an executable shell script that runs a command. The environment variables that
+/// arguments fall back to are set in the script as they were set for this run.
+///
+/// # Arguments
+///
+/// * `path` - The path of the script.
+/// * `args` - The command, where the first argument is the name of the program.
+/// * `vars` - The names of the environment variables to keep.
+/// * `note` - Text that is put at the top of the script as comments.
+///
+/// # Returns
+///
+/// Nothing on success or an error message.
+pub fn write(path: &str, args: &[String], vars: &[&str], note: &str) -> Result<(), String> {
+    // Styles in the note are only for the terminal
+    let styles = Regex::new("\x1b\\[[0-9;]*m").unwrap();
+    let mut script = String::from("#!/bin/sh\n");
+    for line in styles.replace_all(note, "").lines() {
+        script += &format!("# {}\n", line);
+    }
+    for var in vars {
+        if let Ok(value) = env::var(var) {
+            script += &format!("export {}={}\n", var, quote(&value));
+        }
+    }
+    let command: Vec<String> = args.iter().map(|arg| quote(arg)).collect();
+    script += &format!("exec {}\n", command.join(" "));
+    fs::write(path, script).map_err(|err| format!("{}: {}", path, err))?;
+    // Other platforms have no execute bit, so the script is run through a shell there
+    #[cfg(unix)]
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|err| format!("{}: {}", path, err))?;
+    Ok(())
+}
+
+/// Quote an argument for the shell. Arguments that the shell leaves alone are not quoted, so that
+/// the command stays readable.
+///
+/// # Arguments
+///
+/// * `arg` - The argument.
+///
+/// # Returns
+///
+/// The quoted argument.
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
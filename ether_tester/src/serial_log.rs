@@ -0,0 +1,129 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use super::hex;
+use super::logger;
+
+/// The time that the reader waits for serial bytes before it checks whether it should stop.
+const POLL_TIMEOUT_MS: libc::c_int = 100;
+
+/// A serial port that logs every byte that the FPGA sends back over it, such as debug text or
+/// acknowledgements. The bytes are read by a background thread, so writing frames is not held up.
+pub struct LoggedPort<P: Write + AsRawFd> {
+    /// The serial port.
+    port: P,
+
+    /// Tells the reader thread to stop.
+    stop: Arc<AtomicBool>,
+
+    /// The reader thread.
+    reader: Option<JoinHandle<()>>
+}
+
+impl<P: Write + AsRawFd> LoggedPort<P> {
+    /// Start reading a serial port into a log file.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The serial port.
+    /// * `file` - The log file.
+    ///
+    /// # Returns
+    ///
+    /// The logged port.
+    pub fn new(port: P, file: File) -> LoggedPort<P> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let fd = port.as_raw_fd();
+        let reader = {
+            let stop = stop.clone();
+            thread::spawn(move || read_into(fd, file, &stop))
+        };
+        LoggedPort { port, stop, reader: Some(reader) }
+    }
+}
+
+/// Open a serial log. The log is added to the end of any existing file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the log file.
+///
+/// # Returns
+///
+/// The log file or an error message.
+pub fn open(path: &str) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Add the bytes of one read to a serial log as a dump after the time that they arrived, which
+/// uses the same clock as the log on standard error.
+///
+/// # Arguments
+///
+/// * `file` - The log file.
+/// * `bytes` - The bytes that were read.
+///
+/// # Returns
+///
+/// Nothing on success or an error.
+pub fn write_entry(mut file: &File, bytes: &[u8]) -> io::Result<()> {
+    let elapsed = logger::start().elapsed();
+    let entry = format!("[{:>4}.{:06}] Read {} bytes\n{}\n", elapsed.as_secs(),
+        elapsed.subsec_micros(), bytes.len(), hex::dump(bytes));
+    file.write_all(entry.as_bytes())
+}
+
+impl<P: Write + AsRawFd> Write for LoggedPort<P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.port.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+impl<P: Write + AsRawFd> Drop for LoggedPort<P> {
+    fn drop(&mut self) {
+        // The reader must be done with the port before the port is closed
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// Read serial bytes until told to stop and write each read to the log.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor of the serial port.
+/// * `file` - The log file.
+/// * `stop` - Tells the reader to stop.
+fn read_into(fd: RawFd, file: File, stop: &AtomicBool) {
+    let mut buf = [0u8; 4096];
+    while !stop.load(Ordering::SeqCst) {
+        let mut poll_fd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        // An interrupted wait is only tried again
+        if unsafe { libc::poll(&mut poll_fd, 1, POLL_TIMEOUT_MS) } <= 0 {
+            continue;
+        }
+        let len = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if len <= 0 {
+            continue;
+        }
+        // A log that cannot be written is not worth stopping the tests for
+        if write_entry(&file, &buf[..len as usize]).is_err() {
+            break;
+        }
+    }
+}
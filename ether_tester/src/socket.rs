@@ -0,0 +1,397 @@
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+
+/// Bind a UDP socket with `SO_REUSEADDR` set, so that the address can be bound again right away
+/// when the tester is restarted in a tight loop.
+///
+/// # Arguments
+///
+/// * `addr` - The address to bind to.
+///
+/// # Returns
+///
+/// The bound socket or an error.
+#[cfg(unix)]
+pub fn bind(addr: SocketAddrV4) -> io::Result<UdpSocket> {
+    use std::mem;
+    use std::os::unix::io::FromRawFd;
+
+    unsafe {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Close the descriptor on any failure so that it does not leak
+        let fail = |fd| {
+            let err = io::Error::last_os_error();
+            libc::close(fd);
+            Err(err)
+        };
+        let reuse: libc::c_int = 1;
+        if libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_REUSEADDR,
+            &reuse as *const libc::c_int as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t
+        ) < 0 {
+            return fail(fd);
+        }
+        let mut sin: libc::sockaddr_in = mem::zeroed();
+        sin.sin_family = libc::AF_INET as libc::sa_family_t;
+        sin.sin_port = addr.port().to_be();
+        sin.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+        if libc::bind(
+            fd,
+            &sin as *const libc::sockaddr_in as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_in>() as libc::socklen_t
+        ) < 0 {
+            return fail(fd);
+        }
+        Ok(UdpSocket::from_raw_fd(fd))
+    }
+}
+
+/// Bind a UDP socket. The address cannot be reused on this platform.
+///
+/// # Arguments
+///
+/// * `addr` - The address to bind to.
+///
+/// # Returns
+///
+/// The bound socket or an error.
+#[cfg(not(unix))]
+pub fn bind(addr: SocketAddrV4) -> io::Result<UdpSocket> {
+    UdpSocket::bind(addr)
+}
+
+/// Set the size of the receive buffer of a socket.
+///
+/// # Arguments
+///
+/// * `socket` - The socket.
+/// * `size` - The requested size in bytes.
+///
+/// # Returns
+///
+/// The size that the operating system applied, which may differ from the requested size, or an
+/// error.
+#[cfg(unix)]
+pub fn set_recv_buffer(socket: &UdpSocket, size: usize) -> io::Result<usize> {
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+
+    let fd = socket.as_raw_fd();
+    let size = size.min(libc::c_int::MAX as usize) as libc::c_int;
+    unsafe {
+        if libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_RCVBUF,
+            &size as *const libc::c_int as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t
+        ) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut applied: libc::c_int = 0;
+        let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+        if libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_RCVBUF,
+            &mut applied as *mut libc::c_int as *mut libc::c_void,
+            &mut len
+        ) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(applied as usize)
+    }
+}
+
+/// Set the size of the receive buffer of a socket. This is not supported on this platform.
+///
+/// # Arguments
+///
+/// * `socket` - The socket.
+/// * `size` - The requested size in bytes.
+///
+/// # Returns
+///
+/// An error.
+#[cfg(not(unix))]
+pub fn set_recv_buffer(_socket: &UdpSocket, _size: usize) -> io::Result<usize> {
+    Err(io::Error::new(io::ErrorKind::Other, "Receive buffer size is not supported"))
+}
+
+/// The membership of a socket in a multicast group, which leaves the group when it is dropped so
+/// that the host stops accepting the group traffic as soon as the tests are over.
+pub struct Membership {
+    /// A handle to the socket that joined the group.
+    socket: UdpSocket,
+
+    /// The multicast group.
+    group: Ipv4Addr,
+
+    /// The address of the interface that the group was joined on.
+    interface: Ipv4Addr
+}
+
+impl Membership {
+    /// Join a multicast group.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The socket to receive the group traffic on.
+    /// * `group` - The multicast group.
+    /// * `interface` - The address of the interface to join the group on.
+    ///
+    /// # Returns
+    ///
+    /// The membership or an error.
+    pub fn join(socket: &UdpSocket, group: Ipv4Addr, interface: Ipv4Addr)
+        -> io::Result<Membership> {
+        // The handle is taken first so that a failure cannot leave the group joined
+        let socket = socket.try_clone()?;
+        socket.join_multicast_v4(&group, &interface)?;
+        Ok(Membership { socket, group, interface })
+    }
+}
+
+impl Drop for Membership {
+    fn drop(&mut self) {
+        // Closing the socket also leaves the group, so there is nothing to do if this fails
+        let _ = self.socket.leave_multicast_v4(&self.group, &self.interface);
+    }
+}
+
+/// Get the IPv4 addresses of a network interface.
+///
+/// # Arguments
+///
+/// * `name` - The name of the interface, such as `eth0`.
+///
+/// # Returns
+///
+/// The address and netmask of each IPv4 address of the interface, `None` if there is no interface
+/// with the name, or an error.
+#[cfg(unix)]
+pub fn interface_addrs(name: &str) -> io::Result<Option<Vec<(Ipv4Addr, Ipv4Addr)>>> {
+    use std::ffi::CStr;
+    use std::ptr;
+
+    let mut found = false;
+    let mut addrs = vec![];
+    unsafe {
+        let mut ifap: *mut libc::ifaddrs = ptr::null_mut();
+        if libc::getifaddrs(&mut ifap) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut ifa = ifap;
+        while !ifa.is_null() {
+            let entry = &*ifa;
+            ifa = entry.ifa_next;
+            if CStr::from_ptr(entry.ifa_name).to_bytes() != name.as_bytes() {
+                continue;
+            }
+            found = true;
+            if entry.ifa_addr.is_null() || entry.ifa_netmask.is_null()
+                    || (*entry.ifa_addr).sa_family as libc::c_int != libc::AF_INET {
+                continue;
+            }
+            let addr = &*(entry.ifa_addr as *const libc::sockaddr_in);
+            let netmask = &*(entry.ifa_netmask as *const libc::sockaddr_in);
+            addrs.push((Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)),
+                Ipv4Addr::from(u32::from_be(netmask.sin_addr.s_addr))));
+        }
+        libc::freeifaddrs(ifap);
+    }
+    Ok(if found { Some(addrs) } else { None })
+}
+
+/// Get the IPv4 addresses of a network interface. This is not supported on this platform.
+///
+/// # Arguments
+///
+/// * `name` - The name of the interface, such as `eth0`.
+///
+/// # Returns
+///
+/// An error.
+#[cfg(not(unix))]
+pub fn interface_addrs(_name: &str) -> io::Result<Option<Vec<(Ipv4Addr, Ipv4Addr)>>> {
+    Err(io::Error::new(io::ErrorKind::Other, "Interface lookup is not supported"))
+}
+
+/// Find the network interface that has an IPv4 address.
+///
+/// # Arguments
+///
+/// * `ip` - The address.
+///
+/// # Returns
+///
+/// The name of the interface, `None` if no interface has the address, or an error.
+#[cfg(unix)]
+pub fn interface_with_addr(ip: Ipv4Addr) -> io::Result<Option<String>> {
+    use std::ffi::CStr;
+    use std::ptr;
+
+    let mut name = None;
+    unsafe {
+        let mut ifap: *mut libc::ifaddrs = ptr::null_mut();
+        if libc::getifaddrs(&mut ifap) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut ifa = ifap;
+        while !ifa.is_null() {
+            let entry = &*ifa;
+            ifa = entry.ifa_next;
+            if entry.ifa_addr.is_null()
+                    || (*entry.ifa_addr).sa_family as libc::c_int != libc::AF_INET {
+                continue;
+            }
+            let addr = &*(entry.ifa_addr as *const libc::sockaddr_in);
+            if u32::from_be(addr.sin_addr.s_addr) == u32::from(ip) {
+                name = Some(CStr::from_ptr(entry.ifa_name).to_string_lossy().into_owned());
+                break;
+            }
+        }
+        libc::freeifaddrs(ifap);
+    }
+    Ok(name)
+}
+
+/// Find the network interface that has an IPv4 address. This is not supported on this platform.
+///
+/// # Arguments
+///
+/// * `ip` - The address.
+///
+/// # Returns
+///
+/// An error.
+#[cfg(not(unix))]
+pub fn interface_with_addr(_ip: Ipv4Addr) -> io::Result<Option<String>> {
+    Err(io::Error::new(io::ErrorKind::Other, "Interface lookup is not supported"))
+}
+
+/// Get the MAC address of a network interface.
+///
+/// # Arguments
+///
+/// * `name` - The name of the interface, such as `eth0`.
+///
+/// # Returns
+///
+/// The MAC address, `None` if the interface has no MAC address, or an error.
+#[cfg(target_os = "linux")]
+pub fn interface_mac(name: &str) -> io::Result<Option<u64>> {
+    use std::ffi::CStr;
+    use std::ptr;
+
+    let mut mac = None;
+    unsafe {
+        let mut ifap: *mut libc::ifaddrs = ptr::null_mut();
+        if libc::getifaddrs(&mut ifap) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut ifa = ifap;
+        while !ifa.is_null() {
+            let entry = &*ifa;
+            ifa = entry.ifa_next;
+            if CStr::from_ptr(entry.ifa_name).to_bytes() != name.as_bytes()
+                    || entry.ifa_addr.is_null()
+                    || (*entry.ifa_addr).sa_family as libc::c_int != libc::AF_PACKET {
+                continue;
+            }
+            let addr = &*(entry.ifa_addr as *const libc::sockaddr_ll);
+            if addr.sll_halen == 6 {
+                mac = Some(addr.sll_addr[..6].iter().fold(0, |acc, &b| acc << 8 | u64::from(b)));
+            }
+        }
+        libc::freeifaddrs(ifap);
+    }
+    Ok(mac)
+}
+
+/// Get the MAC address of a network interface. This is not supported on this platform.
+///
+/// # Arguments
+///
+/// * `name` - The name of the interface, such as `eth0`.
+///
+/// # Returns
+///
+/// An error.
+#[cfg(not(target_os = "linux"))]
+pub fn interface_mac(_name: &str) -> io::Result<Option<u64>> {
+    Err(io::Error::new(io::ErrorKind::Other, "MAC address lookup is not supported"))
+}
+
+/// Get the number of packets that the operating system dropped for a socket, which happens when
+/// packets arrive faster than they are read and the receive buffer is full. The count covers the
+/// whole life of the socket.
+///
+/// # Arguments
+///
+/// * `socket` - The socket.
+///
+/// # Returns
+///
+/// The number of packets, `None` if the socket is not in the table of UDP sockets, or an error.
+#[cfg(target_os = "linux")]
+pub fn os_drops(socket: &UdpSocket) -> io::Result<Option<u64>> {
+    use std::fs;
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+
+    let inode = unsafe {
+        let mut stat: libc::stat = mem::zeroed();
+        if libc::fstat(socket.as_raw_fd(), &mut stat) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stat.st_ino
+    };
+    let table = fs::read_to_string("/proc/net/udp")?;
+    // The inode is the tenth column and the drop count is the last, after a line of headings
+    for line in table.lines().skip(1) {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.len() > 10 && columns[9].parse() == Ok(inode) {
+            return Ok(columns.last().and_then(|drops| drops.parse().ok()));
+        }
+    }
+    Ok(None)
+}
+
+/// Get the number of packets that the operating system dropped for a socket. This is not
+/// supported on this platform.
+///
+/// # Arguments
+///
+/// * `socket` - The socket.
+///
+/// # Returns
+///
+/// An error.
+#[cfg(not(target_os = "linux"))]
+pub fn os_drops(_socket: &UdpSocket) -> io::Result<Option<u64>> {
+    Err(io::Error::new(io::ErrorKind::Other, "Drop counts are not supported"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The group is left when the membership is dropped, even though the socket stays open, so
+    /// the socket can join it again.
+    #[test]
+    fn leave_on_drop() {
+        let s = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let group = Ipv4Addr::new(239, 1, 2, 3);
+        let membership = Membership::join(&s, group, Ipv4Addr::LOCALHOST).unwrap();
+        assert!(Membership::join(&s, group, Ipv4Addr::LOCALHOST).is_err());
+        drop(membership);
+        assert!(Membership::join(&s, group, Ipv4Addr::LOCALHOST).is_ok());
+    }
+}
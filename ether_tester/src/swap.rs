@@ -0,0 +1,199 @@
+/// The ways that a payload may be reordered by an endianness bug, with a description of each.
+const SWAPS: [(Swap, &str); 3] = [
+    (Swap::Words(2), "byte-swapped in 2-byte words"),
+    (Swap::Words(4), "byte-swapped in 4-byte words"),
+    (Swap::Reverse, "reversed")
+];
+
+/// A reordering of the bytes of a payload.
+#[derive(Clone, Copy)]
+enum Swap {
+    /// The bytes of each word of this many bytes are reversed. Bytes after the last whole word
+    /// are not moved.
+    Words(usize),
+
+    /// The whole payload is reversed.
+    Reverse
+}
+
+impl Swap {
+    /// Reorder bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The bytes to reorder.
+    ///
+    /// # Returns
+    ///
+    /// The reordered bytes.
+    fn apply(self, bytes: &[u8]) -> Vec<u8> {
+        let mut v = bytes.to_vec();
+        match self {
+            Swap::Words(n) => v.chunks_exact_mut(n).for_each(|word| word.reverse()),
+            Swap::Reverse => v.reverse()
+        }
+        v
+    }
+}
+
+/// Find a reordering of the expected payload that matches the actual payload, which points to an
+/// endianness bug in the FPGA rather than corrupted data.
+///
+/// # Arguments
+///
+/// * `expected` - The expected payload.
+/// * `actual` - The received payload.
+///
+/// # Returns
+///
+/// A hint that describes the reordering, or `None` if no reordering matches.
+pub fn suggest(expected: &[u8], actual: &[u8]) -> Option<String> {
+    SWAPS.iter()
+        .find(|&&(swap, _)| swap.apply(expected) == actual)
+        .map(|&(_, name)| format!("Payload matches when {}, check FPGA endianness", name))
+}
+
+/// Check whether every bit of the actual payload is the inverse of the expected payload, which
+/// points to an output with the wrong polarity.
+///
+/// # Arguments
+///
+/// * `expected` - The expected payload.
+/// * `actual` - The received payload.
+///
+/// # Returns
+///
+/// A hint about the polarity, or `None` if the payload is not inverted.
+pub fn suggest_inverted(expected: &[u8], actual: &[u8]) -> Option<String> {
+    if expected.len() != actual.len() || expected.is_empty() {
+        return None;
+    }
+    if expected.iter().zip(actual).any(|(&x, &y)| x ^ y != 0xFF) {
+        return None;
+    }
+    Some("Payload is bit-inverted, check output polarity".to_string())
+}
+
+/// Find a rotation of the expected payload that matches the actual payload, which points to a DMA
+/// start pointer in the FPGA that is off by some number of bytes.
+///
+/// # Arguments
+///
+/// * `expected` - The expected payload.
+/// * `actual` - The received payload.
+///
+/// # Returns
+///
+/// A hint with the number of bytes that the actual payload starts into the expected payload, or
+/// `None` if no rotation matches.
+pub fn suggest_rotation(expected: &[u8], actual: &[u8]) -> Option<String> {
+    let n = expected.len();
+    if n != actual.len() {
+        return None;
+    }
+    // Every rotation is a window of the payload repeated twice
+    let doubled = [expected, expected].concat();
+    (1..n)
+        .find(|&k| doubled[k..k + n] == *actual)
+        .map(|k| format!("Payload matches when rotated by {} bytes, check DMA start alignment", k))
+}
+
+/// Find a constant that every received byte is off from the expected byte by, which points to an
+/// accumulator in the FPGA that starts at the wrong value.
+///
+/// # Arguments
+///
+/// * `expected` - The expected payload.
+/// * `actual` - The received payload.
+///
+/// # Returns
+///
+/// A hint with the offset, or `None` if the bytes are not all off by the same nonzero constant.
+pub fn suggest_offset(expected: &[u8], actual: &[u8]) -> Option<String> {
+    if expected.len() != actual.len() || expected.is_empty() {
+        return None;
+    }
+    let offset = actual[0].wrapping_sub(expected[0]);
+    if offset == 0 || expected.iter().zip(actual).any(|(&x, &y)| x.wrapping_add(offset) != y) {
+        return None;
+    }
+    Some(format!("Payload matches with constant offset of {:+}, check accumulator initialization",
+        offset as i8))
+}
+
+/// Find a number of bits that the actual payload is shifted by from the expected payload, which
+/// points to a serializer in the FPGA that aligns its words at the wrong bit. The bits of each
+/// byte are taken most significant first, and the bits that are shifted in at either end are not
+/// compared because they are unknown.
+///
+/// # Arguments
+///
+/// * `expected` - The expected payload.
+/// * `actual` - The received payload.
+///
+/// # Returns
+///
+/// A hint with the shift, or `None` if no shift of 1 to 7 bits matches.
+pub fn suggest_bitshift(expected: &[u8], actual: &[u8]) -> Option<String> {
+    if expected.len() != actual.len() || expected.len() < 2 {
+        return None;
+    }
+    let bit = |bytes: &[u8], i: usize| (bytes[i / 8] >> (7 - i % 8)) & 1;
+    let bits = 8 * expected.len();
+    for shift in 1..8 {
+        // A late payload has the expected bits after the shifted in bits, and an early payload
+        // has lost the first bits
+        let late = (0..bits - shift).all(|i| bit(actual, i + shift) == bit(expected, i));
+        let early = (0..bits - shift).all(|i| bit(actual, i) == bit(expected, i + shift));
+        if late || early {
+            return Some(format!("Payload matches with a {}-bit shift {}, check SERDES word \
+                alignment", shift, if late { "toward the end" } else { "toward the start" }));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The bytes of each whole word are reversed, and the bytes after the last whole word of an
+    /// odd length are not moved.
+    #[test]
+    fn apply_words() {
+        assert_eq!(Swap::Words(2).apply(&[1, 2, 3, 4]), vec![2, 1, 4, 3]);
+        assert_eq!(Swap::Words(2).apply(&[1, 2, 3, 4, 5]), vec![2, 1, 4, 3, 5]);
+        assert_eq!(Swap::Words(4).apply(&[1, 2, 3, 4, 5, 6, 7, 8]), vec![4, 3, 2, 1, 8, 7, 6, 5]);
+        assert_eq!(Swap::Words(4).apply(&[1, 2, 3, 4, 5, 6]), vec![4, 3, 2, 1, 5, 6]);
+        assert_eq!(Swap::Words(4).apply(&[1, 2, 3]), vec![1, 2, 3]);
+    }
+
+    /// The whole payload is reversed, whatever its length.
+    #[test]
+    fn apply_reverse() {
+        assert_eq!(Swap::Reverse.apply(&[1, 2, 3, 4]), vec![4, 3, 2, 1]);
+        assert_eq!(Swap::Reverse.apply(&[1, 2, 3, 4, 5]), vec![5, 4, 3, 2, 1]);
+        assert_eq!(Swap::Reverse.apply(&[]), Vec::<u8>::new());
+    }
+
+    /// Each reordering is named by the hint, including payloads of an odd length.
+    #[test]
+    fn suggest_each_swap() {
+        let expected: Vec<u8> = (1..=9).collect();
+        for &(swap, name) in &SWAPS {
+            let actual = swap.apply(&expected);
+            assert_eq!(suggest(&expected, &actual),
+                Some(format!("Payload matches when {}, check FPGA endianness", name)));
+        }
+    }
+
+    /// A payload that is received as it was sent, or that is wrong in another way, is not a
+    /// reordering.
+    #[test]
+    fn suggest_none() {
+        let expected: Vec<u8> = (1..=9).collect();
+        assert_eq!(suggest(&expected, &expected), None);
+        assert_eq!(suggest(&expected, &[1, 2, 3, 4, 5, 6, 7, 8, 0]), None);
+        assert_eq!(suggest(&expected, &expected[..8]), None);
+    }
+}
@@ -0,0 +1,23 @@
+/// Format rows of names and values as a two column table. The columns are as wide as their widest
+/// cell, so the table stays aligned for values of any length.
+///
+/// # Arguments
+///
+/// * `rows` - The name and value of each row.
+///
+/// # Returns
+///
+/// The formatted table with one row per line.
+pub fn format(rows: &[(String, String)]) -> String {
+    let width = |cell: &String| cell.chars().count();
+    let name_width = rows.iter().map(|(name, _)| width(name)).max().unwrap_or(0);
+    let value_width = rows.iter().map(|(_, value)| width(value)).max().unwrap_or(0);
+    let border = format!("+{}+{}+", "-".repeat(name_width + 2), "-".repeat(value_width + 2));
+    let mut lines = vec![border.clone()];
+    for (name, value) in rows {
+        lines.push(format!("| {:<name_width$} | {:<value_width$} |", name, value,
+            name_width = name_width, value_width = value_width));
+    }
+    lines.push(border);
+    lines.join("\n")
+}
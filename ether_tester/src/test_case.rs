@@ -1,81 +1,611 @@
 extern crate rand;
-use super::params::Params;
+use self::rand::Rng;
+pub use ether_tester::{ramp, tile};
+use ether_tester::{build_expected, ramp_value, word_ramp_value};
+use super::frame;
+use super::gen_command;
+use super::frame::{DecodedFrame, Field};
+use super::params::{Endian, Params};
+
+/// The number of bytes at the end of the generated payload where the FPGA puts the timestamp from
+/// the serial frame and its own latency.
+pub const TIMESTAMP_LEN: usize = 8;
 
 /// A single test case to perform with the FPGA.
 pub struct TestCase<'a> {
     /// The test parameters to use.
     pub params: &'a Params,
 
-    /// The data seed.
-    pub seed: u8,
+    /// The data seed, which is only more than 8 bits in word mode.
+    pub seed: u16,
+
+    /// The data generator, which is only more than 8 bits in word mode.
+    pub gen: u16,
+
+    /// The packet sequence number, which is only sent when sequence numbers are used.
+    pub seq: u16,
+
+    /// The test device IP address, which is the one from the parameters unless it is fuzzed.
+    pub src_ip: u32,
+
+    /// The test device port, which is the one from the parameters unless it is fuzzed.
+    pub src_port: u16,
+
+    /// The test device MAC address, which is the one from the parameters unless it is fuzzed.
+    pub src_mac: u64,
+
+    /// The host IP address, which is the one from the parameters unless the destinations are
+    /// used in turn.
+    pub dest_ip: u32,
+
+    /// The host port, which is the one from the parameters unless the destinations are used in
+    /// turn.
+    pub dest_port: u16,
+
+    /// The host MAC address, which is the one from the parameters unless the destinations are
+    /// used in turn.
+    pub dest_mac: u64,
 
-    /// The data generator.
-    pub gen: u8
+    /// The time that the host sent the frame in microseconds, which is only sent when the FPGA
+    /// sends back timestamps.
+    pub timestamp: u32
 }
 
 impl<'a> TestCase<'a> {
-    /// Create a new test case from the test parameters.
+    /// Create a new test case from the test parameters. The seed and generator are random unless
+    /// they are fixed by the parameters.
     ///
     /// # Arguments
     ///
     /// * `params` - The test parameters to create a test with.
-    pub fn new(params: &'a Params) -> TestCase {
+    /// * `rng` - The random number generator of the run.
+    pub fn new<R: Rng>(params: &'a Params, rng: &mut R) -> TestCase<'a> {
+        let mut random = || if params.word_mode { rng.gen() } else { rng.gen::<u8>().into() };
+        let seed = params.seed.unwrap_or_else(&mut random);
+        let gen = params.gen.unwrap_or_else(&mut random);
+        TestCase::with_seed_gen(params, seed, gen)
+    }
+
+    /// Create a test case for every seed in order, which covers the whole seed space instead of
+    /// sampling it. The generator is crossed with every seed when it is also swept, and otherwise
+    /// it is random for each test unless it is fixed by the parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The test parameters to create the tests with.
+    /// * `rng` - The random number generator of the run.
+    ///
+    /// # Returns
+    ///
+    /// The test cases in order.
+    pub fn sweep<R: Rng>(params: &'a Params, rng: &mut R) -> Vec<TestCase<'a>> {
+        let gens: Vec<Option<u16>> = if params.gen_sweep {
+            (0..=255).map(Some).collect()
+        } else {
+            vec![params.gen]
+        };
+        let mut test_cases = Vec::with_capacity(256 * gens.len());
+        for seed in 0..=255 {
+            for &gen in &gens {
+                test_cases.push(TestCase::with_seed_gen(params, seed,
+                    gen.unwrap_or_else(|| rng.gen::<u8>().into())));
+            }
+        }
+        test_cases
+    }
+
+    /// Create a new test case with a given seed and generator.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The test parameters to create a test with.
+    /// * `seed` - The data seed.
+    /// * `gen` - The data generator.
+    pub fn with_seed_gen(params: &'a Params, seed: u16, gen: u16) -> TestCase<'a> {
         TestCase {
-            params: params,
-            seed: rand::random(),
-            gen: rand::random()
+            params,
+            seed,
+            gen,
+            seq: 0,
+            src_ip: params.src_ip,
+            src_port: params.src_port,
+            src_mac: params.src_mac,
+            dest_ip: params.dest_ip,
+            dest_port: params.dest_port,
+            dest_mac: params.dest_mac,
+            timestamp: 0
         }
     }
 
-    /// The expected value to receive as the payload for the test.
+    /// Create the test case that a decoded serial frame describes, like the FPGA does. Fields
+    /// that are not in the frame keep the values from the parameters, like registers in the
+    /// firmware that are not written, and the seed and generator are 0 if they are not fixed.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// The expected values as an array.
-    pub fn expected(&self) -> Vec<u8> {
-        let mut v = vec![];
-        if self.params.bytes > 0 {
-            v.push(self.seed);
-            for i in 1..self.params.bytes {
-                let next = ((v[i - 1] as u64) + (self.gen as u64)) as u8;
-                v.push(next);
+    /// * `params` - The test parameters.
+    /// * `frame` - The decoded serial frame.
+    pub fn from_decoded(params: &'a Params, frame: &DecodedFrame) -> TestCase<'a> {
+        TestCase {
+            params,
+            seed: frame.seed.unwrap_or_else(|| params.seed.unwrap_or(0)),
+            gen: frame.gen.unwrap_or_else(|| params.gen.unwrap_or(0)),
+            seq: frame.seq.unwrap_or(0),
+            src_ip: frame.src_ip.unwrap_or(params.src_ip),
+            src_port: frame.src_port.unwrap_or(params.src_port),
+            src_mac: frame.src_mac.unwrap_or(params.src_mac),
+            dest_ip: frame.dest_ip.unwrap_or(params.dest_ip),
+            dest_port: frame.dest_port.unwrap_or(params.dest_port),
+            dest_mac: frame.dest_mac.unwrap_or(params.dest_mac),
+            timestamp: frame.timestamp.unwrap_or(0)
+        }
+    }
+
+    /// Randomize the test device addresses to exercise the frame parser of the FPGA. The IP
+    /// address stays on the subnet of the source so that the packet still reaches the host, and
+    /// the MAC address stays unicast.
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - The random number generator of the run.
+    pub fn fuzz<R: Rng>(&mut self, rng: &mut R) {
+        let host_mask = !self.params.netmask;
+        let subnet = self.params.src_ip & self.params.netmask;
+        // The first and last addresses of a subnet are not hosts when there are others
+        self.src_ip = loop {
+            let host = rng.gen::<u32>() & host_mask;
+            if host_mask < 3 || (host != 0 && host != host_mask) {
+                break subnet | host;
             }
+        };
+        self.src_port = rng.gen_range(1, u16::MAX);
+        self.src_mac = gen_u64(rng) & 0xFEFF_FFFF_FFFF;
+    }
+
+    /// Create the test case that continues the payload sequence of this one. The generator is
+    /// kept and the seed is the value that would follow the last byte or word of the expected
+    /// payload.
+    ///
+    /// # Returns
+    ///
+    /// The next test case in the sequence.
+    pub fn next(&self) -> TestCase<'a> {
+        let params = self.params;
+        // The last value plus the generator is the value for one past the end of the payload, where
+        // a half word at the end counts as a whole one
+        let seed = if params.word_mode {
+            word_ramp_value(self.seed, self.gen, params.bytes.div_ceil(2))
+        } else {
+            ramp_value(self.seed as u8, self.gen as u8, params.bytes, params.ramp_wrap_at).into()
+        };
+        TestCase::with_seed_gen(params, seed, self.gen)
+    }
+
+    /// The expected value to receive as the payload for the test, including any prefix and
+    /// suffix around the generated bytes.
+    ///
+    /// # Returns
+    ///
+    /// The expected values as an array, or an error message if the payload command failed.
+    pub fn expected(&self) -> Result<Vec<u8>, String> {
+        self.expected_in(self.params.frame_endian)
+    }
+
+    /// The expected value to receive as the payload for the test, where the words of a word
+    /// payload are in a given byte order instead of the frame byte order.
+    ///
+    /// # Arguments
+    ///
+    /// * `endian` - The byte order of the words.
+    ///
+    /// # Returns
+    ///
+    /// The expected values as an array, or an error message if the payload command failed.
+    pub fn expected_in(&self, endian: Endian) -> Result<Vec<u8>, String> {
+        let params = self.params;
+        // The first of several candidate payloads is the one that failures are reported against.
+        // The pattern, command, and word mode conflict with the candidates, so none is ignored.
+        if !params.expect_any.is_empty() {
+            return Ok(self.candidate(0));
         }
-        return v
+        let v = if let Some(ref pattern) = params.pattern {
+            tile(pattern, params.bytes)
+        } else if let Some(ref command) = params.gen_command {
+            gen_command::generate(command, self.seed, self.gen, params.bytes)
+                .map_err(|msg| format!("Could not generate payload: {}", msg))?
+        } else if params.word_mode {
+            word_ramp(self.seed, self.gen, params.bytes, endian)
+        } else {
+            ramp(self.seed as u8, self.gen as u8, params.bytes, params.ramp_wrap_at)
+        };
+        Ok(self.complete(v))
+    }
+
+    /// One of the candidate payloads that a packet may match, including any prefix and suffix
+    /// around the generated bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `i` - The index of the candidate.
+    ///
+    /// # Returns
+    ///
+    /// The expected values as an array.
+    pub fn candidate(&self, i: usize) -> Vec<u8> {
+        let generator = self.params.expect_any[i];
+        self.complete(build_expected(self.seed as u8, self.gen as u8, self.params.bytes, generator))
     }
 
-    /// Convert the object to bytes that can be sent over serial.
+    /// Add the sequence number, prefix, and suffix to the generated bytes of a payload.
+    ///
+    /// # Arguments
+    ///
+    /// * `v` - The generated bytes.
     ///
     /// # Returns
     ///
-    /// A byte array representation of the struct.
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = vec![];
+    /// The whole payload.
+    fn complete(&self, mut v: Vec<u8>) -> Vec<u8> {
+        if self.params.seqnum {
+            stamp_seq(&mut v, self.seq);
+        }
+        frame_payload(&self.params.payload_prefix, v, &self.params.payload_suffix)
+    }
+
+    /// Write the object as bytes that can be sent over serial. The buffer is cleared first, so
+    /// that it can be reused for every test without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The buffer to write the bytes to.
+    pub fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.clear();
+        let endian = self.params.frame_endian;
         // Not that this can't be implemented in a more generic way because some of these numbers
         // are 48-bit, which does not lend itself well to removing the size parameter.
-        Self::append_bytes(&mut bytes, self.params.src_ip.into(), 4);
-        Self::append_bytes(&mut bytes, self.params.src_port.into(), 2);
-        Self::append_bytes(&mut bytes, self.params.src_mac, 6);
-        Self::append_bytes(&mut bytes, self.params.dest_ip.into(), 4);
-        Self::append_bytes(&mut bytes, self.params.dest_port.into(), 2);
-        Self::append_bytes(&mut bytes, self.params.dest_mac, 6);
-        Self::append_bytes(&mut bytes, self.seed.into(), 1);
-        Self::append_bytes(&mut bytes, self.gen.into(), 1);
-        assert!(bytes.len() == 26);
-        return bytes
+        for &field in &self.params.frame_layout {
+            match (field, &self.params.pattern) {
+                // The pattern is a string of bytes rather than a number, so it is sent in order
+                (Field::Pattern(_), Some(pattern)) => bytes.extend_from_slice(pattern),
+                _ => Self::append_bytes(bytes, self.field_data(field), field.bytes() as u8,
+                    endian)
+            }
+        }
+        assert!(bytes.len() == frame::frame_len(&self.params.frame_layout));
+    }
+
+    /// Get the value of a field of the serial frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The field.
+    ///
+    /// # Returns
+    ///
+    /// The value, which may have more bits than the field.
+    fn field_data(&self, field: Field) -> u64 {
+        match field {
+            Field::SrcIp => self.src_ip.into(),
+            Field::SrcPort => self.src_port.into(),
+            Field::SrcMac => self.src_mac,
+            Field::DestIp => self.dest_ip.into(),
+            Field::DestPort => self.dest_port.into(),
+            Field::DestMac => self.dest_mac,
+            Field::Seed | Field::SeedWord => self.seed.into(),
+            Field::Gen | Field::GenWord => self.gen.into(),
+            Field::Seq => self.seq.into(),
+            Field::PrefixLen => self.params.payload_prefix.len() as u64,
+            Field::SuffixLen => self.params.payload_suffix.len() as u64,
+            // A modulus of 256 does not fit in the field, so it is sent as 0
+            Field::Wrap => self.params.ramp_wrap_at.map_or(0, |n| n as u8).into(),
+            Field::Len => self.params.bytes as u64,
+            Field::Timestamp => self.timestamp.into(),
+            // The pattern does not fit in a number, so it is written on its own
+            Field::Pattern(_) => 0
+        }
     }
 
     /// Add values to a byte vector by deconstructing them. This makes sure that the data is
-    /// interpreted in big endian byte order.
+    /// interpreted in the given byte order regardless of the byte order of the host.
     ///
     /// # Arguments
     ///
     /// * `vec` - The vector.
     /// * `data` - Consists of the bytes to be added to the vector.
     /// * `bytes` - The number of lower bytes in the value to add to the vector.
-    fn append_bytes(vec: &mut Vec<u8>, data: u64, bytes: u8) {
-        for i in (0..bytes).rev() {
-            vec.push(((data >> (8 * i)) & 0xFF) as u8);
+    /// * `endian` - The byte order to add the bytes in.
+    fn append_bytes(vec: &mut Vec<u8>, data: u64, bytes: u8, endian: Endian) {
+        let byte = |i: u8| ((data >> (8 * i)) & 0xFF) as u8;
+        match endian {
+            Endian::Big => vec.extend((0..bytes).rev().map(byte)),
+            Endian::Little => vec.extend((0..bytes).map(byte))
+        }
+    }
+}
+
+/// Generate a payload of 16-bit words in a byte order. See `ether_tester::word_ramp`.
+///
+/// # Arguments
+///
+/// * `seed` - The data seed.
+/// * `gen` - The data generator.
+/// * `bytes` - The number of bytes in the payload.
+/// * `endian` - The byte order of each word.
+///
+/// # Returns
+///
+/// The payload.
+pub fn word_ramp(seed: u16, gen: u16, bytes: usize, endian: Endian) -> Vec<u8> {
+    ether_tester::word_ramp(seed, gen, bytes, endian == Endian::Big)
+}
+
+/// Place a sequence number in the first two bytes of a payload in big endian order. The bytes that
+/// do not fit in a short payload are left out.
+///
+/// # Arguments
+///
+/// * `payload` - The payload.
+/// * `seq` - The packet sequence number.
+pub fn stamp_seq(payload: &mut [u8], seq: u16) {
+    for (b, s) in payload.iter_mut().zip(&[(seq >> 8) as u8, seq as u8]) {
+        *b = *s;
+    }
+}
+
+/// Place the timestamp from the serial frame and the latency of the FPGA in the last bytes of a
+/// generated payload, both in big endian order, like the FPGA does.
+///
+/// # Arguments
+///
+/// * `payload` - The generated payload, which has at least `TIMESTAMP_LEN` bytes.
+/// * `timestamp` - The timestamp from the serial frame.
+/// * `latency_us` - The time from the end of the serial frame to sending the packet in
+///   microseconds.
+pub fn stamp_time(payload: &mut [u8], timestamp: u32, latency_us: u32) {
+    let start = payload.len() - TIMESTAMP_LEN;
+    payload[start..start + 4].copy_from_slice(&timestamp.to_be_bytes());
+    payload[start + 4..].copy_from_slice(&latency_us.to_be_bytes());
+}
+
+/// Read the timestamp and the latency of the FPGA from the bytes that `stamp_time` wrote.
+///
+/// # Arguments
+///
+/// * `stamp` - The `TIMESTAMP_LEN` bytes.
+///
+/// # Returns
+///
+/// The timestamp and the latency in microseconds.
+pub fn read_time(stamp: &[u8]) -> (u32, u32) {
+    let word = |i: usize| u32::from_be_bytes([stamp[i], stamp[i + 1], stamp[i + 2], stamp[i + 3]]);
+    (word(0), word(4))
+}
+
+/// Put the fixed prefix and suffix bytes around a generated payload.
+///
+/// # Arguments
+///
+/// * `prefix` - The bytes before the payload.
+/// * `payload` - The generated payload.
+/// * `suffix` - The bytes after the payload.
+///
+/// # Returns
+///
+/// The whole payload.
+pub fn frame_payload(prefix: &[u8], payload: Vec<u8>, suffix: &[u8]) -> Vec<u8> {
+    if prefix.is_empty() && suffix.is_empty() {
+        return payload;
+    }
+    let mut v = Vec::with_capacity(prefix.len() + payload.len() + suffix.len());
+    v.extend_from_slice(prefix);
+    v.extend(payload);
+    v.extend_from_slice(suffix);
+    v
+}
+
+/// Get a random 64-bit number. Whole 64-bit values, which include `usize` values and ranges on
+/// 64-bit hosts, read misaligned memory in this version of rand, so the number is made from two
+/// 32-bit halves.
+///
+/// # Arguments
+///
+/// * `rng` - The random number generator.
+///
+/// # Returns
+///
+/// The random number.
+pub fn gen_u64<R: Rng + ?Sized>(rng: &mut R) -> u64 {
+    u64::from(rng.gen::<u32>()) << 32 | u64::from(rng.gen::<u32>())
+}
+
+/// Get a random index in a range. The index is drawn from a `u32` for the same reason as in
+/// `gen_u64`, so the range must be smaller than 2^32.
+///
+/// # Arguments
+///
+/// * `rng` - The random number generator.
+/// * `low` - The lowest index.
+/// * `high` - The index after the highest one, which is more than `low`.
+///
+/// # Returns
+///
+/// The random index.
+pub fn gen_range_usize<R: Rng + ?Sized>(rng: &mut R, low: usize, high: usize) -> usize {
+    low + rng.gen::<u32>() as usize % (high - low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::frame::EtherError;
+    use super::super::params;
+
+    /// The fields that a layout may have, which are all of them except the pattern.
+    const FIELDS: &[&str] = &["src_ip", "src_port", "src_mac", "dest_ip", "dest_port", "dest_mac",
+        "seed", "gen", "seq", "prefix_len", "suffix_len", "wrap", "len", "timestamp"];
+
+    /// The number of random layouts to check, which are slow to parse.
+    const LAYOUTS: usize = 100;
+
+    /// The number of random frames to check with each layout.
+    const CASES: usize = 50;
+
+    /// The run seed of the generator of the random layouts and frames.
+    const ROUND_TRIP_SEED: &str = "370";
+
+    /// Get the fields of a frame of the default layout in a byte order.
+    ///
+    /// # Arguments
+    ///
+    /// * `endian` - The byte order.
+    ///
+    /// # Returns
+    ///
+    /// The frame.
+    fn frame(endian: Endian) -> Vec<u8> {
+        let mut frame = vec![];
+        for &(data, bytes) in &[(0x0A00_0002, 4), (4096, 2), (0xAABB_CCDD_EEFF, 6),
+                (0x0A00_0001, 4), (5555, 2), (0x0011_2233_4455, 6), (0x12, 1), (0x34, 1)] {
+            TestCase::append_bytes(&mut frame, data, bytes, endian);
+        }
+        frame
+    }
+
+    /// A frame of the default layout in big endian, which was checked by hand.
+    #[test]
+    fn frame_big_endian() {
+        assert_eq!(frame(Endian::Big), vec![
+            0x0A, 0x00, 0x00, 0x02, 0x10, 0x00, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF,
+            0x0A, 0x00, 0x00, 0x01, 0x15, 0xB3, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
+            0x12, 0x34]);
+    }
+
+    /// A frame of the default layout in little endian, which was checked by hand.
+    #[test]
+    fn frame_little_endian() {
+        assert_eq!(frame(Endian::Little), vec![
+            0x02, 0x00, 0x00, 0x0A, 0x00, 0x10, 0xFF, 0xEE, 0xDD, 0xCC, 0xBB, 0xAA,
+            0x01, 0x00, 0x00, 0x0A, 0xB3, 0x15, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00,
+            0x12, 0x34]);
+    }
+
+    /// The frame of the default layout decodes to the fields that were written, and a frame that
+    /// is cut short or too long is an error rather than a panic.
+    #[test]
+    fn frame_decode() {
+        let params = params::test_params(&[]);
+        let bytes = frame(Endian::Big);
+        let decoded = DecodedFrame::from_bytes(&bytes, &params.frame_layout, Endian::Big)
+            .unwrap();
+        assert_eq!(decoded, DecodedFrame {
+            seed: Some(0x12),
+            gen: Some(0x34),
+            src_ip: Some(0x0A00_0002),
+            src_port: Some(4096),
+            src_mac: Some(0xAABB_CCDD_EEFF),
+            dest_ip: Some(0x0A00_0001),
+            dest_port: Some(5555),
+            dest_mac: Some(0x0011_2233_4455),
+            ..DecodedFrame::default()
+        });
+        for len in &[0, 25, 27] {
+            let mut bytes = bytes.clone();
+            bytes.resize(*len, 0);
+            assert_eq!(DecodedFrame::from_bytes(&bytes, &params.frame_layout, Endian::Big),
+                Err(EtherError::FrameLength { len: *len, expected: 26 }));
+        }
+    }
+
+    /// A generator of 0 is the constant fill, where every byte is the seed, with and without a
+    /// wrap.
+    #[test]
+    fn constant_fill() {
+        for wrap in &[None, Some("100")] {
+            let mut args = vec!["--bytes", "16"];
+            if let Some(wrap) = *wrap {
+                args.extend(&["--ramp-wrap-at", wrap]);
+            }
+            let params = params::test_params(&args);
+            for &seed in &[0, 42, 99] {
+                let test_case = TestCase::with_seed_gen(&params, seed, 0);
+                assert_eq!(test_case.expected().unwrap(), vec![seed as u8; 16], "wrap {:?}", wrap);
+            }
+        }
+    }
+
+    /// Every frame of a random layout decodes back to the test case that was written, with the
+    /// fields that are not in the layout taken from the parameters. The cases come from a seeded
+    /// generator so that a failure can be repeated.
+    #[test]
+    fn frame_round_trip() {
+        let mut rng = params::test_params(&["--run-seed", ROUND_TRIP_SEED]).rng();
+        for l in 0..LAYOUTS {
+            let mut names = FIELDS.to_vec();
+            rng.shuffle(&mut names);
+            names.truncate(rng.gen_range(1, FIELDS.len() + 1));
+            let layout = names.join(",");
+            let endian = if rng.gen() { "big" } else { "little" };
+            let word_mode = rng.gen::<bool>();
+            let mut args = vec!["--frame-layout", &layout, "--frame-endian", endian];
+            if word_mode {
+                args.push("--word-mode");
+            }
+            let params = params::test_params(&args);
+            let fields_len: usize = params.frame_layout.iter().map(|f| f.bytes()).sum();
+            // A field that is not in the layout decodes as the value from the parameters
+            let pick = |name: &str, written: u64, default: u64| if names.contains(&name) {
+                written
+            } else {
+                default
+            };
+            // The MAC addresses only have 48 bits in the frame
+            let mac = |m: u64| m & 0xFFFF_FFFF_FFFF;
+            for c in 0..CASES {
+                // The seed and the indexes find the failing case when the test is run again
+                let case = format!("--run-seed {}, layout {} ({}, {} endian{}), case {}",
+                    ROUND_TRIP_SEED, l, layout, endian, if word_mode { ", word mode" } else { "" },
+                    c);
+                let (seed, gen) = if word_mode {
+                    (rng.gen::<u16>(), rng.gen::<u16>())
+                } else {
+                    (rng.gen::<u8>().into(), rng.gen::<u8>().into())
+                };
+                let mut t = TestCase::with_seed_gen(&params, seed, gen);
+                t.seq = rng.gen();
+                t.src_ip = rng.gen();
+                t.src_port = rng.gen();
+                t.src_mac = gen_u64(&mut rng);
+                t.dest_ip = rng.gen();
+                t.dest_port = rng.gen();
+                t.dest_mac = gen_u64(&mut rng);
+                t.timestamp = rng.gen();
+
+                let mut bytes = vec![];
+                t.write_bytes(&mut bytes);
+                assert_eq!(bytes.len(), frame::frame_len(&params.frame_layout), "{}", case);
+                assert_eq!(bytes.len(), fields_len, "{}", case);
+
+                let decoded = DecodedFrame::from_bytes(&bytes, &params.frame_layout,
+                    params.frame_endian).unwrap();
+                let d = TestCase::from_decoded(&params, &decoded);
+                assert_eq!(d.seed, pick("seed", seed.into(), 0) as u16, "{}", case);
+                assert_eq!(d.gen, pick("gen", gen.into(), 0) as u16, "{}", case);
+                assert_eq!(d.seq, pick("seq", t.seq.into(), 0) as u16, "{}", case);
+                assert_eq!(d.src_ip, pick("src_ip", t.src_ip.into(), params.src_ip.into()) as u32,
+                    "{}", case);
+                assert_eq!(d.src_port, pick("src_port", t.src_port.into(),
+                    params.src_port.into()) as u16, "{}", case);
+                assert_eq!(d.src_mac, pick("src_mac", mac(t.src_mac), params.src_mac), "{}",
+                    case);
+                assert_eq!(d.dest_ip, pick("dest_ip", t.dest_ip.into(), params.dest_ip.into())
+                    as u32, "{}", case);
+                assert_eq!(d.dest_port, pick("dest_port", t.dest_port.into(),
+                    params.dest_port.into()) as u16, "{}", case);
+                assert_eq!(d.dest_mac, pick("dest_mac", mac(t.dest_mac), params.dest_mac), "{}",
+                    case);
+                assert_eq!(d.timestamp, pick("timestamp", t.timestamp.into(), 0) as u32, "{}",
+                    case);
+            }
         }
     }
 }
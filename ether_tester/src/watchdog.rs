@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// The time between checks of the progress, which is also the longest time that stopping the
+/// watchdog takes.
+const POLL_MS: u64 = 100;
+
+/// The progress of a run, which the run updates and the watchdog thread reads.
+#[derive(Default)]
+struct Progress {
+    /// Indicates that the run is over and the thread should stop.
+    stop: AtomicBool,
+
+    /// The number of the last test that finished, or 0 before the first.
+    rep: AtomicU64,
+
+    /// The time that the last test finished in milliseconds from the start of the watchdog.
+    finished_ms: AtomicU64,
+
+    /// The number of tests that failed so far.
+    failed: AtomicU64,
+
+    /// The number of packets that were received so far.
+    packets: AtomicU64
+}
+
+/// A background thread that warns when no test of a run finishes in time, which leaves a trail in
+/// the log when the whole run is stuck, such as on a serial port that stopped accepting data. The
+/// warning is repeated while the run stays stuck, but the run is never stopped.
+pub struct Watchdog {
+    /// The progress of the run.
+    progress: Arc<Progress>,
+
+    /// The time that the watchdog started.
+    start: Instant,
+
+    /// The watchdog thread.
+    thread: Option<JoinHandle<()>>
+}
+
+impl Watchdog {
+    /// Start watching a run.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The time without a finished test after which to warn, and between the
+    ///   warnings that follow.
+    /// * `prefix` - The text that is printed before each warning, which names the device.
+    ///
+    /// # Returns
+    ///
+    /// The watchdog, which stops when it is dropped.
+    pub fn start(interval: Duration, prefix: &str) -> Watchdog {
+        let progress = Arc::new(Progress::default());
+        let start = Instant::now();
+        let thread = {
+            let progress = progress.clone();
+            let prefix = prefix.to_string();
+            thread::spawn(move || watch(&progress, start, interval, &prefix))
+        };
+        Watchdog { progress, start, thread: Some(thread) }
+    }
+
+    /// Record that a test finished.
+    ///
+    /// # Arguments
+    ///
+    /// * `rep` - The number of the test.
+    /// * `failed` - The number of tests that failed so far.
+    /// * `packets` - The number of packets that were received so far.
+    pub fn finished(&self, rep: usize, failed: u64, packets: u64) {
+        let progress = &self.progress;
+        progress.finished_ms.store(self.start.elapsed().as_millis() as u64, Ordering::SeqCst);
+        progress.rep.store(rep as u64, Ordering::SeqCst);
+        progress.failed.store(failed, Ordering::SeqCst);
+        progress.packets.store(packets, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.progress.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Warn whenever no test has finished for the interval until the run is over.
+///
+/// # Arguments
+///
+/// * `progress` - The progress of the run.
+/// * `start` - The time that the watchdog started.
+/// * `interval` - The time without a finished test after which to warn.
+/// * `prefix` - The text that is printed before each warning.
+fn watch(progress: &Progress, start: Instant, interval: Duration, prefix: &str) {
+    let mut warned: Option<Duration> = None;
+    while !progress.stop.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(POLL_MS));
+        let now = start.elapsed();
+        let finished = Duration::from_millis(progress.finished_ms.load(Ordering::SeqCst));
+        // The warnings repeat at the interval until a test finishes
+        let due = match warned {
+            Some(w) if w > finished => w + interval,
+            _ => finished + interval
+        };
+        if now < due {
+            continue;
+        }
+        warned = Some(now);
+        let rep = progress.rep.load(Ordering::SeqCst);
+        let last = if rep == 0 {
+            "no test has finished yet".to_string()
+        } else {
+            format!("test {} finished {:.1} s into the run", rep, finished.as_secs_f64())
+        };
+        super::warn("Watchdog", format!("{}No test has finished for {:.1} s, test {} is still \
+            running, {}, {} failed, {} packets received", prefix, (now - finished).as_secs_f64(),
+            rep + 1, last, progress.failed.load(Ordering::SeqCst),
+            progress.packets.load(Ordering::SeqCst)));
+    }
+}